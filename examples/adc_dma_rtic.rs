@@ -0,0 +1,245 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::spi::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use ssd1306::{prelude::*, Ssd1306};
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::display::{DmaDisplay, QueueCanvas, SharedQueue};
+use robo_core::sample::SampleBuffer;
+
+/// ADC conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 1_000;
+/// Smoothing of the value shown on the display, see `robo_core::filter::Ema`.
+const FILTER_SHIFT: u8 = 4;
+/// Analog supply of the Nucleo board.
+const VDDA_MV: u32 = 3_300;
+const ADC_MAX: u32 = 4_095;
+/// Rendering only queues draws, so the UI can tick faster than the
+/// blocking examples.
+const UI_TICK_MS: u32 = 50;
+
+static DRAW_QUEUE: SharedQueue = SharedQueue::new();
+
+struct AppState {
+    adc_val: u16,
+    mv_val: u16,
+}
+
+pub struct App {
+    state: AppState,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            state: AppState {
+                adc_val: 0,
+                mv_val: 0,
+            },
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    fn update(&mut self, adc: u16, mv: u16) {
+        self.state.adc_val = adc;
+        self.state.mv_val = mv;
+    }
+}
+
+enum Asset {
+    Background = 0,
+    Numbers = 1,
+}
+
+impl From<Asset> for SpriteId {
+    fn from(asset: Asset) -> Self {
+        asset as _
+    }
+}
+
+widget_group! {
+    UI<&AppState>,
+    {
+        bg: GlyphIcon, Asset::Background, 0, Point::zero();
+        raw_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*2), Size::new(16, 16);
+        mv_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*5), Size::new(16, 16);
+    },
+    |widget: &mut UI, state: &AppState| {
+        write!(widget.raw_value, "{: >4}", state.adc_val).ok();
+        write!(widget.mv_value, "{: >4}", state.mv_val).ok();
+    }
+}
+
+type SpiPins = (PA5<DefaultMode>, NoMiso, PA7<DefaultMode>);
+type Display = DmaDisplay<SpiPins, PA9<Output<PushPull>>, PA15<Output<PushPull>>>;
+
+pub const SPRITES: [(FlashSprite, Glyphs); 2] = [
+    (
+        FlashSprite::new(
+            Asset::Background as _,
+            1,
+            Size::new(128, 64),
+            include_bytes!("assets/adc.bin"),
+        ),
+        Glyphs::Sequential(1),
+    ),
+    (
+        FlashSprite::new(
+            Asset::Numbers as _,
+            11,
+            Size::new(16, 16),
+            include_bytes!("assets/numbers16x16.bin"),
+        ),
+        Glyphs::Alphabet(b" 0123456789"),
+    ),
+];
+
+#[rtic::app(device = stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        app: App,
+        samples: SampleBuffer<32>,
+    }
+
+    #[local]
+    struct Local {
+        sampler: TriggeredSampler,
+        display: Display,
+        canvas: SpriteDisplay<QueueCanvas, { SPRITES.len() }>,
+        ui: UI,
+        ui_timer: Timer<stm32::TIM17>,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+
+        // Setup spi i/o
+        let sck = gpio_a.pa5;
+        let mosi = gpio_a.pa7;
+        let mut nss = gpio_a.pa15.into_push_pull_output();
+        nss.set_high().ok();
+        let mut dc = gpio_a.pa9.into_push_pull_output();
+        dc.set_high().ok();
+        let mut rst = gpio_a.pa10.into_push_pull_output();
+        rst.set_high().ok();
+
+        let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
+        ui_timer.start(UI_TICK_MS.millis());
+        ui_timer.listen();
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+
+        let spi = ctx.device.SPI.spi(
+            (sck, NoMiso, mosi),
+            Mode {
+                polarity: Polarity::IdleLow,
+                phase: Phase::CaptureOnFirstTransition,
+            },
+            2.MHz(),
+            &mut rcc,
+        );
+
+        let interface = SPIInterface::new(spi, dc, nss);
+        let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0);
+
+        let pot_input = gpio_a.pa0;
+        adc.calibrate();
+
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), channel_of(&pot_input));
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        // The driver brings the display up, DMA takes over from there
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        display.reset(&mut rst, &mut delay).unwrap();
+        display.init().unwrap();
+        display.clear().unwrap();
+        let (spi, dc, nss) = display.release().release();
+        let display = DmaDisplay::new(spi, dc, nss, &DRAW_QUEUE);
+        let canvas = SpriteDisplay::new(QueueCanvas::new(&DRAW_QUEUE), SPRITES);
+        let ui = UI::new();
+
+        (
+            Shared {
+                app: App::new(),
+                samples: SampleBuffer::new(FILTER_SHIFT),
+            },
+            Local {
+                sampler,
+                display,
+                canvas,
+                ui,
+                ui_timer,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 2, local = [sampler], shared = [samples])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.samples.lock(|samples| samples.push(raw));
+        }
+    }
+
+    #[task(binds = DMA1_CHANNEL1, priority = 2, local = [display])]
+    fn display_transfer(ctx: display_transfer::Context) {
+        ctx.local.display.on_interrupt();
+    }
+
+    #[task(binds = TIM17, local = [ui, ui_timer, canvas], shared = [app, samples])]
+    fn ui_timer_tick(ctx: ui_timer_tick::Context) {
+        let mut app = ctx.shared.app;
+        let mut samples = ctx.shared.samples;
+        let pot_raw = samples.lock(|samples| samples.filtered());
+        let pot_mv = (pot_raw as u32 * VDDA_MV / ADC_MAX) as u16;
+
+        let ui = ctx.local.ui;
+        // A dropped draw leaves stale content, start over with a full frame
+        if DRAW_QUEUE.lock(|queue| queue.take_dropped()) > 0 {
+            ui.invalidate();
+        }
+        app.lock(|app| {
+            app.update(pot_raw, pot_mv);
+            ui.update(app.state());
+        });
+        ui.render(ctx.local.canvas);
+        rtic::pend(stm32::Interrupt::DMA1_CHANNEL1);
+        ctx.local.ui_timer.clear_irq();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -6,18 +6,47 @@ use core::fmt::Write;
 use panic_semihosting as _;
 use stm32c0xx_hal as hal;
 
-use hal::analog::adc::{self, Adc};
+use hal::analog::adc;
 use hal::gpio::*;
 use hal::prelude::*;
 use hal::serial::*;
-use hal::spi::*;
 use hal::stm32;
+use hal::time::*;
 use hal::timer::*;
 
-use ssd1306::{mode, prelude::*, Ssd1306};
-
 use klaptik::*;
 
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::display::{DirtyCanvas, RenderCounters};
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use c031c6_nucleo_robo_rust::ui::{NUMERIC_8X8, NUMERIC_8X8_SPRITE};
+use robo_core::fixed::{Fixed, Unit};
+use robo_core::sample::SampleBuffer;
+use robo_core::telemetry::{Encoder, MAX_FRAME_LEN};
+
+/// ADC conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 1_000;
+/// Smoothing of the value shown on the display, see `robo_core::filter::Ema`.
+const FILTER_SHIFT: u8 = 4;
+/// Analog supply of the Nucleo board.
+const VDDA_MV: u32 = 3_300;
+const ADC_MAX: u32 = 4_095;
+/// Telemetry channel of the display traffic counters, sent once a second:
+/// bytes sent, bytes skipped, frames, frames skipped.
+const RENDER_CHANNEL: u8 = 0x80;
+/// UI ticks per display counters report.
+const RENDER_REPORT_TICKS: u32 = 5;
+
+static RENDER: RenderCounters = RenderCounters::new();
+
+/// Sends one frame, see `robo_core::telemetry` for the format.
+fn send(serial: &mut Serial<stm32::USART2>, frame: &[u8]) {
+    for &byte in frame {
+        while serial.write(byte).is_err() {}
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 struct AppState {
     adc_val: u16,
     mv_val: u16,
@@ -41,9 +70,13 @@ impl App {
         &self.state
     }
 
-    fn update(&mut self, adc: u16, mv: u16) {
+    /// Returns `false` if the state didn't change and the UI can be left
+    /// alone.
+    fn update(&mut self, adc: u16, mv: u16) -> bool {
+        let prev = self.state;
         self.state.adc_val = adc;
         self.state.mv_val = mv;
+        self.state != prev
     }
 }
 
@@ -64,40 +97,17 @@ widget_group! {
         bg: GlyphIcon, Asset::Background, 0, Point::zero();
         raw_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*2), Size::new(16, 16);
         mv_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*5), Size::new(16, 16);
+        volts: Label<6>, NUMERIC_8X8, "      ", Point::new(8*10, 8*7), Size::new(8, 8);
     },
     |widget: &mut UI, state: &AppState| {
         write!(widget.raw_value, "{: >4}", state.adc_val).ok();
         write!(widget.mv_value, "{: >4}", state.mv_val).ok();
+        let volts = Fixed::new(state.mv_val as i32, 3).decimals(2).unit(Unit::Volt);
+        write!(widget.volts, "{}", volts.width(6)).ok();
     }
 }
 
-type SPII = SPIInterface<
-    Spi<hal::pac::SPI, (PA5<DefaultMode>, NoMiso, PA7<DefaultMode>)>,
-    PA9<Output<PushPull>>,
-    PA15<Output<PushPull>>,
->;
-type DisplayDriver = Ssd1306<SPII, DisplaySize128x64, mode::BasicMode>;
-struct DisplayController {
-    canvas: DisplayDriver,
-}
-
-impl DisplayController {
-    fn new(canvas: DisplayDriver) -> Self {
-        Self { canvas }
-    }
-}
-
-impl Canvas for DisplayController {
-    fn draw(&mut self, bounds: Rectangle, bitmap: &[u8]) {
-        let (start, end) = (bounds.start(), bounds.end());
-        self.canvas
-            .set_draw_area((start.x, start.y), (end.x, end.y))
-            .unwrap();
-        self.canvas.draw(bitmap).unwrap();
-    }
-}
-
-pub const SPRITES: [(FlashSprite, Glyphs); 2] = [
+pub const SPRITES: [(FlashSprite, Glyphs); 3] = [
     (
         FlashSprite::new(
             Asset::Background as _,
@@ -116,6 +126,7 @@ pub const SPRITES: [(FlashSprite, Glyphs); 2] = [
         ),
         Glyphs::Alphabet(b" 0123456789"),
     ),
+    NUMERIC_8X8_SPRITE,
 ];
 
 #[rtic::app(device = stm32, peripherals = true)]
@@ -125,32 +136,26 @@ mod app {
     #[shared]
     struct Shared {
         app: App,
+        samples: SampleBuffer<32>,
     }
 
     #[local]
     struct Local {
-        adc: Adc,
-        display: SpriteDisplay<DisplayController, { SPRITES.len() }>,
+        sampler: TriggeredSampler,
+        display: SpriteDisplay<DirtyCanvas<PanelCanvas<Display>>, { SPRITES.len() }>,
         ui: UI,
         ui_timer: Timer<stm32::TIM17>,
-        pot_input: PA0<DefaultMode>,
         serial: Serial<stm32::USART2>,
+        telemetry: Encoder,
+        pot_channel: u8,
+        ticks: u32,
     }
 
     #[init]
     fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
         let mut rcc = ctx.device.RCC.constrain();
         let gpio_a = ctx.device.GPIOA.split(&mut rcc);
-
-        // Setup spi i/o
-        let sck = gpio_a.pa5;
-        let mosi = gpio_a.pa7;
-        let mut nss = gpio_a.pa15.into_push_pull_output();
-        nss.set_high().ok();
-        let mut dc = gpio_a.pa9.into_push_pull_output();
-        dc.set_high().ok();
-        let mut rst = gpio_a.pa10.into_push_pull_output();
-        rst.set_high().ok();
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
 
         let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
         ui_timer.start(200.millis());
@@ -163,65 +168,115 @@ mod app {
         adc.set_oversampling_shift(20);
         adc.oversampling_enable(true);
 
-        let spi = ctx.device.SPI.spi(
-            (sck, NoMiso, mosi),
-            Mode {
-                polarity: Polarity::IdleLow,
-                phase: Phase::CaptureOnFirstTransition,
-            },
-            2.MHz(),
-            &mut rcc,
-        );
-
         let mut serial = ctx
             .device
             .USART2
             .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
             .unwrap();
 
-        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
-
-        let interface = SPIInterface::new(spi, dc, nss);
-        let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0);
-
         let pot_input = gpio_a.pa0;
+        let pot_channel = channel_of(&pot_input);
         adc.calibrate();
 
-        let mut delay = ctx.device.TIM3.delay(&mut rcc);
-        display.reset(&mut rst, &mut delay).unwrap();
-        display.init().unwrap();
-        display.clear().unwrap();
-        let controller = DisplayController::new(display);
-        let display = SpriteDisplay::new(controller, SPRITES);
-        let ui = UI::new();
+        let mut sampler = TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), pot_channel);
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let controller = PanelCanvas::new(display);
+        let mut display = SpriteDisplay::new(DirtyCanvas::new(controller, &RENDER), SPRITES);
+        let mut ui = UI::new();
 
+        // Later frames are only drawn on change
         let app = App::new();
+        ui.update(app.state());
+        ui.render(&mut display);
 
         (
-            Shared { app },
+            Shared {
+                app,
+                samples: SampleBuffer::new(FILTER_SHIFT),
+            },
             Local {
-                adc,
+                sampler,
                 display,
                 ui,
                 ui_timer,
-                pot_input,
                 serial,
+                telemetry: Encoder::new(),
+                pot_channel,
+                ticks: 0,
             },
             init::Monotonics(),
         )
     }
 
-    #[task(binds = TIM17, local = [adc, ui, ui_timer, display, pot_input, serial], shared = [app])]
+    #[task(binds = ADC, priority = 2, local = [sampler], shared = [samples])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.samples.lock(|samples| samples.push(raw));
+        }
+    }
+
+    #[task(binds = TIM17, local = [ui, ui_timer, display, serial, telemetry, pot_channel, ticks], shared = [app, samples])]
     fn ui_timer_tick(ctx: ui_timer_tick::Context) {
         let mut app = ctx.shared.app;
-        let pot_raw: u16 = ctx.local.adc.read(ctx.local.pot_input).unwrap_or(0);
-        let pot_mv: u16 = ctx.local.adc.read_voltage(ctx.local.pot_input).unwrap_or(0);
-        write!(ctx.local.serial, "{} {}\r\n", pot_raw, pot_mv).unwrap();
-        app.lock(|app| {
-            app.update(pot_raw, pot_mv);
-            ctx.local.ui.update(app.state());
+        let mut samples = ctx.shared.samples;
+        let (pot_raw, count) = samples.lock(|samples| (samples.filtered(), samples.count()));
+        let pot_mv = (pot_raw as u32 * VDDA_MV / ADC_MAX) as u16;
+        let timestamp = (count as u64 * 1_000 / SAMPLE_RATE as u64) as u32;
+        let mut frame = [0; MAX_FRAME_LEN];
+        let frame = ctx.local.telemetry.encode(
+            timestamp,
+            *ctx.local.pot_channel,
+            &[pot_raw, pot_mv],
+            &mut frame,
+        );
+        send(ctx.local.serial, frame);
+
+        let changed = app.lock(|app| {
+            let changed = app.update(pot_raw, pot_mv);
+            if changed {
+                ctx.local.ui.update(app.state());
+            }
+            changed
         });
-        ctx.local.ui.render(ctx.local.display);
+        if changed {
+            ctx.local.ui.render(ctx.local.display);
+        }
+        RENDER.update(|stats| stats.frame(changed));
+
+        *ctx.local.ticks += 1;
+        if ctx.local.ticks.is_multiple_of(RENDER_REPORT_TICKS) {
+            let stats = RENDER.take();
+            let counters = [
+                stats.bytes_sent,
+                stats.bytes_skipped,
+                stats.frames,
+                stats.frames_skipped,
+            ]
+            .map(|counter| counter.min(u16::MAX as u32) as u16);
+            let mut frame = [0; MAX_FRAME_LEN];
+            let frame =
+                ctx.local
+                    .telemetry
+                    .encode(timestamp, RENDER_CHANNEL, &counters, &mut frame);
+            send(ctx.local.serial, frame);
+        }
         ctx.local.ui_timer.clear_irq();
     }
 
@@ -0,0 +1,491 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc::{self, Adc};
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, AnalogWatchdog, Watchdog};
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use robo_core::alarm::{AlarmEvent, Level, Monitor, Thresholds};
+
+struct AppState {
+    adc_val: u16,
+    mv_val: u16,
+    alarm: Option<AlarmEvent>,
+}
+
+pub struct App {
+    state: AppState,
+    monitor: Monitor<3>,
+    /// The only channel converted, the watchdogs see no other.
+    channel: u8,
+}
+
+impl App {
+    fn new(channel: u8) -> Self {
+        Self {
+            state: AppState {
+                adc_val: 0,
+                mv_val: 0,
+                alarm: None,
+            },
+            monitor: Monitor::new(),
+            channel,
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    fn update(&mut self, adc: u16, mv: u16) {
+        self.state.adc_val = adc;
+        self.state.mv_val = mv;
+    }
+
+    fn check(&mut self, channel: u8, value: u16) -> Option<AlarmEvent> {
+        let event = self.monitor.update(channel, value)?;
+        if event.is_alarm() {
+            self.state.alarm = Some(event);
+        } else if self.state.alarm.map(|alarm| alarm.channel) == Some(channel) {
+            self.state.alarm = None;
+        }
+        Some(event)
+    }
+}
+
+enum Asset {
+    Background = 0,
+    Numbers = 1,
+    Font = 2,
+}
+
+impl From<Asset> for SpriteId {
+    fn from(asset: Asset) -> Self {
+        asset as _
+    }
+}
+
+widget_group! {
+    UI<&AppState>,
+    {
+        bg: GlyphIcon, Asset::Background, 0, Point::zero();
+        raw_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*2), Size::new(16, 16);
+        mv_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*5), Size::new(16, 16);
+        alert: Label<16>, Asset::Font, "                ", Point::new(0, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut UI, state: &AppState| {
+        write!(widget.raw_value, "{: >4}", state.adc_val).ok();
+        write!(widget.mv_value, "{: >4}", state.mv_val).ok();
+        match state.alarm {
+            Some(AlarmEvent { channel, level: Level::Low, value }) => {
+                write!(widget.alert, "!CH{: <2} LOW {: >5}", channel, value).ok();
+            }
+            Some(AlarmEvent { channel, level: Level::High, value }) => {
+                write!(widget.alert, "!CH{: <2} HIGH{: >5}", channel, value).ok();
+            }
+            _ => {
+                write!(widget.alert, "{: <16}", "").ok();
+            }
+        }
+    }
+}
+
+pub const SPRITES: [(FlashSprite, Glyphs); 3] = [
+    (
+        FlashSprite::new(
+            Asset::Background as _,
+            1,
+            Size::new(128, 64),
+            include_bytes!("assets/adc.bin"),
+        ),
+        Glyphs::Sequential(1),
+    ),
+    (
+        FlashSprite::new(
+            Asset::Numbers as _,
+            11,
+            Size::new(16, 16),
+            include_bytes!("assets/numbers16x16.bin"),
+        ),
+        Glyphs::Alphabet(b" 0123456789"),
+    ),
+    (
+        FlashSprite::new(
+            Asset::Font as _,
+            46,
+            Size::new(8, 8),
+            include_bytes!("assets/font8x8.bin"),
+        ),
+        Glyphs::Alphabet(b" !%-./0123456789:<=>ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+    ),
+];
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        Alarm(AlarmEvent),
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+                EnvSignal::Alarm(event) => self.alarm(shell, event),
+            }
+        }
+
+        fn alarm(&mut self, shell: &mut Shell, event: AlarmEvent) -> EnvResult {
+            match event.level {
+                Level::Low => write!(
+                    shell,
+                    "{0:}ALARM ch{1:} low: {2:}{0:}",
+                    CR, event.channel, event.value
+                )?,
+                Level::High => write!(
+                    shell,
+                    "{0:}ALARM ch{1:} high: {2:}{0:}",
+                    CR, event.channel, event.value
+                )?,
+                Level::Normal => write!(
+                    shell,
+                    "{0:}ch{1:} back to normal: {2:}{0:}",
+                    CR, event.channel, event.value
+                )?,
+            }
+            shell.write_str(SHELL_PROMPT)?;
+            Ok(())
+        }
+
+        fn awd_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            let converted = self.app.lock(|app| app.channel);
+            let channel = match args.next() {
+                None => return self.awd_list(shell),
+                Some(arg) => match arg.parse::<u8>() {
+                    Ok(channel) if channel == converted => channel,
+                    Ok(channel) => {
+                        write!(shell, "{0:}ch{1:} is not converted{0:}", CR, channel)?;
+                        return Ok(());
+                    }
+                    _ => {
+                        write!(shell, "{0:}invalid channel: \"{1:}\"{0:}", CR, arg)?;
+                        return Ok(());
+                    }
+                },
+            };
+            match (args.next(), args.next()) {
+                (Some("off"), None) => match self.app.lock(|app| app.monitor.unwatch(channel)) {
+                    Some(slot) => {
+                        let watchdog = Watchdog::from_slot(slot).unwrap();
+                        (&mut self.adc, &mut self.watchdog)
+                            .lock(|adc, wd| wd.disable(adc, watchdog));
+                        write!(shell, "{0:}ch{1:} unwatched{0:}", CR, channel)?;
+                    }
+                    None => write!(shell, "{0:}ch{1:} is not watched{0:}", CR, channel)?,
+                },
+                (Some(low), Some(high)) => match (low.parse::<u16>(), high.parse::<u16>()) {
+                    (Ok(low), Ok(high)) if low <= high && high <= MAX_THRESHOLD => {
+                        let thresholds = Thresholds::new(low, high);
+                        match self.app.lock(|app| app.monitor.watch(channel, thresholds)) {
+                            Some(slot) => {
+                                let watchdog = Watchdog::from_slot(slot).unwrap();
+                                (&mut self.adc, &mut self.watchdog).lock(|adc, wd| {
+                                    wd.configure(adc, watchdog, channel, thresholds)
+                                });
+                                write!(
+                                    shell,
+                                    "{0:}ch{1:} watched: {2:}..{3:}{0:}",
+                                    CR, channel, low, high
+                                )?;
+                            }
+                            None => write!(shell, "{0:}all watchdogs are busy{0:}", CR)?,
+                        }
+                    }
+                    _ => write!(shell, "{0:}invalid thresholds{0:}", CR)?,
+                },
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn awd_list(&mut self, shell: &mut Shell) -> EnvResult {
+            shell.write_str(CR)?;
+            self.app.lock(|app| {
+                for (channel, thresholds, level) in app.monitor.iter() {
+                    let status = match level {
+                        Level::Normal => "ok",
+                        Level::Low => "LOW",
+                        Level::High => "HIGH",
+                    };
+                    write!(
+                        shell,
+                        "ch{}: {}..{} {}{}",
+                        channel, thresholds.low, thresholds.high, status, CR
+                    )
+                    .ok();
+                }
+            });
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "awd" => self.awd_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            shell.write_str(SHELL_PROMPT)?;
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["awd", "clear", "help"]);
+
+    const MAX_THRESHOLD: u16 = c031c6_nucleo_robo_rust::adc::MAX_THRESHOLD;
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+ADC Watchdog Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tawd                  List watched channels\r\n\
+\tawd <ch> <lo> <hi>   Watch channel, thresholds in raw counts\r\n\
+\tawd <ch> off         Stop watching channel\r\n\
+\tclear                Clear screen\r\n\
+\thelp                 Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        app: App,
+        adc: Adc,
+        watchdog: AnalogWatchdog,
+    }
+
+    #[local]
+    struct Local {
+        display: SpriteDisplay<PanelCanvas<Display>, { SPRITES.len() }>,
+        ui: UI,
+        ui_timer: Timer<stm32::TIM17>,
+        blink_timer: Timer<stm32::TIM14>,
+        pot_input: PA0<DefaultMode>,
+        pot_channel: u8,
+        alarm_led: PA6<Output<PushPull>>,
+        shell: shell::Shell,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        // LD4 shares PA5 with the display clock, alarms drive a LED on PA6
+        let mut alarm_led = gpio_a.pa6.into_push_pull_output();
+        alarm_led.set_low().ok();
+
+        let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
+        ui_timer.start(200.millis());
+        ui_timer.listen();
+
+        let mut blink_timer = ctx.device.TIM14.timer(&mut rcc);
+        blink_timer.start(Hertz::Hz(8).into_duration());
+        blink_timer.listen();
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        let pot_input = gpio_a.pa0;
+        let pot_channel = channel_of(&pot_input);
+        adc.calibrate();
+
+        let mut delay = ctx.device.TIM3.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let controller = PanelCanvas::new(display);
+        let display = SpriteDisplay::new(controller, SPRITES);
+        let ui = UI::new();
+
+        let mut app = App::new(pot_channel);
+
+        // Alarm when the pot leaves 10%..90% of its range
+        let mut watchdog = AnalogWatchdog::new(&mut adc);
+        let thresholds = Thresholds::new(410, 3686);
+        if let Some(slot) = app.monitor.watch(pot_channel, thresholds) {
+            let awd = Watchdog::from_slot(slot).unwrap();
+            watchdog.configure(&mut adc, awd, pot_channel, thresholds);
+        }
+
+        (
+            Shared { app, adc, watchdog },
+            Local {
+                display,
+                ui,
+                ui_timer,
+                blink_timer,
+                pot_input,
+                pot_channel,
+                alarm_led,
+                shell,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = TIM17, local = [ui, ui_timer, display, pot_input, pot_channel], shared = [app, adc])]
+    fn ui_timer_tick(ctx: ui_timer_tick::Context) {
+        let mut app = ctx.shared.app;
+        let (pot_input, pot_channel) = (ctx.local.pot_input, *ctx.local.pot_channel);
+        let (pot_raw, pot_mv) = ctx.shared.adc.lock(|adc| {
+            let pot_raw: u16 = adc.read(pot_input).unwrap_or(0);
+            let pot_mv: u16 = adc.read_voltage(pot_input).unwrap_or(0);
+            (pot_raw, pot_mv)
+        });
+        app.lock(|app| {
+            app.update(pot_raw, pot_mv);
+            // The watchdog only fires out of window, the way back is seen
+            // here. Alarms are left to the watchdog, so none goes unreported.
+            if app.monitor.level(pot_channel) != Some(Level::Normal) {
+                if let Some(event) = app.check(pot_channel, pot_raw) {
+                    env::spawn(shell::EnvSignal::Alarm(event)).ok();
+                }
+            }
+            ctx.local.ui.update(app.state());
+        });
+        ctx.local.ui.render(ctx.local.display);
+        ctx.local.ui_timer.clear_irq();
+    }
+
+    #[task(binds = ADC, shared = [app, watchdog])]
+    fn adc_alarm(ctx: adc_alarm::Context) {
+        (ctx.shared.app, ctx.shared.watchdog).lock(|app, wd| {
+            let value = wd.value();
+            for watchdog in Watchdog::ALL {
+                if !wd.is_pending(watchdog) {
+                    continue;
+                }
+                wd.unpend(watchdog);
+                let channel = app.monitor.channel(watchdog.slot());
+                if let Some(event) = channel.and_then(|channel| app.check(channel, value)) {
+                    env::spawn(shell::EnvSignal::Alarm(event)).ok();
+                }
+            }
+        });
+    }
+
+    #[task(binds = TIM14, shared = [app], local = [alarm_led, blink_timer])]
+    fn blink_tick(mut ctx: blink_tick::Context) {
+        if ctx.shared.app.lock(|app| app.monitor.alarmed()) {
+            ctx.local.alarm_led.toggle().ok();
+        } else {
+            ctx.local.alarm_led.set_low().ok();
+        }
+        ctx.local.blink_timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 2, capacity = 8, local = [shell], shared = [app, adc, watchdog])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
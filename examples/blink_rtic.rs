@@ -5,26 +5,39 @@
 use panic_semihosting as _;
 use stm32c0xx_hal as hal;
 
-use hal::exti::Event;
 use hal::gpio::*;
 use hal::prelude::*;
 use hal::stm32;
 use hal::time::*;
 use hal::timer::*;
 
-#[rtic::app(device = stm32, peripherals = true)]
+use c031c6_nucleo_robo_rust::button::UserButton;
+use robo_core::button::{Config, Event};
+
+/// Blink rates: the default one and the one a double click switches to.
+const SLOW_HZ: u32 = 3;
+const FAST_HZ: u32 = 10;
+/// Held button speeds blinking up to this rate.
+const MAX_HZ: u32 = 20;
+/// Period of the button debouncing and timeouts.
+const BUTTON_TICK_MS: u32 = 5;
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
 mod app {
     use super::*;
 
     #[shared]
     struct Shared {
         timer: Timer<stm32::TIM17>,
+        button: UserButton,
     }
 
     #[local]
     struct Local {
         exti: stm32::EXTI,
         led: PA5<Output<PushPull>>,
+        button_timer: Timer<stm32::TIM16>,
+        rate: u32,
     }
 
     #[init]
@@ -34,17 +47,23 @@ mod app {
         let gpioc = ctx.device.GPIOC.split(&mut rcc);
 
         let mut timer = ctx.device.TIM17.timer(&mut rcc);
-        timer.start(Hertz::Hz(3).into_duration());
+        timer.start(Hertz::Hz(SLOW_HZ).into_duration());
         timer.listen();
 
+        let mut button_timer = ctx.device.TIM16.timer(&mut rcc);
+        button_timer.start(BUTTON_TICK_MS.millis());
+        button_timer.listen();
+
         let mut exti = ctx.device.EXTI;
-        gpioc.pc13.listen(SignalEdge::Falling, &mut exti);
+        let button = UserButton::new(gpioc.pc13, &mut exti, Config::default());
 
         (
-            Shared { timer },
+            Shared { timer, button },
             Local {
                 exti,
                 led: gpioa.pa5.into_push_pull_output(),
+                button_timer,
+                rate: SLOW_HZ,
             },
             init::Monotonics(),
         )
@@ -56,16 +75,42 @@ mod app {
         ctx.shared.timer.lock(|tim| tim.clear_irq());
     }
 
-    #[task(binds = EXTI4_15, shared = [timer], local = [exti])]
-    fn button_click(mut ctx: button_click::Context) {
-        ctx.shared.timer.lock(|tim| {
-            if tim.enabled() {
-                tim.pause();
-            } else {
+    #[task(binds = EXTI4_15, shared = [button], local = [exti])]
+    fn button_edge(mut ctx: button_edge::Context) {
+        let exti = ctx.local.exti;
+        ctx.shared.button.lock(|button| button.on_edge(exti));
+    }
+
+    #[task(binds = TIM16, shared = [button], local = [button_timer])]
+    fn button_tick(mut ctx: button_tick::Context) {
+        if let Some(event) = ctx.shared.button.lock(|button| button.tick(BUTTON_TICK_MS)) {
+            button_event::spawn(event).ok();
+        }
+        ctx.local.button_timer.clear_irq();
+    }
+
+    /// Short click pauses and resumes, double click switches between slow
+    /// and fast, holding speeds up and a long press starts over.
+    #[task(capacity = 4, shared = [timer], local = [rate])]
+    fn button_event(mut ctx: button_event::Context, event: Event) {
+        let rate = ctx.local.rate;
+        ctx.shared.timer.lock(|tim| match event {
+            Event::Short if tim.enabled() => tim.pause(),
+            Event::Short => tim.resume(),
+            Event::Double => {
+                *rate = if *rate == SLOW_HZ { FAST_HZ } else { SLOW_HZ };
+                tim.start(Hertz::Hz(*rate).into_duration());
+            }
+            Event::Long => {
+                *rate = SLOW_HZ;
+                tim.start(Hertz::Hz(*rate).into_duration());
                 tim.resume();
             }
+            Event::Repeat(_) => {
+                *rate = (*rate + 1).min(MAX_HZ);
+                tim.start(Hertz::Hz(*rate).into_duration());
+            }
         });
-        ctx.local.exti.unpend(Event::GPIO13);
     }
 
     #[idle]
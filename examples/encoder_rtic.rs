@@ -0,0 +1,352 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::encoder::RotaryEncoder;
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use c031c6_nucleo_robo_rust::ui::{self, FONT, NUMERIC_16X32};
+use robo_core::button;
+use robo_core::encoder::{self, Event, Turn};
+use robo_core::fixed::Fixed;
+
+/// Period of the encoder polling, the display follows every few ticks.
+const ENCODER_TICK_MS: u32 = 10;
+const RENDER_TICKS: u32 = 10;
+/// The value scrolled by the knob stays in this range.
+const VALUE_MIN: i32 = -9_999;
+const VALUE_MAX: i32 = 9_999;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AppState {
+    value: i32,
+    velocity: i32,
+    pressed: bool,
+}
+
+pub struct App {
+    state: AppState,
+    /// Turns are printed to the shell while set.
+    watching: bool,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            state: AppState {
+                value: 0,
+                velocity: 0,
+                pressed: false,
+            },
+            watching: false,
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    fn turn(&mut self, turn: &Turn) {
+        let value = self.state.value + turn.accelerated as i32;
+        self.state.value = value.clamp(VALUE_MIN, VALUE_MAX);
+        self.state.velocity = turn.velocity as i32;
+    }
+
+    fn zero(&mut self) {
+        self.state.value = 0;
+    }
+}
+
+widget_group! {
+    UI<&AppState>,
+    {
+        bg: GlyphIcon, ui::BLANK, 0, Point::zero();
+        title: Label<16>, FONT, "    ENCODER     ", Point::zero(), Size::new(8, 8);
+        value: Label<8>, NUMERIC_16X32, "       0", Point::new(0, 8*2), Size::new(16, 32);
+        speed: Label<16>, FONT, "                ", Point::new(0, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut UI, state: &AppState| {
+        write!(widget.value, "{}", Fixed::int(state.value).width(8)).ok();
+        let switch = if state.pressed { "PUSH" } else { "    " };
+        write!(widget.speed, "SPEED {: >5} {}", state.velocity, switch).ok();
+    }
+}
+
+pub const SPRITES: [(FlashSprite, Glyphs); 3] =
+    [ui::FONT_SPRITE, ui::BLANK_SPRITE, ui::NUMERIC_16X32_SPRITE];
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        Encoder(Event),
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+                EnvSignal::Encoder(event) => self.watch(shell, event),
+            }
+        }
+
+        fn watch(&mut self, shell: &mut Shell, event: Event) -> EnvResult {
+            if !self.app.lock(|app| app.watching) {
+                return Ok(());
+            }
+            match event {
+                Event::Turn(turn) => write!(
+                    shell,
+                    "turn {:+} x{} {}/s{}",
+                    turn.detents,
+                    turn.accelerated / turn.detents,
+                    turn.velocity,
+                    CR
+                )?,
+                Event::Switch(event) => write!(shell, "switch {:?}{}", event, CR)?,
+            }
+            Ok(())
+        }
+
+        fn enc_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args.trim() {
+                "" => {
+                    let (state, position, velocity) = (&mut self.app, &mut self.rotary)
+                        .lock(|app, rotary| (*app.state(), rotary.position(), rotary.velocity()));
+                    write!(shell, "{0:}value:    {1:}{0:}", CR, state.value)?;
+                    write!(shell, "position: {}{}", position, CR)?;
+                    write!(shell, "speed:    {}/s{}", velocity, CR)?;
+                    write!(shell, "switch:   {}{}", state.pressed, CR)?;
+                }
+                "zero" => {
+                    (&mut self.app, &mut self.rotary).lock(|app, rotary| {
+                        app.zero();
+                        rotary.set_position(0);
+                    });
+                    write!(shell, "{0:}value and position zeroed{0:}", CR)?;
+                }
+                "watch" => {
+                    self.app.lock(|app| app.watching = true);
+                    write!(shell, "{0:}watching turns, Ctrl+C stops{0:}", CR)?;
+                }
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "enc" => self.enc_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            shell.write_str(SHELL_PROMPT)?;
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.app.lock(|app| app.watching = false);
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "enc", "help"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Encoder Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tenc       Print value, position, speed and switch\r\n\
+\tenc zero  Zero value and position\r\n\
+\tenc watch Print turns and clicks, Ctrl+C stops\r\n\
+\tclear     Clear screen\r\n\
+\thelp      Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        app: App,
+        rotary: RotaryEncoder,
+    }
+
+    #[local]
+    struct Local {
+        display: SpriteDisplay<PanelCanvas<Display>, { SPRITES.len() }>,
+        ui: UI,
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+        ticks: u32,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(ENCODER_TICK_MS.millis());
+        timer.listen();
+
+        let rotary = RotaryEncoder::new(
+            ctx.device.TIM1.timer(&mut rcc),
+            (gpio_a.pa8, gpio_b.pb3, gpio_b.pb4),
+            encoder::Config {
+                tick_ms: ENCODER_TICK_MS,
+                ..Default::default()
+            },
+            button::Config::default(),
+        );
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let display = SpriteDisplay::new(PanelCanvas::new(display), SPRITES);
+
+        (
+            Shared {
+                app: App::new(),
+                rotary,
+            },
+            Local {
+                display,
+                ui: UI::new(),
+                timer,
+                shell,
+                ticks: 0,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = TIM17, local = [ui, display, timer, ticks], shared = [app, rotary])]
+    fn encoder_tick(ctx: encoder_tick::Context) {
+        let (mut app, mut rotary) = (ctx.shared.app, ctx.shared.rotary);
+        let (event, pressed) = rotary.lock(|rotary| (rotary.tick(), rotary.is_pressed()));
+        if let Some(event) = event {
+            encoder_event::spawn(event).ok();
+        }
+
+        let local = ctx.local;
+        *local.ticks += 1;
+        if local.ticks.is_multiple_of(RENDER_TICKS) {
+            app.lock(|app| {
+                app.state.pressed = pressed;
+                if event.is_none() {
+                    // Shows the speed dropping once the knob stops
+                    app.state.velocity = rotary.lock(|rotary| rotary.velocity());
+                }
+                local.ui.update(app.state());
+            });
+            local.ui.render(local.display);
+        }
+        local.timer.clear_irq();
+    }
+
+    /// Turns scroll the value, a click zeroes it.
+    #[task(capacity = 8, shared = [app])]
+    fn encoder_event(mut ctx: encoder_event::Context, event: Event) {
+        ctx.shared.app.lock(|app| match event {
+            Event::Turn(turn) => app.turn(&turn),
+            Event::Switch(button::Event::Short) => app.zero(),
+            Event::Switch(_) => {}
+        });
+        env::spawn(shell::EnvSignal::Encoder(event)).ok();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell], shared = [app, rotary])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
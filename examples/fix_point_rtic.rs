@@ -16,6 +16,32 @@ use defmt::info;
 use defmt_rtt as _;
 
 use dyadic::DF;
+use robo_core::pid::{Config, Gains, Pid};
+use robo_core::q16::Q16;
+
+/// Rate of the timer tick, which also runs the PID demo.
+const TICK_HZ: u32 = 3;
+/// Ticks between setpoint steps of the demo.
+const STEP_TICKS: u32 = 30;
+
+/// `value` in thousandths, for logging.
+fn milli(value: Q16) -> i32 {
+    (value * Q16::from_int(1_000)).to_int()
+}
+
+/// First order lag simulated in `Q16`: gain 2, time constant 2 s.
+pub struct Plant {
+    output: Q16,
+}
+
+impl Plant {
+    fn step(&mut self, input: Q16) -> Q16 {
+        let gain = Q16::from_int(2);
+        let dt_over_tau = Q16::from_ratio(1, 2 * TICK_HZ as i32);
+        self.output += (gain * input - self.output) * dt_over_tau;
+        self.output
+    }
+}
 
 #[rtic::app(device = stm32, peripherals = true)]
 mod app {
@@ -30,6 +56,9 @@ mod app {
     struct Local {
         exti: stm32::EXTI,
         led: PA5<Output<PushPull>>,
+        pid: Pid,
+        plant: Plant,
+        ticks: u32,
     }
 
     #[init]
@@ -39,7 +68,7 @@ mod app {
         let gpioc = ctx.device.GPIOC.split(&mut rcc);
 
         let mut timer = ctx.device.TIM17.timer(&mut rcc);
-        timer.start(Hertz::Hz(3).into_duration());
+        timer.start(Hertz::Hz(TICK_HZ).into_duration());
         timer.listen();
 
         let mut exti = ctx.device.EXTI;
@@ -61,19 +90,51 @@ mod app {
         let c = a + b;
         info!("c is {}", (c * 1000.into()).floor());
 
+        let pid = Pid::new(Config {
+            gains: Gains {
+                kp: Q16::from_ratio(1, 2),
+                ki: Q16::from_ratio(1, 2),
+                kd: Q16::ZERO,
+            },
+            sample_ms: 1_000 / TICK_HZ,
+            out_min: Q16::ZERO,
+            out_max: Q16::from_int(10),
+        });
+
         (
             Shared { timer },
             Local {
                 exti,
                 led: gpioa.pa5.into_push_pull_output(),
+                pid,
+                plant: Plant { output: Q16::ZERO },
+                ticks: 0,
             },
             init::Monotonics(),
         )
     }
 
-    #[task(binds = TIM17, shared = [timer], local = [led])]
+    #[task(binds = TIM17, shared = [timer], local = [led, pid, plant, ticks])]
     fn timer_tick(mut ctx: timer_tick::Context) {
         ctx.local.led.toggle().ok();
+
+        // Steps the setpoint between 4 and 12 and follows it with the PID
+        *ctx.local.ticks += 1;
+        let setpoint = if (*ctx.local.ticks / STEP_TICKS).is_multiple_of(2) {
+            Q16::from_int(4)
+        } else {
+            Q16::from_int(12)
+        };
+        let measurement = ctx.local.plant.output;
+        let output = ctx.local.pid.update(setpoint, measurement);
+        ctx.local.plant.step(output);
+        info!(
+            "setpoint {} measurement {} output {} (thousandths)",
+            milli(setpoint),
+            milli(measurement),
+            milli(output)
+        );
+
         ctx.shared.timer.lock(|tim| tim.clear_irq());
     }
 
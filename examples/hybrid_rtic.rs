@@ -0,0 +1,217 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use embedded_graphics::geometry::{Point as GfxPoint, Size as GfxSize};
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Drawable, OriginDimensions, Primitive};
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle as GfxRectangle};
+use embedded_graphics::text::{Baseline, Text};
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::display::{DirtyCanvas, HybridCanvas, HybridTarget, RenderCounters};
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use robo_core::page::TextLine;
+use robo_core::sample::SampleBuffer;
+
+/// ADC conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 1_000;
+/// Smoothing of the value shown on the display, see `robo_core::filter::Ema`.
+const FILTER_SHIFT: u8 = 4;
+/// Analog supply of the Nucleo board.
+const VDDA_MV: u32 = 3_300;
+const ADC_MAX: u32 = 4_095;
+const UI_TICK_MS: u32 = 100;
+
+/// Level bar below the text.
+const BAR_TOP: i32 = 48;
+const BAR_HEIGHT: u32 = 12;
+
+static RENDER: RenderCounters = RenderCounters::new();
+
+struct AppState {
+    adc_val: u16,
+    mv_val: u16,
+}
+
+enum Asset {
+    Numbers = 0,
+}
+
+impl From<Asset> for SpriteId {
+    fn from(asset: Asset) -> Self {
+        asset as _
+    }
+}
+
+// Fixed art as sprites, the rest is drawn with embedded-graphics
+widget_group! {
+    UI<&AppState>,
+    {
+        raw_value: Label<4>, Asset::Numbers, "0000", Point::new(8*8, 0), Size::new(16, 16);
+    },
+    |widget: &mut UI, state: &AppState| {
+        write!(widget.raw_value, "{: >4}", state.adc_val).ok();
+    }
+}
+
+/// Draws the dynamic part of the frame on top of the sprites.
+fn draw_graphics(target: &mut HybridTarget, state: &AppState) {
+    let text_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+    Text::with_baseline("POT RAW", GfxPoint::new(0, 4), text_style, Baseline::Top)
+        .draw(target)
+        .ok();
+
+    let percent = state.adc_val as u32 * 100 / ADC_MAX;
+    let mut line = TextLine::<21>::new();
+    write!(line, "{} mV  {}%", state.mv_val, percent).ok();
+    let line = core::str::from_utf8(line.as_bytes()).unwrap_or("");
+    Text::with_baseline(line, GfxPoint::new(0, 28), text_style, Baseline::Top)
+        .draw(target)
+        .ok();
+
+    let width = target.size().width;
+    GfxRectangle::new(GfxPoint::new(0, BAR_TOP), GfxSize::new(width, BAR_HEIGHT))
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+        .draw(target)
+        .ok();
+    let fill = (width - 4) * state.adc_val as u32 / ADC_MAX;
+    GfxRectangle::new(
+        GfxPoint::new(2, BAR_TOP + 2),
+        GfxSize::new(fill, BAR_HEIGHT - 4),
+    )
+    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+    .draw(target)
+    .ok();
+}
+
+pub const SPRITES: [(FlashSprite, Glyphs); 1] = [(
+    FlashSprite::new(
+        Asset::Numbers as _,
+        11,
+        Size::new(16, 16),
+        include_bytes!("assets/numbers16x16.bin"),
+    ),
+    Glyphs::Alphabet(b" 0123456789"),
+)];
+
+#[rtic::app(device = stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        samples: SampleBuffer<32>,
+    }
+
+    #[local]
+    struct Local {
+        sampler: TriggeredSampler,
+        display: HybridCanvas<DirtyCanvas<PanelCanvas<Display>>>,
+        ui: UI,
+        ui_timer: Timer<stm32::TIM17>,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
+        ui_timer.start(UI_TICK_MS.millis());
+        ui_timer.listen();
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+
+        let pot_input = gpio_a.pa0;
+        adc.calibrate();
+
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), channel_of(&pot_input));
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let controller = PanelCanvas::new(display);
+        let display = HybridCanvas::new(DirtyCanvas::new(controller, &RENDER));
+
+        (
+            Shared {
+                samples: SampleBuffer::new(FILTER_SHIFT),
+            },
+            Local {
+                sampler,
+                display,
+                ui: UI::new(),
+                ui_timer,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 2, local = [sampler], shared = [samples])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.samples.lock(|samples| samples.push(raw));
+        }
+    }
+
+    #[task(binds = TIM17, local = [ui, ui_timer, display], shared = [samples])]
+    fn ui_timer_tick(mut ctx: ui_timer_tick::Context) {
+        let pot_raw = ctx.shared.samples.lock(|samples| samples.filtered());
+        let state = AppState {
+            adc_val: pot_raw,
+            mv_val: (pot_raw as u32 * VDDA_MV / ADC_MAX) as u16,
+        };
+
+        let ui = ctx.local.ui;
+        ui.update(&state);
+        ctx.local.display.render(|target| {
+            ui.invalidate();
+            ui.render(&mut SpriteDisplay::new(&mut *target, SPRITES));
+            draw_graphics(target, &state);
+        });
+        ctx.local.ui_timer.clear_irq();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,486 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::button::UserButton;
+use c031c6_nucleo_robo_rust::encoder::RotaryEncoder;
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use c031c6_nucleo_robo_rust::ui::{self, FONT};
+use robo_core::button;
+use robo_core::encoder;
+use robo_core::fixed::Fixed;
+use robo_core::input::{InputEvent, InputQueue, PotMotion, Stamped};
+use robo_core::sample::SampleBuffer;
+
+/// ADC conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 1_000;
+const FILTER_SHIFT: u8 = 4;
+/// Pot moves smaller than this are noise, in raw ADC steps.
+const POT_THRESHOLD: u16 = 40;
+/// Period of the input polling, the display follows every few ticks.
+const INPUT_TICK_MS: u32 = 10;
+const RENDER_TICKS: u32 = 10;
+const QUEUE_LEN: usize = 16;
+
+/// Commands the shell turns into input events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Set(i32),
+    Zero,
+}
+
+pub type Queue = InputQueue<Command, QUEUE_LEN>;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AppState {
+    value: i32,
+    pot: u16,
+    last: Option<Stamped<Command>>,
+    dropped: u32,
+}
+
+pub struct App {
+    state: AppState,
+    /// Events are printed to the shell while set.
+    logging: bool,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            state: AppState {
+                value: 0,
+                pot: 0,
+                last: None,
+                dropped: 0,
+            },
+            logging: false,
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    /// All input ends up here.
+    fn handle(&mut self, stamped: Stamped<Command>) {
+        let state = &mut self.state;
+        match stamped.event {
+            InputEvent::Button(button::Event::Short) => state.value += 1,
+            InputEvent::Button(button::Event::Double) => state.value -= 1,
+            InputEvent::Button(button::Event::Long) => state.value = 0,
+            InputEvent::Button(button::Event::Repeat(_)) => state.value += 10,
+            InputEvent::Encoder(encoder::Event::Turn(turn)) => {
+                state.value += turn.accelerated as i32
+            }
+            InputEvent::Encoder(encoder::Event::Switch(_)) => state.value = 0,
+            InputEvent::Pot(position) => state.pot = position,
+            InputEvent::Shell(Command::Set(value)) => state.value = value,
+            InputEvent::Shell(Command::Zero) => state.value = 0,
+        }
+        state.value = state.value.clamp(-9_999, 9_999);
+        state.last = Some(stamped);
+    }
+}
+
+/// Writes an event the way the display and the shell show it.
+fn describe(out: &mut impl Write, event: &InputEvent<Command>) -> core::fmt::Result {
+    match event {
+        InputEvent::Button(button::Event::Short) => out.write_str("BTN SHORT"),
+        InputEvent::Button(button::Event::Double) => out.write_str("BTN DOUBLE"),
+        InputEvent::Button(button::Event::Long) => out.write_str("BTN LONG"),
+        InputEvent::Button(button::Event::Repeat(n)) => write!(out, "BTN REPEAT {}", n),
+        InputEvent::Encoder(encoder::Event::Turn(turn)) => {
+            write!(out, "ENC {:+}", turn.accelerated)
+        }
+        InputEvent::Encoder(encoder::Event::Switch(_)) => out.write_str("ENC PUSH"),
+        InputEvent::Pot(position) => write!(out, "POT {}", position),
+        InputEvent::Shell(Command::Set(value)) => write!(out, "CMD SET {}", value),
+        InputEvent::Shell(Command::Zero) => out.write_str("CMD ZERO"),
+    }
+}
+
+widget_group! {
+    UI<&AppState>,
+    {
+        bg: GlyphIcon, ui::BLANK, 0, Point::zero();
+        title: Label<16>, FONT, "     INPUT      ", Point::zero(), Size::new(8, 8);
+        value: Label<16>, FONT, "                ", Point::new(0, 8*2), Size::new(8, 8);
+        pot: Label<16>, FONT, "                ", Point::new(0, 8*3), Size::new(8, 8);
+        event: Label<16>, FONT, "                ", Point::new(0, 8*5), Size::new(8, 8);
+        at: Label<16>, FONT, "                ", Point::new(0, 8*6), Size::new(8, 8);
+        dropped: Label<16>, FONT, "                ", Point::new(0, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut UI, state: &AppState| {
+        write!(widget.value, "VALUE {: >10}", state.value).ok();
+        write!(widget.pot, "POT   {: >10}", state.pot).ok();
+        if let Some(last) = state.last {
+            // Padded to the full line, so a short event clears a longer one
+            let mut line = robo_core::page::TextLine::<16>::new();
+            describe(&mut line, &last.event).ok();
+            widget.event.write_str(core::str::from_utf8(line.as_bytes()).unwrap_or("")).ok();
+            let secs = Fixed::new((last.at / 10) as i32, 2);
+            write!(widget.at, "AT    {}S", secs.width(9)).ok();
+        }
+        write!(widget.dropped, "DROPPED {: >8}", state.dropped).ok();
+    }
+}
+
+pub const SPRITES: [(FlashSprite, Glyphs); 2] = [ui::FONT_SPRITE, ui::BLANK_SPRITE];
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<6>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        Log(Stamped<Command>),
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+                EnvSignal::Log(stamped) => self.log(shell, stamped),
+            }
+        }
+
+        fn log(&mut self, shell: &mut Shell, stamped: Stamped<Command>) -> EnvResult {
+            if self.app.lock(|app| app.logging) {
+                write!(shell, "{: >8} ", stamped.at)?;
+                describe(shell, &stamped.event)?;
+                shell.write_str(CR)?;
+            }
+            Ok(())
+        }
+
+        /// Commands with an effect on the application go through the queue.
+        fn send(&mut self, shell: &mut Shell, command: Command) -> EnvResult {
+            let queued = self
+                .queue
+                .lock(|queue| queue.push(InputEvent::Shell(command)));
+            app::input::spawn().ok();
+            match queued {
+                true => write!(shell, "{0:}ok{0:}", CR)?,
+                false => write!(shell, "{0:}ok, queue overflow{0:}", CR)?,
+            }
+            Ok(())
+        }
+
+        fn set_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args.trim().parse::<i32>() {
+                Ok(value) => self.send(shell, Command::Set(value))?,
+                Err(_) => write!(shell, "{0:}usage: set <value>{0:}", CR)?,
+            }
+            Ok(())
+        }
+
+        fn queue_cmd(&mut self, shell: &mut Shell) -> EnvResult {
+            let (len, now, stats) = self
+                .queue
+                .lock(|queue| (queue.len(), queue.now(), queue.stats()));
+            write!(shell, "{0:}waiting:    {1:}/{2:}{0:}", CR, len, QUEUE_LEN)?;
+            write!(shell, "pushed:     {}{}", stats.pushed, CR)?;
+            write!(shell, "dropped:    {}{}", stats.dropped, CR)?;
+            write!(shell, "high water: {}{}", stats.high_water, CR)?;
+            write!(shell, "clock:      {} ms{}", now, CR)?;
+            Ok(())
+        }
+
+        fn log_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args.trim() {
+                mode @ ("on" | "off") => {
+                    self.app.lock(|app| app.logging = mode == "on");
+                    shell.write_str(CR)?;
+                }
+                _ => write!(shell, "{0:}usage: log <on|off>{0:}", CR)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "set" => self.set_cmd(shell, args)?,
+                "zero" => self.send(shell, Command::Zero)?,
+                "queue" => self.queue_cmd(shell)?,
+                "log" => self.log_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            shell.write_str(SHELL_PROMPT)?;
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.app.lock(|app| app.logging = false);
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete =
+        StaticAutocomplete(["clear", "help", "log", "queue", "set", "zero"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Input Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tset <value>    Set the value\r\n\
+\tzero           Zero the value\r\n\
+\tqueue          Print input queue statistics\r\n\
+\tlog <on|off>   Print input events, Ctrl+C stops\r\n\
+\tclear          Clear screen\r\n\
+\thelp           Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        app: App,
+        queue: Queue,
+        samples: SampleBuffer<32>,
+        button: UserButton,
+    }
+
+    #[local]
+    struct Local {
+        exti: stm32::EXTI,
+        sampler: TriggeredSampler,
+        rotary: RotaryEncoder,
+        pot: PotMotion,
+        display: SpriteDisplay<PanelCanvas<Display>, { SPRITES.len() }>,
+        ui: UI,
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+        ticks: u32,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+        let gpio_c = ctx.device.GPIOC.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(INPUT_TICK_MS.millis());
+        timer.listen();
+
+        let mut exti = ctx.device.EXTI;
+        let button = UserButton::new(gpio_c.pc13, &mut exti, button::Config::default());
+        let rotary = RotaryEncoder::new(
+            ctx.device.TIM1.timer(&mut rcc),
+            (gpio_a.pa8, gpio_b.pb3, gpio_b.pb4),
+            encoder::Config {
+                tick_ms: INPUT_TICK_MS,
+                ..Default::default()
+            },
+            button::Config::default(),
+        );
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+        adc.calibrate();
+        let pot_input = gpio_a.pa0;
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), channel_of(&pot_input));
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let display = SpriteDisplay::new(PanelCanvas::new(display), SPRITES);
+
+        (
+            Shared {
+                app: App::new(),
+                queue: Queue::new(),
+                samples: SampleBuffer::new(FILTER_SHIFT),
+                button,
+            },
+            Local {
+                exti,
+                sampler,
+                rotary,
+                pot: PotMotion::new(POT_THRESHOLD),
+                display,
+                ui: UI::new(),
+                timer,
+                shell,
+                ticks: 0,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 3, local = [sampler], shared = [samples])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.samples.lock(|samples| samples.push(raw));
+        }
+    }
+
+    #[task(binds = EXTI4_15, priority = 3, local = [exti], shared = [button])]
+    fn button_edge(mut ctx: button_edge::Context) {
+        let exti = ctx.local.exti;
+        ctx.shared.button.lock(|button| button.on_edge(exti));
+    }
+
+    /// Polls every input source into the queue.
+    #[task(binds = TIM17, priority = 2, local = [rotary, pot, timer], shared = [queue, samples, button])]
+    fn input_tick(ctx: input_tick::Context) {
+        let (mut queue, mut samples, mut button) =
+            (ctx.shared.queue, ctx.shared.samples, ctx.shared.button);
+        let local = ctx.local;
+        let clicked = button.lock(|button| button.tick(INPUT_TICK_MS));
+        let turned = local.rotary.tick();
+        let moved = local.pot.update(samples.lock(|samples| samples.filtered()));
+
+        let pending = queue.lock(|queue| {
+            queue.tick(INPUT_TICK_MS);
+            if let Some(event) = clicked {
+                queue.push(InputEvent::Button(event));
+            }
+            if let Some(event) = turned {
+                queue.push(InputEvent::Encoder(event));
+            }
+            if let Some(position) = moved {
+                queue.push(InputEvent::Pot(position));
+            }
+            !queue.is_empty()
+        });
+        if pending {
+            input::spawn().ok();
+        }
+        render::spawn().ok();
+        local.timer.clear_irq();
+    }
+
+    /// The one place reacting to input.
+    #[task(priority = 1, shared = [app, queue])]
+    fn input(ctx: input::Context) {
+        let (mut app, mut queue) = (ctx.shared.app, ctx.shared.queue);
+        while let Some(stamped) = queue.lock(|queue| queue.pop()) {
+            let logging = app.lock(|app| {
+                app.handle(stamped);
+                app.logging
+            });
+            if logging {
+                env::spawn(shell::EnvSignal::Log(stamped)).ok();
+            }
+        }
+        let dropped = queue.lock(|queue| queue.stats().dropped);
+        app.lock(|app| app.state.dropped = dropped);
+    }
+
+    #[task(priority = 1, local = [ui, display, ticks], shared = [app])]
+    fn render(mut ctx: render::Context) {
+        let local = ctx.local;
+        *local.ticks += 1;
+        if !local.ticks.is_multiple_of(RENDER_TICKS) {
+            return;
+        }
+        ctx.shared.app.lock(|app| local.ui.update(app.state()));
+        local.ui.render(local.display);
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell], shared = [app, queue])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
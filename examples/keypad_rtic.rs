@@ -0,0 +1,259 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::timer::*;
+
+use c031c6_nucleo_robo_rust::keypad;
+use robo_core::keypad::{self as matrix, KeyEvent, Matrix, LAYOUT_4X4};
+
+/// Period of the matrix scan, keys change after `debounce_scans` of them.
+const SCAN_TICK_MS: u32 = 5;
+
+/// 4x4 keypad with rows on PB0, PB1, PB2, PB5 and columns on PB6, PB7,
+/// PC6, PC7.
+pub type Keypad = keypad::Keypad<Pin<Output<OpenDrain>>, Pin<Input<PullUp>>, 4, 4>;
+
+/// Writes the labels of the keys down, then the state of the scan.
+fn write_keys(out: &mut impl Write, matrix: &Matrix<4, 4>) -> core::fmt::Result {
+    out.write_str("keys:")?;
+    for key in matrix.keys() {
+        let label = LAYOUT_4X4[key.row as usize * 4 + key.col as usize];
+        write!(out, " {}", label as char)?;
+    }
+    let blocked = matrix.blocked();
+    if blocked != 0 {
+        out.write_str("  blocked:")?;
+        for (index, label) in LAYOUT_4X4.iter().enumerate() {
+            if blocked & (1 << index) != 0 {
+                write!(out, " {}", *label as char)?;
+            }
+        }
+    }
+    if matrix.is_ghosting() {
+        out.write_str("  ghosting")?;
+    }
+    Ok(())
+}
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        Key(KeyEvent),
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+                EnvSignal::Key(_) => self.monitor(shell),
+            }
+        }
+
+        fn monitor(&mut self, shell: &mut Shell) -> EnvResult {
+            if self.monitoring.lock(|monitoring| *monitoring) {
+                // Overwrites the line, so the live state stays in place
+                shell.write_str("\r\x1b[K")?;
+                self.keypad
+                    .lock(|keypad| write_keys(shell, keypad.matrix()))?;
+            }
+            Ok(())
+        }
+
+        fn keys_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            match (args.next(), args.next(), args.next()) {
+                (None, _, _) => {
+                    self.monitoring.lock(|monitoring| *monitoring = true);
+                    write!(shell, "{0:}monitoring keys, Ctrl+C stops{0:}", CR)?;
+                    self.monitor(shell)?;
+                }
+                (Some("config"), Some(debounce), Some(rollover)) => {
+                    match (debounce.parse::<u8>(), rollover.parse::<u8>()) {
+                        (Ok(debounce_scans @ 1..), Ok(rollover @ 1..=16)) => {
+                            let config = matrix::Config {
+                                debounce_scans,
+                                rollover,
+                            };
+                            self.keypad.lock(|keypad| keypad.set_config(config));
+                            write!(shell, "{0:}ok{0:}", CR)?;
+                        }
+                        _ => write!(shell, "{0:}debounce 1.., rollover 1..16{0:}", CR)?,
+                    }
+                }
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "keys" => self.keys_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            if !self.monitoring.lock(|monitoring| *monitoring) {
+                shell.write_str(SHELL_PROMPT)?;
+            }
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.monitoring.lock(|monitoring| *monitoring = false);
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "help", "keys"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Keypad Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tkeys                      Monitor the keys down, Ctrl+C stops\r\n\
+\tkeys config <scans> <n>   Set debounce scans and rollover limit\r\n\
+\tclear                     Clear screen\r\n\
+\thelp                      Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        keypad: Keypad,
+        monitoring: bool,
+    }
+
+    #[local]
+    struct Local {
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+        let gpio_c = ctx.device.GPIOC.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(SCAN_TICK_MS.millis());
+        timer.listen();
+
+        let keypad = Keypad::new(
+            [
+                gpio_b.pb0.into_open_drain_output().downgrade(),
+                gpio_b.pb1.into_open_drain_output().downgrade(),
+                gpio_b.pb2.into_open_drain_output().downgrade(),
+                gpio_b.pb5.into_open_drain_output().downgrade(),
+            ],
+            [
+                gpio_b.pb6.into_pull_up_input().downgrade(),
+                gpio_b.pb7.into_pull_up_input().downgrade(),
+                gpio_c.pc6.into_pull_up_input().downgrade(),
+                gpio_c.pc7.into_pull_up_input().downgrade(),
+            ],
+            matrix::Config::default(),
+        );
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        (
+            Shared {
+                keypad,
+                monitoring: false,
+            },
+            Local { timer, shell },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = TIM17, priority = 2, local = [timer], shared = [keypad])]
+    fn scan_tick(mut ctx: scan_tick::Context) {
+        ctx.shared.keypad.lock(|keypad| {
+            keypad.scan(|event| {
+                env::spawn(shell::EnvSignal::Key(event)).ok();
+            })
+        });
+        ctx.local.timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell], shared = [keypad, monitoring])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,510 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::line::LineSensors;
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use c031c6_nucleo_robo_rust::ui::FONT_ALPHABET;
+use robo_core::line::{self, LineArray, Polarity, Position, Side, MAX_SENSORS, SCALE};
+use robo_core::page::{self, TextLine, PAGE_HEIGHT};
+
+/// Scans of the whole array per second, paced by TIM3.
+const SCAN_RATE: u32 = 200;
+/// Period of the display updates and watch output.
+const UI_TICK_MS: u32 = 50;
+/// Sensor bars, from the top of page 1 down to the bottom of page 4.
+const BAR_PAGES: u8 = 4;
+/// Display page of the line position marker.
+const MARKER_PAGE: u8 = 5;
+const STATUS_PAGE: u8 = 7;
+
+const FONT: &[u8] = include_bytes!("assets/font8x8.bin");
+
+/// Where the periodic reports go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Off,
+    Watch,
+}
+
+/// Copy of the array for the display, taken once per UI tick.
+struct Snapshot {
+    values: [u16; MAX_SENSORS],
+    sensors: usize,
+    threshold: u16,
+    edge: i16,
+    position: Option<Position>,
+    calibrating: bool,
+    calibrated: bool,
+}
+
+impl Snapshot {
+    fn take(line: &LineArray, calibrating: bool) -> Self {
+        let mut values = [0; MAX_SENSORS];
+        values[..line.sensors()].copy_from_slice(line.values());
+        Self {
+            values,
+            sensors: line.sensors(),
+            threshold: line.config().threshold,
+            edge: line.edge(),
+            position: line.position(),
+            calibrating,
+            calibrated: line.is_calibrated(),
+        }
+    }
+
+    /// Renders one page of the sensor bars, counted from their top: a
+    /// column per sensor filled from the bottom up to its reading, and a
+    /// dotted line at the threshold.
+    fn render_bars(&self, page: u8, buf: &mut [u8]) {
+        buf.fill(0);
+        let width = buf.len() / self.sensors;
+        let bottom = BAR_PAGES as u32 * PAGE_HEIGHT as u32 - 1;
+        let row = |value: u16| bottom - value.min(SCALE) as u32 * bottom / SCALE as u32;
+        let top = page as u32 * PAGE_HEIGHT as u32;
+        let threshold = row(self.threshold);
+
+        for (sensor, &value) in self.values[..self.sensors].iter().enumerate() {
+            let from = row(value);
+            // Keeps a gap between neighbouring bars
+            let columns = sensor * width + 1..(sensor + 1) * width - 1;
+            for col in &mut buf[columns] {
+                for bit in 0..PAGE_HEIGHT as u32 {
+                    if (from..=bottom).contains(&(top + bit)) {
+                        *col |= 1 << bit;
+                    }
+                }
+            }
+        }
+        if (top..top + PAGE_HEIGHT as u32).contains(&threshold) {
+            for col in buf.iter_mut().step_by(4) {
+                *col ^= 1 << (threshold - top);
+            }
+        }
+    }
+
+    /// A caret under the line position, arrows towards a lost line.
+    fn render_marker(&self, buf: &mut [u8]) {
+        buf.fill(0);
+        match self.position {
+            Some(Position::Line(position)) => {
+                let span = (buf.len() - 1) as i32;
+                let edge = self.edge.max(1) as i32;
+                let x = (position as i32 + edge) * span / (2 * edge);
+                for (offset, bits) in [
+                    (-2, 0b1000),
+                    (-1, 0b1100),
+                    (0, 0b1110),
+                    (1, 0b1100),
+                    (2, 0b1000),
+                ] {
+                    if let Some(col) = buf.get_mut((x + offset).clamp(0, span) as usize) {
+                        *col |= bits << 2;
+                    }
+                }
+            }
+            Some(Position::Lost(Side::Left)) => {
+                page::text(FONT, FONT_ALPHABET, b"<<", &mut buf[..16]);
+            }
+            Some(Position::Lost(Side::Right)) => {
+                let len = buf.len();
+                page::text(FONT, FONT_ALPHABET, b">>", &mut buf[len - 16..]);
+            }
+            Some(Position::Lost(Side::Center)) | None => {}
+        }
+    }
+
+    fn status(&self) -> TextLine<16> {
+        let mut status = TextLine::new();
+        match self.position {
+            _ if self.calibrating => write!(status, "CALIBRATING"),
+            _ if !self.calibrated => write!(status, "NOT CALIBRATED"),
+            Some(Position::Line(position)) => write!(status, "POS {: >6}", position),
+            Some(Position::Lost(side)) => write!(status, "LOST {}", side_name(side)),
+            None => Ok(()),
+        }
+        .ok();
+        status
+    }
+
+    fn render(&self, display: &mut PanelCanvas<Display>) {
+        let mut buf = [0; 128];
+        let draw = |display: &mut PanelCanvas<Display>, page: u8, buf: &[u8]| {
+            display.draw(
+                Rectangle::new(Point::new(0, page as i32 * 8), Size::new(128, 8)),
+                buf,
+            );
+        };
+
+        page::text(FONT, FONT_ALPHABET, b"  LINE FOLLOWER ", &mut buf);
+        draw(display, 0, &buf);
+        for page in 0..BAR_PAGES {
+            self.render_bars(page, &mut buf);
+            draw(display, 1 + page, &buf);
+        }
+        self.render_marker(&mut buf);
+        draw(display, MARKER_PAGE, &buf);
+        page::text(FONT, FONT_ALPHABET, self.status().as_bytes(), &mut buf);
+        draw(display, STATUS_PAGE, &buf);
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Left => "LEFT",
+        Side::Center => "CENTER",
+        Side::Right => "RIGHT",
+    }
+}
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        Report,
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    fn write_position(out: &mut impl Write, position: Option<Position>) -> core::fmt::Result {
+        match position {
+            Some(Position::Line(position)) => write!(out, "{: >6}", position),
+            Some(Position::Lost(side)) => write!(out, "lost {}", side_name(side)),
+            None => out.write_str("-"),
+        }
+    }
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+                EnvSignal::Report => self.report(shell),
+            }
+        }
+
+        fn report(&mut self, shell: &mut Shell) -> EnvResult {
+            if self.output.lock(|output| *output) == Output::Off {
+                return Ok(());
+            }
+            // Overwrites the line, so the live state stays in place
+            shell.write_str("\r\x1b[K")?;
+            self.line.lock(|line| {
+                for value in line.values() {
+                    write!(shell, "{: >5}", value)?;
+                }
+                shell.write_str("  ")?;
+                write_position(shell, line.position())
+            })?;
+            Ok(())
+        }
+
+        fn status(&mut self, shell: &mut Shell) -> EnvResult {
+            let calibrating = self.calibrating.lock(|calibrating| *calibrating);
+            self.line.lock(|line| {
+                let config = line.config();
+                write!(
+                    shell,
+                    "{0:}{1:} sensors, {2:?} line, threshold {3:}{0:}",
+                    CR,
+                    line.sensors(),
+                    config.polarity,
+                    config.threshold
+                )?;
+                for (sensor, (calibration, value)) in
+                    line.calibration().iter().zip(line.values()).enumerate()
+                {
+                    write!(shell, "{}: {: >5}", sensor + 1, value)?;
+                    if calibration.span() > 0 {
+                        write!(shell, "  cal {}..{}", calibration.min, calibration.max)?;
+                    }
+                    shell.write_str(CR)?;
+                }
+                shell.write_str("position: ")?;
+                write_position(shell, line.position())?;
+                let state = match (calibrating, line.is_calibrated()) {
+                    (true, _) => "calibrating",
+                    (false, true) => "calibrated",
+                    (false, false) => "not calibrated",
+                };
+                write!(shell, "{0:}{1:}{0:}", CR, state)
+            })?;
+            Ok(())
+        }
+
+        fn configure(&mut self, configure: impl FnOnce(&mut line::Config)) {
+            self.line.lock(|line| {
+                let mut config = *line.config();
+                configure(&mut config);
+                line.set_config(config);
+            });
+        }
+
+        fn line_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            match (args.next(), args.next()) {
+                (None, _) => self.status(shell)?,
+                (Some("watch"), None) => {
+                    self.output.lock(|output| *output = Output::Watch);
+                    write!(shell, "{0:}watching, Ctrl+C stops{0:}", CR)?;
+                }
+                (Some("cal"), None) => {
+                    self.line.lock(|line| line.reset_calibration());
+                    self.calibrating.lock(|calibrating| *calibrating = true);
+                    write!(
+                        shell,
+                        "{0:}sweep the array across the line, then \"line cal done\"{0:}",
+                        CR
+                    )?;
+                }
+                (Some("cal"), Some("done")) => {
+                    self.calibrating.lock(|calibrating| *calibrating = false);
+                    if !self.line.lock(|line| line.is_calibrated()) {
+                        write!(shell, "{0:}some sensors never saw the line{0:}", CR)?;
+                    } else {
+                        shell.write_str(CR)?;
+                    }
+                }
+                (Some("dark"), None) => {
+                    self.configure(|config| config.polarity = Polarity::Dark);
+                    shell.write_str(CR)?;
+                }
+                (Some("light"), None) => {
+                    self.configure(|config| config.polarity = Polarity::Light);
+                    shell.write_str(CR)?;
+                }
+                (Some("threshold"), Some(threshold)) => match threshold.parse::<u16>() {
+                    Ok(threshold) if threshold <= SCALE => {
+                        self.configure(|config| config.threshold = threshold);
+                        shell.write_str(CR)?;
+                    }
+                    _ => write!(shell, "{0:}threshold must be 0..{1:}{0:}", CR, SCALE)?,
+                },
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "line" => self.line_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            if self.output.lock(|output| *output) == Output::Off {
+                shell.write_str(SHELL_PROMPT)?;
+            }
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.output.lock(|output| *output = Output::Off);
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "help", "line"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Line Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tline                  Print readings, calibration and position\r\n\
+\tline watch            Print readings live, Ctrl+C stops\r\n\
+\tline cal              Restart the calibration sweep\r\n\
+\tline cal done         Finish the calibration sweep\r\n\
+\tline <dark|light>     Set the line color\r\n\
+\tline threshold <n>    Set the on-line level, 0..1000\r\n\
+\tclear                 Clear screen\r\n\
+\thelp                  Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        line: LineArray,
+        calibrating: bool,
+        output: Output,
+    }
+
+    #[local]
+    struct Local {
+        sensors: LineSensors,
+        display: PanelCanvas<Display>,
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(UI_TICK_MS.millis());
+        timer.listen();
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+        adc.calibrate();
+
+        // Five sensors on A0 to A4, from the left of the array
+        let channels = [
+            channel_of(&gpio_a.pa0),
+            channel_of(&gpio_a.pa1),
+            channel_of(&gpio_a.pa4),
+            channel_of(&gpio_b.pb1),
+            channel_of(&gpio_a.pa11),
+        ];
+        let sampler = TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), channels[0]);
+        let mut sensors = LineSensors::new(sampler, &channels);
+        sensors.sampler_mut().start(Hertz::Hz(SCAN_RATE));
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let display = PanelCanvas::new(display);
+
+        (
+            Shared {
+                line: LineArray::new(channels.len(), line::Config::default()),
+                // Starts with a sweep, there's no stored calibration
+                calibrating: true,
+                output: Output::Off,
+            },
+            Local {
+                sensors,
+                display,
+                timer,
+                shell,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 3, local = [sensors], shared = [line, calibrating])]
+    fn adc_sample(ctx: adc_sample::Context) {
+        if let Some(raw) = ctx.local.sensors.read() {
+            (ctx.shared.line, ctx.shared.calibrating).lock(|line, calibrating| {
+                if *calibrating {
+                    line.calibrate(raw);
+                }
+                line.update(raw);
+            });
+        }
+    }
+
+    #[task(binds = TIM17, priority = 2, local = [display, timer], shared = [line, calibrating, output])]
+    fn ui_tick(ctx: ui_tick::Context) {
+        let (mut line, mut calibrating, mut output) =
+            (ctx.shared.line, ctx.shared.calibrating, ctx.shared.output);
+        let calibrating = calibrating.lock(|calibrating| *calibrating);
+        let snapshot = line.lock(|line| Snapshot::take(line, calibrating));
+        snapshot.render(ctx.local.display);
+        if output.lock(|output| *output) != Output::Off {
+            env::spawn(shell::EnvSignal::Report).ok();
+        }
+        ctx.local.timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell], shared = [line, calibrating, output])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,326 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::timer::pwm;
+use hal::timer::{Channel1, Channel2, Channel3, Channel4, Timer};
+
+use c031c6_nucleo_robo_rust::motor::{HBridge, Pwm};
+use robo_core::motor::{Mode, Motor, Stop, DUTY_MAX};
+
+/// Above the audible range, the L298N copes with up to about 25 kHz.
+const PWM_FREQ_KHZ: u32 = 20;
+/// Period of the speed ramps.
+const RAMP_TICK_MS: u32 = 10;
+/// Full reverse to full forward in one second.
+const DEFAULT_ACCEL: u16 = 2_000;
+
+/// Bridge A: enable on PA6 (TIM3_CH1), IN1 on PA8 (TIM1_CH1), IN2 on PB3
+/// (TIM1_CH2). Bridge B: enable on PB5 (TIM3_CH2), IN1 on PB6 (TIM1_CH3),
+/// IN2 on PA11 (TIM1_CH4). Every input is a PWM channel, so one wiring
+/// serves all driver modes. The TB6612 STBY input goes to PB2.
+pub type Left = HBridge<
+    Pwm<pwm::PwmPin<stm32::TIM3, Channel1>>,
+    Pwm<pwm::PwmPin<stm32::TIM1, Channel1>>,
+    Pwm<pwm::PwmPin<stm32::TIM1, Channel2>>,
+>;
+pub type Right = HBridge<
+    Pwm<pwm::PwmPin<stm32::TIM3, Channel2>>,
+    Pwm<pwm::PwmPin<stm32::TIM1, Channel3>>,
+    Pwm<pwm::PwmPin<stm32::TIM1, Channel4>>,
+>;
+
+pub struct Motors {
+    left: Left,
+    right: Right,
+}
+
+impl Motors {
+    fn set_speed(&mut self, left: i16, right: i16) {
+        self.left.motor_mut().set_speed(left);
+        self.right.motor_mut().set_speed(right);
+    }
+
+    fn halt(&mut self, stop: Stop) {
+        self.left.halt(stop);
+        self.right.halt(stop);
+    }
+
+    fn configure(&mut self, apply: impl Fn(&mut Motor)) {
+        apply(self.left.motor_mut());
+        apply(self.right.motor_mut());
+    }
+
+    fn tick(&mut self, elapsed_ms: u32) {
+        self.left.tick(elapsed_ms);
+        self.right.tick(elapsed_ms);
+    }
+}
+
+/// Speed in percent as used by the shell.
+fn percent(speed: i16) -> i32 {
+    speed as i32 * 100 / DUTY_MAX as i32
+}
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+            }
+        }
+
+        fn status(&mut self, shell: &mut Shell) -> EnvResult {
+            let (left, right) = self
+                .motors
+                .lock(|motors| (*motors.left.motor(), *motors.right.motor()));
+            write!(shell, "{0:}mode:  {1:?}{0:}", CR, left.mode())?;
+            write!(shell, "stop:  {:?}{}", left.stop_mode(), CR)?;
+            let ramp = left.accel() as u32 * 100 / DUTY_MAX as u32;
+            write!(shell, "ramp:  {}%/s{}", ramp, CR)?;
+            for (name, motor) in [("left: ", left), ("right:", right)] {
+                write!(
+                    shell,
+                    "{} {}% -> {}%{}",
+                    name,
+                    percent(motor.speed()),
+                    percent(motor.target()),
+                    CR
+                )?;
+            }
+            Ok(())
+        }
+
+        fn motor_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            match (args.next(), args.next()) {
+                (None, _) => self.status(shell)?,
+                (Some("stop"), stop) => {
+                    let stop = match stop {
+                        Some("coast") => Stop::Coast,
+                        _ => Stop::Brake,
+                    };
+                    self.motors.lock(|motors| motors.halt(stop));
+                    write!(shell, "{0:}stopped: {1:?}{0:}", CR, stop)?;
+                }
+                (Some("ramp"), Some(accel)) => match accel.parse::<u16>() {
+                    Ok(accel @ 0..=6_000) => {
+                        let accel = accel * (DUTY_MAX / 100);
+                        self.motors
+                            .lock(|motors| motors.configure(|motor| motor.set_accel(accel)));
+                        shell.write_str(CR)?;
+                    }
+                    _ => write!(shell, "{0:}ramp must be 0..6000 %/s{0:}", CR)?,
+                },
+                (Some("mode"), Some(chip)) => {
+                    let mode = match chip {
+                        "l298n" => Some(Mode::L298n),
+                        "tb6612" => Some(Mode::Tb6612),
+                        "drv8833" => Some(Mode::Drv8833),
+                        _ => None,
+                    };
+                    match mode {
+                        Some(mode) => {
+                            self.motors.lock(|motors| {
+                                motors.halt(Stop::Brake);
+                                motors.configure(|motor| motor.set_mode(mode));
+                            });
+                            shell.write_str(CR)?;
+                        }
+                        None => write!(shell, "{0:}unknown driver: {1:}{0:}", CR, chip)?,
+                    }
+                }
+                (Some(left), Some(right)) => match (left.parse::<i16>(), right.parse::<i16>()) {
+                    (Ok(left @ -100..=100), Ok(right @ -100..=100)) => {
+                        let scale = (DUTY_MAX / 100) as i16;
+                        self.motors
+                            .lock(|motors| motors.set_speed(left * scale, right * scale));
+                        shell.write_str(CR)?;
+                    }
+                    _ => write!(shell, "{0:}speeds must be -100..100 %{0:}", CR)?,
+                },
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "motor" => self.motor_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            shell.write_str(SHELL_PROMPT)?;
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.motors.lock(|motors| motors.halt(Stop::Brake));
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "help", "motor"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Motor Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tmotor                  Print mode and speeds\r\n\
+\tmotor <l> <r>          Set speeds in % -100..100\r\n\
+\tmotor stop [coast]     Stop now, braking unless coast\r\n\
+\tmotor ramp <%/s>       Set acceleration, 0 for none\r\n\
+\tmotor mode <chip>      Set driver: l298n, tb6612 or drv8833\r\n\
+\tclear                  Clear screen\r\n\
+\thelp                   Print this message\r\n\
+\r\n\
+Ctrl+C stops both motors.\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        motors: Motors,
+    }
+
+    #[local]
+    struct Local {
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+        _standby: PB2<Output<PushPull>>,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(RAMP_TICK_MS.millis());
+        timer.listen();
+
+        let mut standby = gpio_b.pb2.into_push_pull_output();
+        standby.set_high().ok();
+
+        let tim1 = ctx.device.TIM1.pwm(PWM_FREQ_KHZ.kHz(), &mut rcc);
+        let tim3 = ctx.device.TIM3.pwm(PWM_FREQ_KHZ.kHz(), &mut rcc);
+        let mut motor = Motor::new(Mode::L298n);
+        motor.set_accel(DEFAULT_ACCEL);
+        let motors = Motors {
+            left: HBridge::new(
+                Pwm::new(tim3.bind_pin(gpio_a.pa6)),
+                Pwm::new(tim1.bind_pin(gpio_a.pa8)),
+                Pwm::new(tim1.bind_pin(gpio_b.pb3)),
+                motor,
+            ),
+            right: HBridge::new(
+                Pwm::new(tim3.bind_pin(gpio_b.pb5)),
+                Pwm::new(tim1.bind_pin(gpio_b.pb6)),
+                Pwm::new(tim1.bind_pin(gpio_a.pa11)),
+                motor,
+            ),
+        };
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        (
+            Shared { motors },
+            Local {
+                timer,
+                shell,
+                _standby: standby,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = TIM17, priority = 2, local = [timer], shared = [motors])]
+    fn ramp_tick(mut ctx: ramp_tick::Context) {
+        ctx.shared.motors.lock(|motors| motors.tick(RAMP_TICK_MS));
+        ctx.local.timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell], shared = [motors])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
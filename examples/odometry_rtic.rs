@@ -0,0 +1,382 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::timer::*;
+
+use c031c6_nucleo_robo_rust::odometry::WheelEncoders;
+use robo_core::fixed::Fixed;
+use robo_core::odometry::{self, Method, Odometry, Wheel};
+use robo_core::telemetry::{Encoder, MAX_FRAME_LEN};
+
+/// Period of the speed estimate.
+const UPDATE_TICK_MS: u32 = 10;
+/// Updates per line of `odo watch` or telemetry frame.
+const REPORT_TICKS: u32 = 10;
+/// Distance between the wheels.
+const TRACK_MM: u16 = 130;
+/// Telemetry channel of `odo stream`: left and right count, left and right
+/// speed in mm/s, yaw rate in mrad/s. Counts wrap at 16 bits, speeds are
+/// `i16`.
+const ODOMETRY_CHANNEL: u8 = 0x20;
+
+/// Where the periodic reports go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Off,
+    Watch,
+    Stream,
+}
+
+fn write_wheel(out: &mut impl Write, name: &str, wheel: &Wheel) -> core::fmt::Result {
+    write!(
+        out,
+        "{} {:>7} ticks {:>6} mm {:>5} mm/s {} rpm",
+        name,
+        wheel.count(),
+        wheel.distance_mm(),
+        wheel.speed(),
+        Fixed::new(wheel.rpm(), 1).width(6)
+    )
+}
+
+fn write_robot(out: &mut impl Write, odometry: &Odometry) -> core::fmt::Result {
+    write!(
+        out,
+        "robot: {:>6} mm {:>5} mm/s {:>5} mrad/s",
+        odometry.distance_mm(),
+        odometry.speed(),
+        odometry.yaw_rate()
+    )
+}
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        /// Periodic report at the given time in milliseconds.
+        Report(u32),
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(
+            &mut self,
+            shell: &mut Shell,
+            telemetry: &mut Encoder,
+            sig: EnvSignal,
+        ) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+                EnvSignal::Report(now) => self.report(shell, telemetry, now),
+            }
+        }
+
+        fn report(&mut self, shell: &mut Shell, telemetry: &mut Encoder, now: u32) -> EnvResult {
+            match self.output.lock(|output| *output) {
+                Output::Off => {}
+                Output::Watch => {
+                    // Overwrites the line, so the live state stays in place
+                    shell.write_str("\r\x1b[K")?;
+                    self.wheels.lock(|wheels| {
+                        let odometry = wheels.odometry();
+                        write!(
+                            shell,
+                            "L {:>7} R {:>7}  ",
+                            odometry.left.count(),
+                            odometry.right.count()
+                        )?;
+                        write_robot(shell, odometry)
+                    })?;
+                }
+                Output::Stream => {
+                    let values = self.wheels.lock(|wheels| {
+                        let odometry = wheels.odometry();
+                        [
+                            odometry.left.count() as u16,
+                            odometry.right.count() as u16,
+                            odometry.left.speed() as i16 as u16,
+                            odometry.right.speed() as i16 as u16,
+                            odometry.yaw_rate() as i16 as u16,
+                        ]
+                    });
+                    let mut frame = [0; MAX_FRAME_LEN];
+                    let frame = telemetry.encode(now, ODOMETRY_CHANNEL, &values, &mut frame);
+                    let serial = shell.get_serial_mut();
+                    for &byte in frame {
+                        while serial.write(byte).is_err() {}
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        fn status(&mut self, shell: &mut Shell) -> EnvResult {
+            self.wheels.lock(|wheels| {
+                let odometry = wheels.odometry();
+                let config = odometry.left.config();
+                write!(
+                    shell,
+                    "{0:}wheel: {1:} ticks/rev {2:} mm, {3:?}{0:}",
+                    CR, config.ticks_per_rev, config.wheel_diameter_mm, config.method
+                )?;
+                write_wheel(shell, "left: ", &odometry.left)?;
+                shell.write_str(CR)?;
+                write_wheel(shell, "right:", &odometry.right)?;
+                shell.write_str(CR)?;
+                write_robot(shell, odometry)?;
+                shell.write_str(CR)
+            })?;
+            Ok(())
+        }
+
+        fn configure(&mut self, configure: impl Fn(&mut odometry::Config)) {
+            self.wheels.lock(|wheels| {
+                let odometry = wheels.odometry_mut();
+                for wheel in [&mut odometry.left, &mut odometry.right] {
+                    let mut config = *wheel.config();
+                    configure(&mut config);
+                    wheel.set_config(config);
+                }
+            });
+        }
+
+        fn odo_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            match (args.next(), args.next(), args.next()) {
+                (None, _, _) => self.status(shell)?,
+                (Some("reset"), None, _) => {
+                    self.wheels.lock(|wheels| wheels.odometry_mut().reset());
+                    shell.write_str(CR)?;
+                }
+                (Some("watch"), None, _) => {
+                    self.output.lock(|output| *output = Output::Watch);
+                    write!(shell, "{0:}watching, Ctrl+C stops{0:}", CR)?;
+                }
+                (Some("stream"), None, _) => {
+                    self.output.lock(|output| *output = Output::Stream);
+                }
+                (Some("method"), Some(method), None) => {
+                    let method = match method {
+                        "window" => Some(Method::Window),
+                        "period" => Some(Method::Period),
+                        "auto" => Some(Method::Auto),
+                        _ => None,
+                    };
+                    match method {
+                        Some(method) => {
+                            self.configure(|config| config.method = method);
+                            shell.write_str(CR)?;
+                        }
+                        None => write!(shell, "{0:}methods: window, period, auto{0:}", CR)?,
+                    }
+                }
+                (Some("wheel"), Some(ticks), Some(diameter)) => {
+                    match (ticks.parse::<u16>(), diameter.parse::<u16>()) {
+                        (Ok(ticks @ 1..), Ok(diameter @ 1..)) => {
+                            self.configure(|config| {
+                                config.ticks_per_rev = ticks;
+                                config.wheel_diameter_mm = diameter;
+                            });
+                            shell.write_str(CR)?;
+                        }
+                        _ => write!(shell, "{0:}ticks and diameter must be 1..{0:}", CR)?,
+                    }
+                }
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "odo" => self.odo_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            if self.output.lock(|output| *output) == Output::Off {
+                shell.write_str(SHELL_PROMPT)?;
+            }
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.output.lock(|output| *output = Output::Off);
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "help", "odo"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Odometry Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\todo                      Print counts, distances and speeds\r\n\
+\todo reset                Zero counts and distances\r\n\
+\todo watch                Print counts live, Ctrl+C stops\r\n\
+\todo stream               Send telemetry frames, Ctrl+C stops\r\n\
+\todo method <method>      Set speed estimate: window, period or auto\r\n\
+\todo wheel <ticks> <mm>   Set ticks per revolution and wheel diameter\r\n\
+\tclear                    Clear screen\r\n\
+\thelp                     Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        wheels: WheelEncoders,
+        exti: stm32::EXTI,
+        output: Output,
+    }
+
+    #[local]
+    struct Local {
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+        telemetry: Encoder,
+        ticks: u32,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(UPDATE_TICK_MS.millis());
+        timer.listen();
+
+        let mut exti = ctx.device.EXTI;
+        let clock = ctx.device.TIM16.timer(&mut rcc);
+        let wheels = WheelEncoders::new(
+            clock,
+            (gpio_b.pb0, gpio_b.pb1, gpio_b.pb4, gpio_b.pb7),
+            &mut exti,
+            &rcc,
+            odometry::Config::default(),
+            odometry::Config {
+                reverse: true,
+                ..odometry::Config::default()
+            },
+            TRACK_MM,
+        );
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        (
+            Shared {
+                wheels,
+                exti,
+                output: Output::Off,
+            },
+            Local {
+                timer,
+                shell,
+                telemetry: Encoder::new(),
+                ticks: 0,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = EXTI0_1, priority = 3, shared = [wheels, exti])]
+    fn left_edge(ctx: left_edge::Context) {
+        (ctx.shared.wheels, ctx.shared.exti).lock(|wheels, exti| wheels.on_edge(exti));
+    }
+
+    #[task(binds = EXTI4_15, priority = 3, shared = [wheels, exti])]
+    fn right_edge(ctx: right_edge::Context) {
+        (ctx.shared.wheels, ctx.shared.exti).lock(|wheels, exti| wheels.on_edge(exti));
+    }
+
+    #[task(binds = TIM17, priority = 2, local = [timer, ticks], shared = [wheels, output])]
+    fn update_tick(mut ctx: update_tick::Context) {
+        ctx.shared.wheels.lock(|wheels| wheels.update());
+        *ctx.local.ticks += 1;
+        let output = ctx.shared.output.lock(|output| *output);
+        if output != Output::Off && ctx.local.ticks.is_multiple_of(REPORT_TICKS) {
+            let now = ctx.local.ticks.wrapping_mul(UPDATE_TICK_MS);
+            env::spawn(shell::EnvSignal::Report(now)).ok();
+        }
+        ctx.local.timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell, telemetry], shared = [wheels, output])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, ctx.local.telemetry, sig)
+            .ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -9,18 +9,25 @@ use stm32c0xx_hal as hal;
 use defmt::info;
 use defmt_rtt as _;
 
-use hal::analog::adc::{self, Adc};
+use hal::analog::adc;
 use hal::gpio::*;
 use hal::prelude::*;
 use hal::serial::*;
-use hal::spi::*;
 use hal::stm32;
+use hal::time::*;
 use hal::timer::*;
 
-use ssd1306::{mode, prelude::*, Ssd1306};
-
 use klaptik::*;
 
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use robo_core::sample::SampleBuffer;
+
+/// ADC conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 1_000;
+/// Smoothing of the value shown on the display, see `robo_core::filter::Ema`.
+const FILTER_SHIFT: u8 = 4;
+
 struct AppState {
     adc_val: u16,
 }
@@ -72,32 +79,6 @@ widget_group! {
     }
 }
 
-type SPII = SPIInterface<
-    Spi<hal::pac::SPI, (PA5<DefaultMode>, NoMiso, PA7<DefaultMode>)>,
-    PA9<Output<PushPull>>,
-    PA15<Output<PushPull>>,
->;
-type DisplayDriver = Ssd1306<SPII, DisplaySize128x64, mode::BasicMode>;
-struct DisplayController {
-    canvas: DisplayDriver,
-}
-
-impl DisplayController {
-    fn new(canvas: DisplayDriver) -> Self {
-        Self { canvas }
-    }
-}
-
-impl Canvas for DisplayController {
-    fn draw(&mut self, bounds: Rectangle, bitmap: &[u8]) {
-        let (start, end) = (bounds.start(), bounds.end());
-        self.canvas
-            .set_draw_area((start.x, start.y), (end.x, end.y))
-            .unwrap();
-        self.canvas.draw(bitmap).unwrap();
-    }
-}
-
 pub const BAR: [&str; 16] = [
     "                ",
     "<>              ",
@@ -154,15 +135,15 @@ mod app {
     #[shared]
     struct Shared {
         app: App,
+        samples: SampleBuffer<32>,
     }
 
     #[local]
     struct Local {
-        adc: Adc,
-        display: SpriteDisplay<DisplayController, { SPRITES.len() }>,
+        sampler: TriggeredSampler,
+        display: SpriteDisplay<PanelCanvas<Display>, { SPRITES.len() }>,
         ui: UI,
         ui_timer: Timer<stm32::TIM17>,
-        pot_input: PA0<DefaultMode>,
         serial: Serial<stm32::USART2>,
     }
 
@@ -170,16 +151,7 @@ mod app {
     fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
         let mut rcc = ctx.device.RCC.constrain();
         let gpio_a = ctx.device.GPIOA.split(&mut rcc);
-
-        // Setup spi i/o
-        let sck = gpio_a.pa5;
-        let mosi = gpio_a.pa7;
-        let mut nss = gpio_a.pa15.into_push_pull_output();
-        nss.set_high().ok();
-        let mut dc = gpio_a.pa9.into_push_pull_output();
-        dc.set_high().ok();
-        let mut rst = gpio_a.pa10.into_push_pull_output();
-        rst.set_high().ok();
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
 
         let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
         ui_timer.start(50.millis());
@@ -192,16 +164,6 @@ mod app {
         adc.set_oversampling_shift(20);
         adc.oversampling_enable(true);
 
-        let spi = ctx.device.SPI.spi(
-            (sck, NoMiso, mosi),
-            Mode {
-                polarity: Polarity::IdleLow,
-                phase: Phase::CaptureOnFirstTransition,
-            },
-            2.MHz(),
-            &mut rcc,
-        );
-
         let mut serial = ctx
             .device
             .USART2
@@ -210,17 +172,30 @@ mod app {
 
         writeln!(serial, "Hello from STM32C031\r\n").unwrap();
 
-        let interface = SPIInterface::new(spi, dc, nss);
-        let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0);
-
         let pot_input = gpio_a.pa0;
         adc.calibrate();
 
-        let mut delay = ctx.device.TIM3.delay(&mut rcc);
-        display.reset(&mut rst, &mut delay).unwrap();
-        display.init().unwrap();
-        display.clear().unwrap();
-        let controller = DisplayController::new(display);
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), channel_of(&pot_input));
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let controller = PanelCanvas::new(display);
         let display = SpriteDisplay::new(controller, SPRITES);
         let ui = UI::new();
 
@@ -229,23 +204,33 @@ mod app {
         info!("App initialized");
 
         (
-            Shared { app },
+            Shared {
+                app,
+                samples: SampleBuffer::new(FILTER_SHIFT),
+            },
             Local {
-                adc,
+                sampler,
                 display,
                 ui,
                 ui_timer,
-                pot_input,
                 serial,
             },
             init::Monotonics(),
         )
     }
 
-    #[task(binds = TIM17, local = [adc, ui, ui_timer, display, pot_input, serial], shared = [app])]
+    #[task(binds = ADC, priority = 2, local = [sampler], shared = [samples])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.samples.lock(|samples| samples.push(raw));
+        }
+    }
+
+    #[task(binds = TIM17, local = [ui, ui_timer, display, serial], shared = [app, samples])]
     fn ui_timer_tick(ctx: ui_timer_tick::Context) {
         let mut app = ctx.shared.app;
-        let pot_raw: u16 = ctx.local.adc.read(ctx.local.pot_input).unwrap_or(0);
+        let mut samples = ctx.shared.samples;
+        let pot_raw = samples.lock(|samples| samples.filtered());
         write!(ctx.local.serial, "{}\r\n", pot_raw).unwrap();
         app.lock(|app| {
             app.update(pot_raw);
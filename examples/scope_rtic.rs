@@ -0,0 +1,302 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::button::UserButton;
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use robo_core::button::{self, Event};
+use robo_core::filter::Ema;
+use robo_core::page::{self, TextLine};
+use robo_core::scope::{Capture, Edge, Plot, State, Trigger};
+
+/// Samples in one capture, one per display column.
+const CAPTURE_LEN: usize = 128;
+/// Samples kept before the trigger point.
+const PRE_TRIGGER: usize = 32;
+/// Edge triggered on at start, a long press of the button flips it.
+const TRIGGER_EDGE: Edge = Edge::Rising;
+/// Period of the button debouncing and timeouts.
+const BUTTON_TICK_MS: u32 = 5;
+/// Display columns per horizontal division.
+const DIV_X: u32 = 16;
+/// Pixels per vertical division.
+const DIV_Y: u32 = 8;
+/// Timebases in microseconds per division, cycled with a click of the
+/// button.
+const TIMEBASES_US: [u32; 6] = [5_000, 10_000, 20_000, 50_000, 100_000, 200_000];
+const VOLTS_DIV_MV: u32 = 500;
+/// Analog supply of the Nucleo board.
+const VDDA_MV: u32 = 3_300;
+const ADC_MAX: u32 = 4_095;
+
+const PLOT: Plot = Plot {
+    page: 1,
+    height: 56,
+    full_scale: (VOLTS_DIV_MV * 56 / DIV_Y * ADC_MAX / VDDA_MV) as u16,
+    grid_x: DIV_X as u8,
+    grid_y: DIV_Y as u8,
+};
+
+const FONT: &[u8] = include_bytes!("assets/font8x8.bin");
+const FONT_ALPHABET: &[u8] = b" !%-./0123456789:<=>ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+fn sample_rate(timebase_us: u32) -> Hertz {
+    Hertz::Hz(DIV_X * 1_000_000 / timebase_us)
+}
+
+fn to_mv(raw: u16) -> u32 {
+    raw as u32 * VDDA_MV / ADC_MAX
+}
+
+pub struct Frame {
+    rows: [u8; CAPTURE_LEN],
+    level: u8,
+}
+
+impl Frame {
+    fn new() -> Self {
+        Self {
+            rows: [PLOT.height - 1; CAPTURE_LEN],
+            level: PLOT.height - 1,
+        }
+    }
+
+    fn capture(&mut self, capture: &Capture<CAPTURE_LEN>) {
+        for (row, sample) in self.rows.iter_mut().zip(capture.samples().iter()) {
+            *row = PLOT.row(sample);
+        }
+        self.level = PLOT.row(capture.trigger().level);
+    }
+
+    fn render(&self, display: &mut PanelCanvas<Display>) {
+        let mut buf = [0; 128];
+        for page in PLOT.page..PLOT.page + PLOT.height / 8 {
+            PLOT.render_page(&self.rows, Some(self.level), page, &mut buf);
+            display.draw(
+                Rectangle::new(Point::new(0, page * 8), Size::new(128, 8)),
+                &buf,
+            );
+        }
+    }
+}
+
+#[rtic::app(device = stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        sampler: TriggeredSampler,
+        capture: Capture<CAPTURE_LEN>,
+        timebase: usize,
+        button: UserButton,
+    }
+
+    #[local]
+    struct Local {
+        exti: stm32::EXTI,
+        display: PanelCanvas<Display>,
+        frame: Frame,
+        ui_timer: Timer<stm32::TIM17>,
+        button_timer: Timer<stm32::TIM16>,
+        pot_channel: u8,
+        pot_filter: Ema,
+        input_channel: u8,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+        let gpio_c = ctx.device.GPIOC.split(&mut rcc);
+
+        let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
+        ui_timer.start(100.millis());
+        ui_timer.listen();
+
+        let mut button_timer = ctx.device.TIM16.timer(&mut rcc);
+        button_timer.start(BUTTON_TICK_MS.millis());
+        button_timer.listen();
+
+        let mut exti = ctx.device.EXTI;
+        let button = UserButton::new(gpio_c.pc13, &mut exti, button::Config::default());
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_39);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_4);
+        adc.set_oversampling_shift(2);
+        adc.oversampling_enable(true);
+
+        // The pot sets the trigger level, the signal goes to A1
+        let pot_input = gpio_a.pa0;
+        let scope_input = gpio_a.pa1;
+        let pot_channel = channel_of(&pot_input);
+        let input_channel = channel_of(&scope_input);
+        adc.calibrate();
+
+        let timebase = 2;
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), input_channel);
+        sampler.set_channels(&[pot_channel, input_channel]);
+        sampler.start(sample_rate(TIMEBASES_US[timebase]));
+
+        let mut capture = Capture::new(Trigger::new(TRIGGER_EDGE, 2048), PRE_TRIGGER);
+        capture.set_auto(Some(CAPTURE_LEN * 4));
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let display = PanelCanvas::new(display);
+
+        (
+            Shared {
+                sampler,
+                capture,
+                timebase,
+                button,
+            },
+            Local {
+                exti,
+                display,
+                frame: Frame::new(),
+                ui_timer,
+                button_timer,
+                pot_channel,
+                pot_filter: Ema::new(6),
+                input_channel,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 2, local = [pot_channel, pot_filter, input_channel], shared = [sampler, capture])]
+    fn adc_sample(ctx: adc_sample::Context) {
+        let local = ctx.local;
+        (ctx.shared.sampler, ctx.shared.capture).lock(|sampler, capture| match sampler.read() {
+            Some((channel, value)) if channel == *local.input_channel => {
+                capture.push(value);
+            }
+            Some((channel, value)) if channel == *local.pot_channel => {
+                let level = local.pot_filter.update(value);
+                capture.set_trigger(Trigger::new(capture.trigger().edge, level));
+            }
+            _ => {}
+        });
+    }
+
+    #[task(binds = TIM17, local = [display, frame, ui_timer], shared = [capture, timebase])]
+    fn ui_timer_tick(ctx: ui_timer_tick::Context) {
+        let frame = ctx.local.frame;
+        let mut capture = ctx.shared.capture;
+        let (trigger, captured) = capture.lock(|capture| {
+            let captured = capture.state() == State::Done;
+            if captured {
+                frame.capture(capture);
+                capture.arm();
+            }
+            (capture.trigger(), captured)
+        });
+        let mut timebase = ctx.shared.timebase;
+        let timebase_us = timebase.lock(|timebase| TIMEBASES_US[*timebase]);
+
+        let mut readout = TextLine::<16>::new();
+        if timebase_us >= 1_000 {
+            write!(readout, "{}MS ", timebase_us / 1_000).ok();
+        } else {
+            write!(readout, "{}US ", timebase_us).ok();
+        }
+        let level_mv = to_mv(trigger.level);
+        let edge = match trigger.edge {
+            Edge::Rising => 'R',
+            Edge::Falling => 'F',
+        };
+        write!(
+            readout,
+            "{}.{}V {}{}.{:02}",
+            VOLTS_DIV_MV / 1_000,
+            VOLTS_DIV_MV % 1_000 / 100,
+            edge,
+            level_mv / 1_000,
+            level_mv % 1_000 / 10
+        )
+        .ok();
+
+        let mut buf = [0; 128];
+        page::text(FONT, FONT_ALPHABET, readout.as_bytes(), &mut buf);
+        ctx.local
+            .display
+            .draw(Rectangle::new(Point::zero(), Size::new(128, 8)), &buf);
+        if captured {
+            frame.render(ctx.local.display);
+        }
+        ctx.local.ui_timer.clear_irq();
+    }
+
+    #[task(binds = EXTI4_15, local = [exti], shared = [button])]
+    fn button_edge(mut ctx: button_edge::Context) {
+        let exti = ctx.local.exti;
+        ctx.shared.button.lock(|button| button.on_edge(exti));
+    }
+
+    #[task(binds = TIM16, local = [button_timer], shared = [button, sampler, capture, timebase])]
+    fn button_tick(ctx: button_tick::Context) {
+        let mut button = ctx.shared.button;
+        match button.lock(|button| button.tick(BUTTON_TICK_MS)) {
+            Some(Event::Short) => {
+                (ctx.shared.sampler, ctx.shared.timebase).lock(|sampler, timebase| {
+                    *timebase = (*timebase + 1) % TIMEBASES_US.len();
+                    sampler.set_rate(sample_rate(TIMEBASES_US[*timebase]));
+                });
+            }
+            Some(Event::Long) => {
+                let mut capture = ctx.shared.capture;
+                capture.lock(|capture| {
+                    let trigger = capture.trigger();
+                    let edge = match trigger.edge {
+                        Edge::Rising => Edge::Falling,
+                        Edge::Falling => Edge::Rising,
+                    };
+                    capture.set_trigger(Trigger::new(edge, trigger.level));
+                    capture.arm();
+                });
+            }
+            _ => {}
+        }
+        ctx.local.button_timer.clear_irq();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,870 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use ssd1306::prelude::DisplayRotation;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::button::UserButton;
+use c031c6_nucleo_robo_rust::panel::{self, Display, Panel as _, PanelCanvas};
+use robo_core::alarm::{AlarmEvent, Level, Monitor, Thresholds};
+use robo_core::button::{self, Event};
+use robo_core::menu::{self, Item, Kind, Menu, Settings};
+use robo_core::sample::SampleBuffer;
+use robo_core::saver::{Power, Screensaver};
+use robo_core::screen::{Refresh, Screens};
+use robo_core::stats::{Stats, WindowStats};
+
+/// Selectable ADC conversions per second, paced by TIM3.
+const SAMPLE_RATES: [u32; 5] = [100, 250, 500, 1_000, 2_000];
+/// Largest statistics window in samples.
+const MAX_WINDOW: usize = 128;
+/// Analog supply of the Nucleo board.
+const VDDA_MV: u32 = 3_300;
+const ADC_MAX: u32 = 4_095;
+/// Default alarm window of the pot.
+const POT_THRESHOLDS: Thresholds = Thresholds::new(410, 3_686);
+/// UI tick, screen update periods are multiples of it.
+const UI_TICK_MS: u32 = 50;
+/// Period of the button debouncing and timeouts.
+const BUTTON_TICK_MS: u32 = 5;
+/// Pot move waking the screensaver, in raw ADC counts.
+const WAKE_THRESHOLD: u16 = 256;
+/// Selectable display rotations, the screens are laid out for landscape.
+const ROTATIONS: [DisplayRotation; 2] = [DisplayRotation::Rotate0, DisplayRotation::Rotate180];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Screen {
+    Readout,
+    PotBar,
+    Stats,
+    Settings,
+    About,
+}
+
+/// Screens in button order with their update period in UI ticks.
+const SCREENS: [(Screen, u16); 5] = [
+    (Screen::Readout, 4),
+    (Screen::PotBar, 1),
+    (Screen::Stats, 10),
+    (Screen::Settings, 2),
+    (Screen::About, 0),
+];
+
+/// Menu field ids of `Config`.
+const RATE: u8 = 0;
+const FILTER: u8 = 1;
+const WINDOW: u8 = 2;
+const ALARM: u8 = 3;
+const ALARM_LOW: u8 = 4;
+const ALARM_HIGH: u8 = 5;
+const CONTRAST: u8 = 6;
+const INVERT: u8 = 7;
+const ROTATE: u8 = 8;
+const DIM_AFTER: u8 = 9;
+const OFF_AFTER: u8 = 10;
+/// Menu action restoring the default configuration.
+const DEFAULTS: u8 = 0;
+/// Menu action switching the display off until the next button press.
+const SLEEP: u8 = 1;
+
+const SAMPLING_MENU: [Item; 4] = [
+    Item::new(
+        "RATE HZ",
+        Kind::Choice {
+            id: RATE,
+            choices: &["100", "250", "500", "1000", "2000"],
+        },
+    ),
+    Item::new(
+        "FILTER",
+        Kind::Number {
+            id: FILTER,
+            min: 0,
+            max: 8,
+            step: 1,
+        },
+    ),
+    Item::new(
+        "WINDOW",
+        Kind::Number {
+            id: WINDOW,
+            min: 8,
+            max: MAX_WINDOW as i32,
+            step: 8,
+        },
+    ),
+    Item::new("BACK", Kind::Back),
+];
+
+const ALARM_MENU: [Item; 4] = [
+    Item::new(
+        "ENABLE",
+        Kind::Choice {
+            id: ALARM,
+            choices: &["OFF", "ON"],
+        },
+    ),
+    Item::new(
+        "LOW",
+        Kind::Number {
+            id: ALARM_LOW,
+            min: 0,
+            max: ADC_MAX as i32,
+            step: 32,
+        },
+    ),
+    Item::new(
+        "HIGH",
+        Kind::Number {
+            id: ALARM_HIGH,
+            min: 0,
+            max: ADC_MAX as i32,
+            step: 32,
+        },
+    ),
+    Item::new("BACK", Kind::Back),
+];
+
+const DISPLAY_MENU: [Item; 7] = [
+    Item::new(
+        "CONTRAST",
+        Kind::Number {
+            id: CONTRAST,
+            min: 0,
+            max: 255,
+            step: 15,
+        },
+    ),
+    Item::new(
+        "INVERT",
+        Kind::Choice {
+            id: INVERT,
+            choices: &["OFF", "ON"],
+        },
+    ),
+    Item::new(
+        "ROTATE",
+        Kind::Choice {
+            id: ROTATE,
+            choices: &["0", "180"],
+        },
+    ),
+    Item::new(
+        "DIM S",
+        Kind::Number {
+            id: DIM_AFTER,
+            min: 0,
+            max: 600,
+            step: 30,
+        },
+    ),
+    Item::new(
+        "OFF S",
+        Kind::Number {
+            id: OFF_AFTER,
+            min: 0,
+            max: 1_800,
+            step: 60,
+        },
+    ),
+    Item::new("SLEEP", Kind::Action(SLEEP)),
+    Item::new("BACK", Kind::Back),
+];
+
+static SETTINGS_MENU: [Item; 5] = [
+    Item::new("SAMPLING", Kind::Submenu(&SAMPLING_MENU)),
+    Item::new("ALARM", Kind::Submenu(&ALARM_MENU)),
+    Item::new("DISPLAY", Kind::Submenu(&DISPLAY_MENU)),
+    Item::new("DEFAULTS", Kind::Action(DEFAULTS)),
+    Item::new("EXIT", Kind::Back),
+];
+
+/// Menu rows below the title line.
+const MENU_ROWS: usize = 7;
+
+#[derive(Clone, Copy)]
+pub struct Config {
+    /// Index into `SAMPLE_RATES`.
+    rate: usize,
+    filter_shift: u8,
+    window: usize,
+    alarm: bool,
+    thresholds: Thresholds,
+    contrast: u8,
+    invert: bool,
+    /// Index into `ROTATIONS`.
+    rotation: usize,
+    /// Screensaver timeouts in seconds, 0 disables.
+    dim_after: u16,
+    off_after: u16,
+}
+
+impl Config {
+    const fn new() -> Self {
+        Self {
+            rate: 3,
+            filter_shift: 4,
+            window: MAX_WINDOW,
+            alarm: true,
+            thresholds: POT_THRESHOLDS,
+            contrast: 135,
+            invert: false,
+            rotation: 0,
+            dim_after: 60,
+            off_after: 300,
+        }
+    }
+
+    fn sample_rate(&self) -> Hertz {
+        Hertz::Hz(SAMPLE_RATES[self.rate])
+    }
+
+    /// Screensaver timeouts in UI ticks.
+    fn saver_timeouts(&self) -> (u32, u32) {
+        let ticks = |secs: u16| secs as u32 * 1_000 / UI_TICK_MS;
+        (ticks(self.dim_after), ticks(self.off_after))
+    }
+}
+
+impl Settings for Config {
+    fn get(&self, id: u8) -> i32 {
+        match id {
+            RATE => self.rate as i32,
+            FILTER => self.filter_shift as i32,
+            WINDOW => self.window as i32,
+            ALARM => self.alarm as i32,
+            ALARM_LOW => self.thresholds.low as i32,
+            ALARM_HIGH => self.thresholds.high as i32,
+            CONTRAST => self.contrast as i32,
+            INVERT => self.invert as i32,
+            ROTATE => self.rotation as i32,
+            DIM_AFTER => self.dim_after as i32,
+            OFF_AFTER => self.off_after as i32,
+            _ => 0,
+        }
+    }
+
+    fn set(&mut self, id: u8, value: i32) {
+        match id {
+            RATE => self.rate = value as usize,
+            FILTER => self.filter_shift = value as u8,
+            WINDOW => self.window = value as usize,
+            ALARM => self.alarm = value != 0,
+            ALARM_LOW => self.thresholds.low = value as u16,
+            ALARM_HIGH => self.thresholds.high = value as u16,
+            CONTRAST => self.contrast = value as u8,
+            INVERT => self.invert = value != 0,
+            ROTATE => self.rotation = value as usize,
+            DIM_AFTER => self.dim_after = value as u16,
+            OFF_AFTER => self.off_after = value as u16,
+            _ => {}
+        }
+    }
+}
+
+struct AppState {
+    adc_val: u16,
+    mv_val: u16,
+    stats: Stats,
+    alarm: Option<AlarmEvent>,
+    config: Config,
+    menu: Menu<3>,
+}
+
+/// Display settings applied to the panel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Panel {
+    contrast: u8,
+    invert: bool,
+    rotation: usize,
+    power: Power,
+}
+
+pub struct App {
+    state: AppState,
+    monitor: Monitor<1>,
+    saver: Screensaver,
+    channel: u8,
+}
+
+impl App {
+    fn new(channel: u8) -> Self {
+        let (dim_after, off_after) = Config::new().saver_timeouts();
+        let mut app = Self {
+            state: AppState {
+                adc_val: 0,
+                mv_val: 0,
+                stats: Stats::default(),
+                alarm: None,
+                config: Config::new(),
+                menu: Menu::new("SETTINGS", &SETTINGS_MENU, MENU_ROWS, ADC_MAX as u16),
+            },
+            monitor: Monitor::new(),
+            saver: Screensaver::new(dim_after, off_after, WAKE_THRESHOLD),
+            channel,
+        };
+        app.watch();
+        app
+    }
+
+    fn config(&self) -> &Config {
+        &self.state.config
+    }
+
+    /// Display settings, with the screensaver applied.
+    fn panel(&self) -> Panel {
+        let config = &self.state.config;
+        Panel {
+            contrast: config.contrast,
+            invert: config.invert,
+            rotation: config.rotation,
+            power: self.saver.power(),
+        }
+    }
+
+    /// Runs the screensaver for a UI tick, large pot moves keep it awake.
+    fn idle(&mut self, position: u16) {
+        self.saver.position(position);
+        self.saver.tick();
+    }
+
+    /// Button press, returns `false` if it was used up for waking the
+    /// display.
+    fn wake(&mut self) -> bool {
+        !self.saver.wake()
+    }
+
+    /// Feeds the pot to the menu while it's on screen.
+    fn navigate(&mut self, position: u16) {
+        self.state.menu.position(position);
+    }
+
+    /// Confirms the selected menu item. Alarm settings are applied here,
+    /// the rest is up to the caller. An alarm window with the low threshold
+    /// above the high one is rejected and the old thresholds stay.
+    fn confirm(&mut self) -> Option<menu::Event> {
+        let state = &mut self.state;
+        let thresholds = state.config.thresholds;
+        let event = state.menu.confirm(&mut state.config)?;
+        if state.config.thresholds.low > state.config.thresholds.high {
+            state.config.thresholds = thresholds;
+            return None;
+        }
+        match event {
+            menu::Event::Action(DEFAULTS) => {
+                state.config = Config::new();
+                self.watch();
+                self.apply_timeouts();
+            }
+            menu::Event::Action(SLEEP) => self.saver.sleep(),
+            menu::Event::Changed {
+                id: ALARM | ALARM_LOW | ALARM_HIGH,
+                ..
+            } => self.watch(),
+            menu::Event::Changed {
+                id: DIM_AFTER | OFF_AFTER,
+                ..
+            } => self.apply_timeouts(),
+            menu::Event::Exit => state.menu.reset(),
+            _ => {}
+        }
+        Some(event)
+    }
+
+    fn apply_timeouts(&mut self) {
+        let (dim_after, off_after) = self.state.config.saver_timeouts();
+        self.saver.set_timeouts(dim_after, off_after);
+    }
+
+    fn watch(&mut self) {
+        let config = self.state.config;
+        if config.alarm {
+            self.monitor.watch(self.channel, config.thresholds);
+        } else {
+            self.monitor.unwatch(self.channel);
+            self.state.alarm = None;
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    fn update(&mut self, adc: u16, stats: Stats) {
+        self.state.adc_val = adc;
+        self.state.mv_val = (adc as u32 * VDDA_MV / ADC_MAX) as u16;
+        self.state.stats = stats;
+    }
+
+    fn check(&mut self, channel: u8, value: u16) -> Option<AlarmEvent> {
+        let event = self.monitor.update(channel, value)?;
+        self.state.alarm = Some(event).filter(AlarmEvent::is_alarm);
+        Some(event)
+    }
+}
+
+enum Asset {
+    Blank = 0,
+    AdcBackground = 1,
+    PotBackground = 2,
+    Numbers = 3,
+    Bar = 4,
+    Font = 5,
+}
+
+impl From<Asset> for SpriteId {
+    fn from(asset: Asset) -> Self {
+        asset as _
+    }
+}
+
+widget_group! {
+    ReadoutUI<&AppState>,
+    {
+        bg: GlyphIcon, Asset::AdcBackground, 0, Point::zero();
+        raw_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*2), Size::new(16, 16);
+        mv_value: Label<4>, Asset::Numbers, "0000", Point::new(8*5, 8*5), Size::new(16, 16);
+        alert: Label<16>, Asset::Font, "                ", Point::new(0, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut ReadoutUI, state: &AppState| {
+        write!(widget.raw_value, "{: >4}", state.adc_val).ok();
+        write!(widget.mv_value, "{: >4}", state.mv_val).ok();
+        match state.alarm {
+            Some(AlarmEvent { level: Level::Low, value, .. }) => {
+                write!(widget.alert, "!POT LOW   {: >5}", value).ok();
+            }
+            Some(AlarmEvent { level: Level::High, value, .. }) => {
+                write!(widget.alert, "!POT HIGH  {: >5}", value).ok();
+            }
+            _ => {
+                write!(widget.alert, "{: <16}", "").ok();
+            }
+        }
+    }
+}
+
+widget_group! {
+    PotBarUI<&AppState>,
+    {
+        bg: GlyphIcon, Asset::PotBackground, 0, Point::zero();
+        bar: Label<16>, Asset::Bar, "                ", Point::new(0, 8*4), Size::new(8, 8);
+        percents: Label<3>, Asset::Numbers, "000", Point::new(8*5, 8*6), Size::new(16, 16);
+    },
+    |widget: &mut PotBarUI, state: &AppState| {
+        let percent = state.adc_val as u32 * 1000 / 4096 / 10;
+        write!(widget.percents, "{: >3}", percent).ok();
+        let bar_pos = percent * 16 / 100;
+        write!(widget.bar, "{: >16}", BAR[bar_pos as usize]).ok();
+    }
+}
+
+widget_group! {
+    StatsUI<&AppState>,
+    {
+        bg: GlyphIcon, Asset::Blank, 0, Point::zero();
+        title: Label<16>, Asset::Font, "STATS           ", Point::zero(), Size::new(8, 8);
+        min_max: Label<16>, Asset::Font, "                ", Point::new(0, 8*2), Size::new(8, 8);
+        mean_rms: Label<16>, Asset::Font, "                ", Point::new(0, 8*3), Size::new(8, 8);
+        std_dev: Label<16>, Asset::Font, "                ", Point::new(0, 8*4), Size::new(8, 8);
+    },
+    |widget: &mut StatsUI, state: &AppState| {
+        let stats = &state.stats;
+        write!(widget.title, "STATS   W:{: >6}", state.config.window).ok();
+        write!(widget.min_max, "MIN{: >5} MAX{: >4}", stats.min, stats.max).ok();
+        write!(widget.mean_rms, "AVG{: >5} RMS{: >4}", stats.mean, stats.rms).ok();
+        write!(widget.std_dev, "SD {: >5} N{: >6}", stats.std_dev, stats.count).ok();
+    }
+}
+
+widget_group! {
+    SettingsUI<&AppState>,
+    {
+        bg: GlyphIcon, Asset::Blank, 0, Point::zero();
+        title: Label<16>, Asset::Font, "                ", Point::zero(), Size::new(8, 8);
+        row1: Label<16>, Asset::Font, "                ", Point::new(0, 8), Size::new(8, 8);
+        row2: Label<16>, Asset::Font, "                ", Point::new(0, 8*2), Size::new(8, 8);
+        row3: Label<16>, Asset::Font, "                ", Point::new(0, 8*3), Size::new(8, 8);
+        row4: Label<16>, Asset::Font, "                ", Point::new(0, 8*4), Size::new(8, 8);
+        row5: Label<16>, Asset::Font, "                ", Point::new(0, 8*5), Size::new(8, 8);
+        row6: Label<16>, Asset::Font, "                ", Point::new(0, 8*6), Size::new(8, 8);
+        row7: Label<16>, Asset::Font, "                ", Point::new(0, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut SettingsUI, state: &AppState| {
+        let (menu, config) = (&state.menu, &state.config);
+        write!(widget.title, "{: <16}", menu.title()).ok();
+        menu.write_row(0, config, 16, &mut widget.row1).ok();
+        menu.write_row(1, config, 16, &mut widget.row2).ok();
+        menu.write_row(2, config, 16, &mut widget.row3).ok();
+        menu.write_row(3, config, 16, &mut widget.row4).ok();
+        menu.write_row(4, config, 16, &mut widget.row5).ok();
+        menu.write_row(5, config, 16, &mut widget.row6).ok();
+        menu.write_row(6, config, 16, &mut widget.row7).ok();
+    }
+}
+
+widget_group! {
+    AboutUI<&AppState>,
+    {
+        bg: GlyphIcon, Asset::Blank, 0, Point::zero();
+        name: Label<16>, Asset::Font, "ROBO RUST       ", Point::zero(), Size::new(8, 8);
+        version: Label<16>, Asset::Font, "                ", Point::new(0, 8*2), Size::new(8, 8);
+        board: Label<16>, Asset::Font, "NUCLEO-C031C6   ", Point::new(0, 8*4), Size::new(8, 8);
+        hint: Label<16>, Asset::Font, "BUTTON: NEXT    ", Point::new(0, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut AboutUI, _: &AppState| {
+        write!(widget.version, "FW {: <13}", env!("CARGO_PKG_VERSION")).ok();
+    }
+}
+
+/// Sends the panel settings, returns `true` if the content has to be
+/// redrawn.
+fn apply(display: &mut Display, panel: Panel, prev: Panel) -> bool {
+    let contrast = match panel.power {
+        Power::On => panel.contrast,
+        Power::Dim | Power::Off => 0,
+    };
+    display.set_contrast(contrast).ok();
+    display.set_invert(panel.invert).ok();
+    display.set_rotation(ROTATIONS[panel.rotation]).ok();
+    display.set_display_on(panel.power != Power::Off).ok();
+    // Nothing is rendered while off, and rotating mirrors what's shown
+    prev.power == Power::Off || panel.rotation != prev.rotation
+}
+
+pub const BAR: [&str; 16] = [
+    "                ",
+    "<>              ",
+    "<=>             ",
+    "<==>            ",
+    "<===>           ",
+    "<====>          ",
+    "<=====>         ",
+    "<======>        ",
+    "<=======>       ",
+    "<========>      ",
+    "<=========>     ",
+    "<==========>    ",
+    "<===========>   ",
+    "<============>  ",
+    "<=============> ",
+    "<==============>",
+];
+
+pub const SPRITES: [(FlashSprite, Glyphs); 6] = [
+    (
+        FlashSprite::new(Asset::Blank as _, 1, Size::new(128, 64), &[0; 128 * 64 / 8]),
+        Glyphs::Sequential(1),
+    ),
+    (
+        FlashSprite::new(
+            Asset::AdcBackground as _,
+            1,
+            Size::new(128, 64),
+            include_bytes!("assets/adc.bin"),
+        ),
+        Glyphs::Sequential(1),
+    ),
+    (
+        FlashSprite::new(
+            Asset::PotBackground as _,
+            1,
+            Size::new(128, 64),
+            include_bytes!("assets/potpos.bin"),
+        ),
+        Glyphs::Sequential(1),
+    ),
+    (
+        FlashSprite::new(
+            Asset::Numbers as _,
+            11,
+            Size::new(16, 16),
+            include_bytes!("assets/numbers16x16.bin"),
+        ),
+        Glyphs::Alphabet(b" 0123456789"),
+    ),
+    (
+        FlashSprite::new(
+            Asset::Bar as _,
+            4,
+            Size::new(8, 8),
+            include_bytes!("assets/bar.bin"),
+        ),
+        Glyphs::Alphabet(b" <=>"),
+    ),
+    (
+        FlashSprite::new(
+            Asset::Font as _,
+            46,
+            Size::new(8, 8),
+            include_bytes!("assets/font8x8.bin"),
+        ),
+        Glyphs::Alphabet(b" !%-./0123456789:<=>ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+    ),
+];
+
+/// Redraws a screen, from scratch on a full refresh.
+macro_rules! refresh {
+    ($ui:expr, $refresh:expr, $state:expr, $display:expr) => {{
+        if $refresh == Refresh::Full {
+            $ui.invalidate();
+        }
+        $ui.update($state);
+        $ui.render($display);
+    }};
+}
+
+pub struct Views {
+    readout: ReadoutUI,
+    pot_bar: PotBarUI,
+    stats: StatsUI,
+    settings: SettingsUI,
+    about: AboutUI,
+}
+
+impl Views {
+    fn new() -> Self {
+        Self {
+            readout: ReadoutUI::new(),
+            pot_bar: PotBarUI::new(),
+            stats: StatsUI::new(),
+            settings: SettingsUI::new(),
+            about: AboutUI::new(),
+        }
+    }
+
+    fn render(
+        &mut self,
+        screen: Screen,
+        refresh: Refresh,
+        state: &AppState,
+        controller: &mut PanelCanvas<Display>,
+    ) {
+        let display = &mut SpriteDisplay::new(controller, SPRITES);
+        match screen {
+            Screen::Readout => refresh!(self.readout, refresh, state, display),
+            Screen::PotBar => refresh!(self.pot_bar, refresh, state, display),
+            Screen::Stats => refresh!(self.stats, refresh, state, display),
+            Screen::Settings => refresh!(self.settings, refresh, state, display),
+            Screen::About => refresh!(self.about, refresh, state, display),
+        }
+    }
+}
+
+#[rtic::app(device = stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        app: App,
+        sampler: TriggeredSampler,
+        samples: SampleBuffer<32>,
+        stats: WindowStats<MAX_WINDOW>,
+        screens: Screens<Screen, { SCREENS.len() }>,
+        button: UserButton,
+    }
+
+    #[local]
+    struct Local {
+        exti: stm32::EXTI,
+        display: PanelCanvas<Display>,
+        panel: Panel,
+        views: Views,
+        ui_timer: Timer<stm32::TIM17>,
+        button_timer: Timer<stm32::TIM16>,
+        pot_channel: u8,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+        let gpio_c = ctx.device.GPIOC.split(&mut rcc);
+
+        let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
+        ui_timer.start(UI_TICK_MS.millis());
+        ui_timer.listen();
+
+        let mut button_timer = ctx.device.TIM16.timer(&mut rcc);
+        button_timer.start(BUTTON_TICK_MS.millis());
+        button_timer.listen();
+
+        let mut exti = ctx.device.EXTI;
+        let button = UserButton::new(gpio_c.pc13, &mut exti, button::Config::default());
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+
+        let pot_input = gpio_a.pa0;
+        let pot_channel = channel_of(&pot_input);
+        adc.calibrate();
+
+        let app = App::new(pot_channel);
+        let config = *app.config();
+        let mut sampler = TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), pot_channel);
+        sampler.start(config.sample_rate());
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let mut display = PanelCanvas::new(display);
+        let panel = app.panel();
+        apply(display.panel(), panel, panel);
+
+        (
+            Shared {
+                app,
+                sampler,
+                samples: SampleBuffer::new(config.filter_shift),
+                stats: WindowStats::new(config.window),
+                screens: Screens::new(SCREENS),
+                button,
+            },
+            Local {
+                exti,
+                display,
+                panel,
+                views: Views::new(),
+                ui_timer,
+                button_timer,
+                pot_channel,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 2, shared = [sampler, samples, stats])]
+    fn adc_sample(ctx: adc_sample::Context) {
+        (ctx.shared.sampler, ctx.shared.samples, ctx.shared.stats).lock(
+            |sampler, samples, stats| {
+                if let Some((_, raw)) = sampler.read() {
+                    samples.push(raw);
+                    stats.push(raw);
+                }
+            },
+        );
+    }
+
+    #[task(binds = TIM17, local = [views, display, panel, ui_timer, pot_channel], shared = [app, samples, stats, screens])]
+    fn ui_timer_tick(ctx: ui_timer_tick::Context) {
+        let (pot_raw, stats) = (ctx.shared.samples, ctx.shared.stats)
+            .lock(|samples, stats| (samples.filtered(), stats.stats()));
+        let local = ctx.local;
+        (ctx.shared.app, ctx.shared.screens).lock(|app, screens| {
+            app.update(pot_raw, stats);
+            app.idle(pot_raw);
+            let settings = screens.current() == Screen::Settings;
+            if let Some(event) = app.check(*local.pot_channel, pot_raw) {
+                // The pot also drives the menu, an alarm doesn't take it away
+                if event.is_alarm() && !settings {
+                    screens.show(Screen::Readout);
+                    app.wake();
+                }
+            }
+            let screen = screens.current();
+            if settings {
+                app.navigate(pot_raw);
+            }
+
+            let panel = app.panel();
+            if panel != *local.panel {
+                if apply(local.display.panel(), panel, *local.panel) {
+                    screens.invalidate();
+                }
+                *local.panel = panel;
+            }
+            if panel.power == Power::Off {
+                return;
+            }
+            if let Some(refresh) = screens.tick() {
+                local
+                    .views
+                    .render(screen, refresh, app.state(), local.display);
+            }
+        });
+        local.ui_timer.clear_irq();
+    }
+
+    #[task(binds = EXTI4_15, local = [exti], shared = [button])]
+    fn button_edge(mut ctx: button_edge::Context) {
+        let exti = ctx.local.exti;
+        ctx.shared.button.lock(|button| button.on_edge(exti));
+    }
+
+    #[task(binds = TIM16, local = [button_timer], shared = [button, app, sampler, samples, stats, screens])]
+    fn button_tick(ctx: button_tick::Context) {
+        ctx.local.button_timer.clear_irq();
+        let shared = ctx.shared;
+        let mut button = shared.button;
+        if button.lock(|button| button.tick(BUTTON_TICK_MS)) != Some(Event::Short) {
+            return;
+        }
+        let (mut app, mut screens) = (shared.app, shared.screens);
+        let (mut sampler, mut samples, mut stats) = (shared.sampler, shared.samples, shared.stats);
+        // A click waking the display does nothing else
+        if !app.lock(|app| app.wake()) {
+            return;
+        }
+        if screens.lock(|screens| screens.current()) != Screen::Settings {
+            screens.lock(|screens| screens.next());
+        } else {
+            let (event, config) = app.lock(|app| (app.confirm(), *app.config()));
+            match event {
+                Some(menu::Event::Exit) => screens.lock(|screens| screens.next()),
+                Some(menu::Event::Changed { id: RATE, .. }) => {
+                    sampler.lock(|sampler| sampler.set_rate(config.sample_rate()));
+                }
+                Some(menu::Event::Changed { id: FILTER, .. }) => {
+                    samples.lock(|samples| samples.set_filter_shift(config.filter_shift));
+                }
+                Some(menu::Event::Changed { id: WINDOW, .. }) => {
+                    stats.lock(|stats| stats.set_window(config.window));
+                }
+                Some(menu::Event::Action(DEFAULTS)) => {
+                    sampler.lock(|sampler| sampler.set_rate(config.sample_rate()));
+                    samples.lock(|samples| samples.set_filter_shift(config.filter_shift));
+                    stats.lock(|stats| stats.set_window(config.window));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
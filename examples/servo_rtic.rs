@@ -0,0 +1,388 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::pwm;
+use hal::timer::{Channel1, Channel2, Channel3, Channel4, Timer};
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::servo::{ServoPin, FRAME_HZ};
+use robo_core::sample::SampleBuffer;
+use robo_core::servo::{Calibration, Servo, MAX_ANGLE};
+
+/// ADC conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 1_000;
+const FILTER_SHIFT: u8 = 4;
+const ADC_MAX: u32 = 4_095;
+/// One servo frame.
+const FRAME_MS: u32 = 1_000 / FRAME_HZ;
+const SERVOS: usize = 4;
+
+/// Servos 1 to 4 on PA8 (TIM1_CH1), PB3 (TIM1_CH2), PB6 (TIM1_CH3) and
+/// PA11 (TIM1_CH4).
+pub struct Servos {
+    s1: ServoPin<pwm::PwmPin<stm32::TIM1, Channel1>>,
+    s2: ServoPin<pwm::PwmPin<stm32::TIM1, Channel2>>,
+    s3: ServoPin<pwm::PwmPin<stm32::TIM1, Channel3>>,
+    s4: ServoPin<pwm::PwmPin<stm32::TIM1, Channel4>>,
+}
+
+impl Servos {
+    /// Servo `n`, counting from 1.
+    fn get(&self, n: usize) -> Option<&Servo> {
+        match n {
+            1 => Some(self.s1.servo()),
+            2 => Some(self.s2.servo()),
+            3 => Some(self.s3.servo()),
+            4 => Some(self.s4.servo()),
+            _ => None,
+        }
+    }
+
+    fn get_mut(&mut self, n: usize) -> Option<&mut Servo> {
+        match n {
+            1 => Some(self.s1.servo_mut()),
+            2 => Some(self.s2.servo_mut()),
+            3 => Some(self.s3.servo_mut()),
+            4 => Some(self.s4.servo_mut()),
+            _ => None,
+        }
+    }
+
+    fn tick(&mut self, elapsed_ms: u32) {
+        self.s1.tick(elapsed_ms);
+        self.s2.tick(elapsed_ms);
+        self.s3.tick(elapsed_ms);
+        self.s4.tick(elapsed_ms);
+    }
+}
+
+fn write_servo(out: &mut impl Write, n: usize, servo: &Servo) -> core::fmt::Result {
+    let Calibration {
+        min_us,
+        center_us,
+        max_us,
+    } = *servo.calibration();
+    match (servo.angle(), servo.pulse_us()) {
+        (Some(angle), Some(us)) => write!(out, "{}: {:>3} deg {:>4} us", n, angle, us)?,
+        _ => write!(out, "{}: detached       ", n)?,
+    }
+    write!(out, "  cal {}/{}/{} us", min_us, center_us, max_us)
+}
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+            }
+        }
+
+        fn status(&mut self, shell: &mut Shell) -> EnvResult {
+            let pot_servo = self.pot_servo.lock(|pot_servo| *pot_servo);
+            self.servos.lock(|servos| {
+                shell.write_str(CR)?;
+                for n in 1..=SERVOS {
+                    if let Some(servo) = servos.get(n) {
+                        write_servo(shell, n, servo)?;
+                        shell.write_str(CR)?;
+                    }
+                }
+                let slew = servos.get(1).map(Servo::slew).unwrap_or_default();
+                write!(shell, "slew: {} us/s{}", slew, CR)
+            })?;
+            match pot_servo {
+                Some(n) => write!(shell, "pot: servo {}{}", n, CR)?,
+                None => write!(shell, "pot: off{}", CR)?,
+            }
+            Ok(())
+        }
+
+        /// Applies `command` to servo `n`, reports a bad number.
+        fn with_servo(
+            &mut self,
+            shell: &mut Shell,
+            n: &str,
+            command: impl FnOnce(&mut Servo),
+        ) -> EnvResult {
+            let done = match n.parse::<usize>() {
+                Ok(n) => self.servos.lock(|servos| servos.get_mut(n).map(command)),
+                Err(_) => None,
+            };
+            match done {
+                Some(()) => shell.write_str(CR)?,
+                None => write!(shell, "{0:}servos are 1..{1:}{0:}", CR, SERVOS)?,
+            }
+            Ok(())
+        }
+
+        fn servo_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            match (args.next(), args.next(), args.next()) {
+                (None, _, _) => self.status(shell)?,
+                (Some("slew"), Some(slew), None) => match slew.parse::<u16>() {
+                    Ok(slew) => {
+                        self.servos.lock(|servos| {
+                            for n in 1..=SERVOS {
+                                if let Some(servo) = servos.get_mut(n) {
+                                    servo.set_slew(slew);
+                                }
+                            }
+                        });
+                        shell.write_str(CR)?;
+                    }
+                    Err(_) => write!(shell, "{0:}slew must be 0..65535 us/s{0:}", CR)?,
+                },
+                (Some("pot"), Some(target), None) => {
+                    let pot_servo = match target.parse::<usize>() {
+                        Ok(n @ 1..=SERVOS) => Some(Some(n)),
+                        _ if target == "off" => Some(None),
+                        _ => None,
+                    };
+                    match pot_servo {
+                        Some(pot_servo) => {
+                            self.pot_servo.lock(|current| *current = pot_servo);
+                            shell.write_str(CR)?;
+                        }
+                        None => write!(shell, "{0:}pot takes 1..{1:} or off{0:}", CR, SERVOS)?,
+                    }
+                }
+                (Some(n), Some("off"), None) => self.with_servo(shell, n, Servo::detach)?,
+                (Some(n), Some("us"), Some(us)) => match us.parse::<u16>() {
+                    Ok(us) => self.with_servo(shell, n, |servo| servo.set_us(us))?,
+                    Err(_) => write!(shell, "{0:}invalid pulse width{0:}", CR)?,
+                },
+                (Some(n), Some("cal"), Some(min)) => {
+                    let (center, max) = (args.next().unwrap_or(""), args.next().unwrap_or(""));
+                    let calibration = match (min.parse(), center.parse(), max.parse()) {
+                        (Ok(min_us), Ok(center_us), Ok(max_us)) => Some(Calibration {
+                            min_us,
+                            center_us,
+                            max_us,
+                        }),
+                        _ => None,
+                    };
+                    match calibration {
+                        Some(calibration) if calibration.is_valid() => {
+                            self.with_servo(shell, n, |servo| {
+                                servo.set_calibration(calibration);
+                            })?
+                        }
+                        _ => write!(shell, "{0:}needs min < center < max < 20000{0:}", CR)?,
+                    }
+                }
+                (Some(n), Some(angle), None) => match angle.parse::<u16>() {
+                    Ok(angle @ 0..=MAX_ANGLE) => {
+                        self.with_servo(shell, n, |servo| servo.set_angle(angle))?
+                    }
+                    _ => write!(shell, "{0:}angle must be 0..{1:}{0:}", CR, MAX_ANGLE)?,
+                },
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "servo" => self.servo_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            shell.write_str(SHELL_PROMPT)?;
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "help", "servo"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Servo Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tservo                            Print the servos\r\n\
+\tservo <n> <deg>                  Move servo 1..4 to 0..180 degrees\r\n\
+\tservo <n> us <us>                Set the pulse width\r\n\
+\tservo <n> off                    Stop the pulses\r\n\
+\tservo <n> cal <min> <c> <max>    Set pulse widths of 0, 90, 180 deg\r\n\
+\tservo slew <us/s>                Limit the speed, 0 for none\r\n\
+\tservo pot <n|off>                Let the pot move a servo\r\n\
+\tclear                            Clear screen\r\n\
+\thelp                             Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        servos: Servos,
+        samples: SampleBuffer<32>,
+        /// Servo following the pot.
+        pot_servo: Option<usize>,
+    }
+
+    #[local]
+    struct Local {
+        sampler: TriggeredSampler,
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(FRAME_MS.millis());
+        timer.listen();
+
+        let tim1 = ctx.device.TIM1.pwm(FRAME_HZ.Hz(), &mut rcc);
+        let calibration = Calibration::default();
+        let servos = Servos {
+            s1: ServoPin::new(tim1.bind_pin(gpio_a.pa8), calibration),
+            s2: ServoPin::new(tim1.bind_pin(gpio_b.pb3), calibration),
+            s3: ServoPin::new(tim1.bind_pin(gpio_b.pb6), calibration),
+            s4: ServoPin::new(tim1.bind_pin(gpio_a.pa11), calibration),
+        };
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+        adc.calibrate();
+        let pot_input = gpio_a.pa0;
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), channel_of(&pot_input));
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        (
+            Shared {
+                servos,
+                samples: SampleBuffer::new(FILTER_SHIFT),
+                pot_servo: None,
+            },
+            Local {
+                sampler,
+                timer,
+                shell,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 3, local = [sampler], shared = [samples])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.samples.lock(|samples| samples.push(raw));
+        }
+    }
+
+    /// Once per servo frame: the pot demo, then the slew.
+    #[task(binds = TIM17, priority = 2, local = [timer], shared = [servos, samples, pot_servo])]
+    fn frame_tick(ctx: frame_tick::Context) {
+        let (mut servos, mut samples, mut pot_servo) =
+            (ctx.shared.servos, ctx.shared.samples, ctx.shared.pot_servo);
+        let pot = samples.lock(|samples| samples.filtered()) as u32;
+        let angle = (pot.min(ADC_MAX) * MAX_ANGLE as u32 / ADC_MAX) as u16;
+        let pot_servo = pot_servo.lock(|pot_servo| *pot_servo);
+        servos.lock(|servos| {
+            if let Some(servo) = pot_servo.and_then(|n| servos.get_mut(n)) {
+                servo.set_angle(angle);
+            }
+            servos.tick(FRAME_MS);
+        });
+        ctx.local.timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell], shared = [servos, pot_servo])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
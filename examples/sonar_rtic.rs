@@ -0,0 +1,401 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{
+    enable_temperature_sensor, temperature_dc, TriggeredSampler, TEMPERATURE_CHANNEL,
+};
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use c031c6_nucleo_robo_rust::sonar::Hcsr04;
+use c031c6_nucleo_robo_rust::ui::{self, FONT, NUMERIC_16X32, NUMERIC_8X8};
+use robo_core::fixed::{Fixed, Unit};
+use robo_core::sample::SampleBuffer;
+use robo_core::sonar::{self, Reading, Sonar, MAX_WINDOW};
+
+/// Temperature conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 10;
+const FILTER_SHIFT: u8 = 3;
+/// Analog supply of the Nucleo board.
+const VDDA_MV: u32 = 3_300;
+/// Period of the display and temperature updates.
+const UI_TICK_MS: u32 = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AppState {
+    reading: Option<Reading>,
+    temp_dc: i16,
+}
+
+pub struct App {
+    state: AppState,
+    /// Readings are printed to the shell while set.
+    watching: bool,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            state: AppState {
+                reading: None,
+                temp_dc: 0,
+            },
+            watching: false,
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+}
+
+fn write_reading(out: &mut impl Write, reading: Option<Reading>) -> core::fmt::Result {
+    match reading {
+        Some(Reading::Distance(mm)) => write!(out, "{} mm", mm),
+        Some(Reading::NoEcho) => out.write_str("no echo"),
+        Some(Reading::Timeout) => out.write_str("timeout"),
+        None => out.write_str("-"),
+    }
+}
+
+widget_group! {
+    UI<&AppState>,
+    {
+        bg: GlyphIcon, ui::BLANK, 0, Point::zero();
+        title: Label<16>, FONT, "     SONAR      ", Point::zero(), Size::new(8, 8);
+        distance: Label<8>, NUMERIC_16X32, "        ", Point::new(0, 8*2), Size::new(16, 32);
+        status: Label<10>, FONT, "          ", Point::new(0, 8*7), Size::new(8, 8);
+        temp: Label<6>, NUMERIC_8X8, "      ", Point::new(8*10, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut UI, state: &AppState| {
+        match state.reading {
+            Some(Reading::Distance(mm)) => {
+                write!(widget.distance, "{}", Fixed::int(mm as i32).width(8)).ok();
+                write!(widget.status, "MM        ").ok();
+            }
+            Some(Reading::NoEcho) => {
+                write!(widget.distance, "    ----").ok();
+                write!(widget.status, "NO ECHO   ").ok();
+            }
+            Some(Reading::Timeout) => {
+                write!(widget.distance, "        ").ok();
+                write!(widget.status, "NO SENSOR ").ok();
+            }
+            None => {}
+        }
+        let temp = Fixed::new(state.temp_dc as i32, 1).unit(Unit::Celsius);
+        write!(widget.temp, "{}", temp.width(6)).ok();
+    }
+}
+
+pub const SPRITES: [(FlashSprite, Glyphs); 4] = [
+    ui::FONT_SPRITE,
+    ui::BLANK_SPRITE,
+    ui::NUMERIC_16X32_SPRITE,
+    ui::NUMERIC_8X8_SPRITE,
+];
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        Reading(Reading),
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => shell.spin(self),
+                EnvSignal::Reading(reading) => self.watch(shell, reading),
+            }
+        }
+
+        fn watch(&mut self, shell: &mut Shell, reading: Reading) -> EnvResult {
+            if self.app.lock(|app| app.watching) {
+                write_reading(shell, Some(reading))?;
+                shell.write_str(CR)?;
+            }
+            Ok(())
+        }
+
+        fn range_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            match (args.next(), args.next()) {
+                (None, _) => {
+                    let (filtered, last, temp_dc, window) = self.sonar.lock(|sonar| {
+                        (
+                            sonar.reading(),
+                            sonar.last(),
+                            sonar.temperature(),
+                            sonar.config().window,
+                        )
+                    });
+                    shell.write_str(CR)?;
+                    write!(shell, "median of {}: ", window)?;
+                    write_reading(shell, filtered)?;
+                    write!(shell, "{}last:        ", CR)?;
+                    write_reading(shell, last)?;
+                    let temp = Fixed::new(temp_dc as i32, 1);
+                    write!(shell, "{0:}air:         {1:} C{0:}", CR, temp)?;
+                }
+                (Some("watch"), None) => {
+                    self.app.lock(|app| app.watching = true);
+                    write!(shell, "{0:}watching readings, Ctrl+C stops{0:}", CR)?;
+                }
+                (Some("window"), Some(window)) => match window.parse::<u8>() {
+                    Ok(window) if (1..=MAX_WINDOW).contains(&(window as usize)) => {
+                        self.sonar.lock(|sonar| {
+                            sonar.set_config(sonar::Config {
+                                window,
+                                ..*sonar.config()
+                            })
+                        });
+                        shell.write_str(CR)?;
+                    }
+                    _ => write!(shell, "{0:}window must be 1..{1:}{0:}", CR, MAX_WINDOW)?,
+                },
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            match cmd {
+                "clear" => shell.clear()?,
+                "range" => self.range_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            if !self.app.lock(|app| app.watching) {
+                shell.write_str(SHELL_PROMPT)?;
+            }
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.app.lock(|app| app.watching = false);
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "help", "range"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Sonar Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\trange              Print filtered and last distance, air temperature\r\n\
+\trange watch        Print every reading, Ctrl+C stops\r\n\
+\trange window <n>   Set the median window\r\n\
+\tclear              Clear screen\r\n\
+\thelp               Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        app: App,
+        sonar: Sonar,
+        samples: SampleBuffer<8>,
+    }
+
+    #[local]
+    struct Local {
+        ranger: Hcsr04,
+        sampler: TriggeredSampler,
+        display: SpriteDisplay<PanelCanvas<Display>, { SPRITES.len() }>,
+        ui: UI,
+        timer: Timer<stm32::TIM17>,
+        shell: shell::Shell,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+
+        let mut timer = ctx.device.TIM17.timer(&mut rcc);
+        timer.start(UI_TICK_MS.millis());
+        timer.listen();
+
+        let ranger = Hcsr04::new(
+            ctx.device.TIM1.timer(&mut rcc),
+            (gpio_a.pa8, gpio_b.pb6),
+            &rcc,
+        );
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.calibrate();
+        enable_temperature_sensor(&mut adc);
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), TEMPERATURE_CHANNEL);
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let display = SpriteDisplay::new(PanelCanvas::new(display), SPRITES);
+
+        (
+            Shared {
+                app: App::new(),
+                sonar: Sonar::new(sonar::Config::default()),
+                samples: SampleBuffer::new(FILTER_SHIFT),
+            },
+            Local {
+                ranger,
+                sampler,
+                display,
+                ui: UI::new(),
+                timer,
+                shell,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    /// End of a measurement period, the next trigger pulse goes out now.
+    #[task(binds = TIM1_BRK_UP_TRG_COM, priority = 3, local = [ranger], shared = [sonar])]
+    fn range_update(mut ctx: range_update::Context) {
+        let capture = ctx.local.ranger.on_update();
+        let reading = ctx.shared.sonar.lock(|sonar| sonar.update(capture));
+        range_event::spawn(reading).ok();
+    }
+
+    #[task(binds = ADC, priority = 3, local = [sampler], shared = [samples])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.samples.lock(|samples| samples.push(raw));
+        }
+    }
+
+    /// Keeps the filtered reading for the display and shows it to the shell.
+    #[task(capacity = 4, shared = [app, sonar])]
+    fn range_event(ctx: range_event::Context, reading: Reading) {
+        let (mut app, mut sonar) = (ctx.shared.app, ctx.shared.sonar);
+        let filtered = sonar.lock(|sonar| sonar.reading());
+        app.lock(|app| app.state.reading = filtered);
+        env::spawn(shell::EnvSignal::Reading(reading)).ok();
+    }
+
+    #[task(binds = TIM17, priority = 2, local = [ui, display, timer], shared = [app, sonar, samples])]
+    fn ui_tick(ctx: ui_tick::Context) {
+        let (mut app, mut sonar, mut samples) =
+            (ctx.shared.app, ctx.shared.sonar, ctx.shared.samples);
+        let temp_dc = samples
+            .lock(|samples| samples.latest().map(|_| samples.filtered()))
+            .map(|raw| temperature_dc(raw, VDDA_MV));
+        if let Some(temp_dc) = temp_dc {
+            sonar.lock(|sonar| sonar.set_temperature(temp_dc));
+        }
+
+        let local = ctx.local;
+        app.lock(|app| {
+            if let Some(temp_dc) = temp_dc {
+                app.state.temp_dc = temp_dc;
+            }
+            local.ui.update(app.state());
+        });
+        local.ui.render(local.display);
+        local.timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 1, capacity = 8, local = [shell], shared = [app, sonar])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,441 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use rtic::{self, Mutex};
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::serial::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::button::UserButton;
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use c031c6_nucleo_robo_rust::ui::{self, SplashUI, StatusBarUI};
+use robo_core::alarm::{Level, Thresholds};
+use robo_core::button::{self, Event};
+use robo_core::screen::Splash;
+use robo_core::stats::{PeakHold, Stats, WindowStats};
+use robo_core::status::Status;
+
+/// ADC conversions per second, paced by TIM3.
+const SAMPLE_RATE: u32 = 1_000;
+/// Largest statistics window in samples.
+const MAX_WINDOW: usize = 256;
+/// The status bar flags the alarm while the mean is out of this window.
+const POT_THRESHOLDS: Thresholds = Thresholds::new(410, 3_686);
+const UI_TICK_MS: u32 = 200;
+/// Period of the button debouncing and timeouts.
+const BUTTON_TICK_MS: u32 = 5;
+/// Boot splash duration in UI ticks.
+const SPLASH_TICKS: u16 = (2_000 / UI_TICK_MS) as u16;
+
+pub type SignalStats = WindowStats<MAX_WINDOW>;
+
+struct AppState {
+    window: usize,
+    stats: Stats,
+    peak: Option<PeakHold>,
+    alarm: bool,
+}
+
+pub struct App {
+    state: AppState,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            state: AppState {
+                window: MAX_WINDOW,
+                stats: Stats::default(),
+                peak: None,
+                alarm: false,
+            },
+        }
+    }
+
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    fn update(&mut self, stats: &SignalStats) {
+        self.state.window = stats.window();
+        self.state.stats = stats.stats();
+        self.state.peak = stats.peak();
+        let stats = &self.state.stats;
+        self.state.alarm = stats.count > 0 && POT_THRESHOLDS.level(stats.mean) != Level::Normal;
+    }
+
+    fn alarm(&self) -> bool {
+        self.state.alarm
+    }
+}
+
+widget_group! {
+    UI<&AppState>,
+    {
+        bg: GlyphIcon, ui::BLANK, 0, Point::zero();
+        title: Label<16>, ui::FONT, "STATS           ", Point::new(0, 8), Size::new(8, 8);
+        min_max: Label<16>, ui::FONT, "                ", Point::new(0, 8*2), Size::new(8, 8);
+        mean_rms: Label<16>, ui::FONT, "                ", Point::new(0, 8*3), Size::new(8, 8);
+        std_dev: Label<16>, ui::FONT, "                ", Point::new(0, 8*4), Size::new(8, 8);
+        peak: Label<16>, ui::FONT, "                ", Point::new(0, 8*6), Size::new(8, 8);
+    },
+    |widget: &mut UI, state: &AppState| {
+        let stats = &state.stats;
+        write!(widget.title, "STATS   W:{: >6}", state.window).ok();
+        write!(widget.min_max, "MIN{: >5} MAX{: >4}", stats.min, stats.max).ok();
+        write!(widget.mean_rms, "AVG{: >5} RMS{: >4}", stats.mean, stats.rms).ok();
+        write!(widget.std_dev, "SD {: >5} N{: >6}", stats.std_dev, stats.count).ok();
+        match state.peak {
+            Some(peak) => write!(widget.peak, "PK {: >5} -{: >6}", peak.min, peak.max).ok(),
+            None => write!(widget.peak, "PK          -   ").ok(),
+        };
+    }
+}
+
+pub const SPRITES: [(FlashSprite, Glyphs); 2] = [ui::FONT_SPRITE, ui::BLANK_SPRITE];
+
+mod shell {
+    use super::*;
+
+    pub use ushell::{
+        autocomplete::StaticAutocomplete, control, history::LRUHistory, Environment,
+        Input as ushell_input, ShellError as ushell_error, SpinResult, UShell,
+    };
+
+    pub const CMD_MAX_LEN: usize = 32;
+
+    pub type Autocomplete = StaticAutocomplete<3>;
+    pub type History = LRUHistory<{ CMD_MAX_LEN }, 32>;
+    pub type Uart = Serial<stm32::USART2>;
+    pub type Shell = UShell<Uart, Autocomplete, History, { CMD_MAX_LEN }>;
+
+    pub enum EnvSignal {
+        Shell,
+        Stream,
+        ButtonClick,
+    }
+
+    pub type Env<'a> = super::app::env::SharedResources<'a>;
+    pub type EnvResult = SpinResult<Uart, ()>;
+
+    impl Env<'_> {
+        pub fn on_signal(&mut self, shell: &mut Shell, sig: EnvSignal) -> EnvResult {
+            match sig {
+                EnvSignal::Shell => {
+                    self.status.lock(|status| status.received());
+                    shell.spin(self)
+                }
+                EnvSignal::Stream => self.stream(shell),
+                EnvSignal::ButtonClick => self.button_click(),
+            }
+        }
+
+        fn button_click(&mut self) -> EnvResult {
+            self.stats.lock(|stats| stats.reset_peak());
+            Ok(())
+        }
+
+        fn stream(&mut self, shell: &mut Shell) -> EnvResult {
+            if self.streaming.lock(|streaming| *streaming) {
+                let stats = self.stats.lock(|stats| stats.stats());
+                self.status.lock(|status| status.sent());
+                write!(
+                    shell,
+                    "{} {} {} {} {}{}",
+                    stats.min, stats.max, stats.mean, stats.rms, stats.std_dev, CR
+                )?;
+            }
+            Ok(())
+        }
+
+        fn stats_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            let mut args = args.split_whitespace();
+            match (args.next(), args.next()) {
+                (None, _) => {
+                    let (window, stats, peak) = self
+                        .stats
+                        .lock(|stats| (stats.window(), stats.stats(), stats.peak()));
+                    write!(shell, "{0:}window:  {1:}/{2:}{0:}", CR, stats.count, window)?;
+                    write!(shell, "min:     {}{}", stats.min, CR)?;
+                    write!(shell, "max:     {}{}", stats.max, CR)?;
+                    write!(shell, "mean:    {}{}", stats.mean, CR)?;
+                    write!(shell, "rms:     {}{}", stats.rms, CR)?;
+                    write!(shell, "std-dev: {}{}", stats.std_dev, CR)?;
+                    if let Some(peak) = peak {
+                        write!(shell, "peak:    {}..{}{}", peak.min, peak.max, CR)?;
+                    }
+                }
+                (Some("reset"), None) => {
+                    self.stats.lock(|stats| {
+                        stats.reset_peak();
+                        stats.clear();
+                    });
+                    write!(shell, "{0:}statistics reset{0:}", CR)?;
+                }
+                (Some("window"), Some(len)) => match len.parse::<usize>() {
+                    Ok(len) if len > 0 && len <= MAX_WINDOW => {
+                        self.stats.lock(|stats| stats.set_window(len));
+                        write!(shell, "{0:}window: {1:}{0:}", CR, len)?;
+                    }
+                    _ => write!(shell, "{0:}window must be 1..{1:}{0:}", CR, MAX_WINDOW)?,
+                },
+                (Some("stream"), Some(mode @ ("on" | "off"))) => {
+                    self.streaming.lock(|streaming| *streaming = mode == "on");
+                    write!(shell, "{0:}min max mean rms std-dev{0:}", CR)?;
+                }
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+
+        fn help_cmd(&mut self, shell: &mut Shell, args: &str) -> EnvResult {
+            match args {
+                _ => shell.write_str(HELP)?,
+            }
+            Ok(())
+        }
+    }
+
+    impl Environment<Uart, Autocomplete, History, (), { CMD_MAX_LEN }> for Env<'_> {
+        fn command(&mut self, shell: &mut Shell, cmd: &str, args: &str) -> EnvResult {
+            self.status.lock(|status| status.sent());
+            match cmd {
+                "clear" => shell.clear()?,
+                "stats" => self.stats_cmd(shell, args)?,
+                "help" => self.help_cmd(shell, args)?,
+                "" => shell.write_str(CR)?,
+                _ => write!(shell, "{0:}unsupported command: \"{1:}\"{0:}", CR, cmd)?,
+            }
+            shell.write_str(SHELL_PROMPT)?;
+            Ok(())
+        }
+
+        fn control(&mut self, shell: &mut Shell, code: u8) -> EnvResult {
+            match code {
+                control::CTRL_C => {
+                    self.streaming.lock(|streaming| *streaming = false);
+                    shell.write_str(CR)?;
+                    shell.write_str(SHELL_PROMPT)?;
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    pub const AUTOCOMPLETE: Autocomplete = StaticAutocomplete(["clear", "help", "stats"]);
+
+    const SHELL_PROMPT: &str = "#> ";
+    const CR: &str = "\r\n";
+    const HELP: &str = "\r\n\
+Signal Stats Shell v.1\r\n\r\n\
+USAGE:\r\n\
+\tcommand [args]\r\n\r\n\
+COMMANDS:\r\n\
+\tstats                 Print statistics of the window\r\n\
+\tstats reset           Clear window and peak hold\r\n\
+\tstats window <n>      Set window length in samples\r\n\
+\tstats stream <on|off> Stream statistics, Ctrl+C stops\r\n\
+\tclear                 Clear screen\r\n\
+\thelp                  Print this message\r\n\
+";
+}
+
+#[rtic::app(device = stm32, peripherals = true, dispatchers = [USART1])]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        app: App,
+        stats: SignalStats,
+        streaming: bool,
+        status: Status,
+        button: UserButton,
+    }
+
+    #[local]
+    struct Local {
+        exti: stm32::EXTI,
+        sampler: TriggeredSampler,
+        display: SpriteDisplay<PanelCanvas<Display>, { SPRITES.len() }>,
+        ui: UI,
+        status_bar: StatusBarUI,
+        splash: Splash,
+        splash_ui: SplashUI,
+        ui_timer: Timer<stm32::TIM17>,
+        button_timer: Timer<stm32::TIM16>,
+        shell: shell::Shell,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+        let gpio_c = ctx.device.GPIOC.split(&mut rcc);
+
+        let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
+        ui_timer.start(UI_TICK_MS.millis());
+        ui_timer.listen();
+
+        let mut button_timer = ctx.device.TIM16.timer(&mut rcc);
+        button_timer.start(BUTTON_TICK_MS.millis());
+        button_timer.listen();
+
+        let mut exti = ctx.device.EXTI;
+        let button = UserButton::new(gpio_c.pc13, &mut exti, button::Config::default());
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+
+        let mut serial = ctx
+            .device
+            .USART2
+            .usart((gpio_a.pa2, gpio_a.pa3), Config::default(), &mut rcc)
+            .unwrap();
+        serial.listen(hal::serial::Event::Rxne);
+
+        writeln!(serial, "Hello from STM32C031\r\n").unwrap();
+
+        let shell = shell::UShell::new(serial, shell::AUTOCOMPLETE, shell::LRUHistory::default());
+
+        let pot_input = gpio_a.pa0;
+        adc.calibrate();
+
+        let mut sampler =
+            TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), channel_of(&pot_input));
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let controller = PanelCanvas::new(display);
+        let display = SpriteDisplay::new(controller, SPRITES);
+        let ui = UI::new();
+
+        (
+            Shared {
+                app: App::new(),
+                stats: SignalStats::new(MAX_WINDOW),
+                streaming: false,
+                status: Status::new(UI_TICK_MS, ui::SHORT_VERSION),
+                button,
+            },
+            Local {
+                exti,
+                sampler,
+                display,
+                ui,
+                status_bar: StatusBarUI::new(),
+                splash: Splash::new(SPLASH_TICKS),
+                splash_ui: SplashUI::new(),
+                ui_timer,
+                button_timer,
+                shell,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 3, local = [sampler], shared = [stats])]
+    fn adc_sample(mut ctx: adc_sample::Context) {
+        if let Some((_, raw)) = ctx.local.sampler.read() {
+            ctx.shared.stats.lock(|stats| stats.push(raw));
+        }
+    }
+
+    #[task(binds = TIM17, local = [ui, status_bar, splash, splash_ui, ui_timer, display], shared = [app, stats, status])]
+    fn ui_timer_tick(ctx: ui_timer_tick::Context) {
+        let mut app = ctx.shared.app;
+        let alarm = (&mut app, ctx.shared.stats).lock(|app, stats| {
+            app.update(stats);
+            app.alarm()
+        });
+        let mut status = ctx.shared.status;
+        let status = status.lock(|status| {
+            status.alarm = alarm;
+            status.tick();
+            *status
+        });
+
+        let local = ctx.local;
+        if local.splash.tick() {
+            local.ui.invalidate();
+            local.status_bar.invalidate();
+        }
+        if local.splash.is_done() {
+            app.lock(|app| local.ui.update(app.state()));
+            local.ui.render(local.display);
+            // The bar goes on top of the screen background
+            local.status_bar.update(&status);
+            local.status_bar.render(local.display);
+        } else {
+            local.splash_ui.update(local.splash);
+            local.splash_ui.render(local.display);
+        }
+        env::spawn(shell::EnvSignal::Stream).ok();
+        local.ui_timer.clear_irq();
+    }
+
+    #[task(binds = USART2, priority = 1)]
+    fn serial_callback(_: serial_callback::Context) {
+        env::spawn(shell::EnvSignal::Shell).ok();
+    }
+
+    #[task(priority = 2, capacity = 8, local = [shell], shared = [stats, streaming, status])]
+    fn env(ctx: env::Context, sig: shell::EnvSignal) {
+        let mut env = ctx.shared;
+        env.on_signal(ctx.local.shell, sig).ok();
+    }
+
+    #[task(binds = EXTI4_15, local = [exti], shared = [button])]
+    fn button_edge(mut ctx: button_edge::Context) {
+        let exti = ctx.local.exti;
+        ctx.shared.button.lock(|button| button.on_edge(exti));
+    }
+
+    #[task(binds = TIM16, local = [button_timer], shared = [button])]
+    fn button_tick(mut ctx: button_tick::Context) {
+        if ctx.shared.button.lock(|button| button.tick(BUTTON_TICK_MS)) == Some(Event::Short) {
+            env::spawn(shell::EnvSignal::ButtonClick).ok();
+        }
+        ctx.local.button_timer.clear_irq();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
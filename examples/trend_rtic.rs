@@ -0,0 +1,254 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use panic_semihosting as _;
+use stm32c0xx_hal as hal;
+
+use hal::analog::adc;
+use hal::prelude::*;
+use hal::stm32;
+use hal::time::*;
+use hal::timer::*;
+
+use klaptik::*;
+
+use c031c6_nucleo_robo_rust::adc::{channel_of, TriggeredSampler};
+use c031c6_nucleo_robo_rust::button::UserButton;
+use c031c6_nucleo_robo_rust::panel::{self, Display, PanelCanvas};
+use robo_core::button::{self, Event};
+use robo_core::chart::{Chart, Scale, Style};
+use robo_core::page::{self, TextLine};
+use robo_core::sample::SampleBuffer;
+
+/// ADC conversions per second and channel, paced by TIM3. Every trigger
+/// converts both channels.
+const SAMPLE_RATE: u32 = 1_000;
+/// Smoothing before the samples are decimated into the charts.
+const FILTER_SHIFT: u8 = 4;
+/// One chart column per UI tick.
+const UI_TICK_MS: u32 = 50;
+/// Period of the button debouncing and timeouts.
+const BUTTON_TICK_MS: u32 = 5;
+/// Chart history, one sample per display column.
+const HISTORY: usize = 128;
+const CHART_HEIGHT: u8 = 24;
+const ADC_MAX: u16 = 4_095;
+
+/// Chart modes cycled with a click of the button.
+const MODES: [(Style, Scale); 4] = [
+    (Style::Line, Scale::Auto),
+    (
+        Style::Line,
+        Scale::Fixed {
+            min: 0,
+            max: ADC_MAX,
+        },
+    ),
+    (Style::Bar, Scale::Auto),
+    (
+        Style::Bar,
+        Scale::Fixed {
+            min: 0,
+            max: ADC_MAX,
+        },
+    ),
+];
+
+const FONT: &[u8] = include_bytes!("assets/font8x8.bin");
+const FONT_ALPHABET: &[u8] = b" !%-./0123456789:<=>ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// A channel trend: a title line followed by its chart.
+pub struct Trend {
+    name: &'static str,
+    page: u8,
+    chart: Chart<HISTORY>,
+}
+
+impl Trend {
+    fn new(name: &'static str, page: u8) -> Self {
+        let (style, scale) = MODES[0];
+        let mut chart = Chart::new(CHART_HEIGHT, style, scale);
+        chart.markers = true;
+        Self { name, page, chart }
+    }
+
+    fn set_mode(&mut self, mode: usize) {
+        let (style, scale) = MODES[mode];
+        self.chart.style = style;
+        self.chart.scale = scale;
+        self.chart.markers = style == Style::Line;
+    }
+
+    fn render(&self, display: &mut PanelCanvas<Display>) {
+        let mut title = TextLine::<16>::new();
+        let latest = self.chart.samples().latest().unwrap_or(0);
+        let scale = match self.chart.scale {
+            Scale::Auto => "AUTO",
+            Scale::Fixed { .. } => "FULL",
+        };
+        write!(title, "{: <3} {: >4}    {}", self.name, latest, scale).ok();
+
+        let mut buf = [0; 128];
+        page::text(FONT, FONT_ALPHABET, title.as_bytes(), &mut buf);
+        display.draw(
+            Rectangle::new(Point::new(0, self.page * 8), Size::new(128, 8)),
+            &buf,
+        );
+        for page in 0..self.chart.pages() {
+            self.chart.render_page(page, &mut buf);
+            display.draw(
+                Rectangle::new(Point::new(0, (self.page + 1 + page) * 8), Size::new(128, 8)),
+                &buf,
+            );
+        }
+    }
+}
+
+#[rtic::app(device = stm32, peripherals = true)]
+mod app {
+    use super::*;
+
+    #[shared]
+    struct Shared {
+        pot: SampleBuffer<8>,
+        input: SampleBuffer<8>,
+        mode: usize,
+        button: UserButton,
+    }
+
+    #[local]
+    struct Local {
+        exti: stm32::EXTI,
+        sampler: TriggeredSampler,
+        display: PanelCanvas<Display>,
+        trends: [Trend; 2],
+        ui_timer: Timer<stm32::TIM17>,
+        button_timer: Timer<stm32::TIM16>,
+        pot_channel: u8,
+    }
+
+    #[init]
+    fn init(ctx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let mut rcc = ctx.device.RCC.constrain();
+        let gpio_a = ctx.device.GPIOA.split(&mut rcc);
+        let gpio_b = ctx.device.GPIOB.split(&mut rcc);
+        let gpio_c = ctx.device.GPIOC.split(&mut rcc);
+
+        let mut ui_timer = ctx.device.TIM17.timer(&mut rcc);
+        ui_timer.start(UI_TICK_MS.millis());
+        ui_timer.listen();
+
+        let mut button_timer = ctx.device.TIM16.timer(&mut rcc);
+        button_timer.start(BUTTON_TICK_MS.millis());
+        button_timer.listen();
+
+        let mut exti = ctx.device.EXTI;
+        let button = UserButton::new(gpio_c.pc13, &mut exti, button::Config::default());
+
+        let mut adc = ctx.device.ADC.constrain(&mut rcc);
+        adc.set_sample_time(adc::SampleTime::T_160);
+        adc.set_precision(adc::Precision::B_12);
+        adc.set_oversampling_ratio(adc::OversamplingRatio::X_16);
+        adc.set_oversampling_shift(20);
+        adc.oversampling_enable(true);
+
+        // The pot on A0 and an arbitrary signal on A1
+        let pot_input = gpio_a.pa0;
+        let analog_input = gpio_a.pa1;
+        let pot_channel = channel_of(&pot_input);
+        let input_channel = channel_of(&analog_input);
+        adc.calibrate();
+
+        let mut sampler = TriggeredSampler::new(adc, ctx.device.TIM3.timer(&mut rcc), pot_channel);
+        sampler.set_channels(&[pot_channel, input_channel]);
+        sampler.start(Hertz::Hz(SAMPLE_RATE));
+
+        let mut delay = ctx.device.TIM14.delay(&mut rcc);
+        let display = panel::connect(
+            panel::Wiring {
+                spi: ctx.device.SPI,
+                i2c: ctx.device.I2C,
+                sck: gpio_a.pa5,
+                mosi: gpio_a.pa7,
+                dc: gpio_a.pa9,
+                cs: gpio_a.pa15,
+                rst: gpio_a.pa10,
+                scl: gpio_b.pb8,
+                sda: gpio_b.pb9,
+            },
+            &mut delay,
+            &mut rcc,
+        );
+        let display = PanelCanvas::new(display);
+
+        (
+            Shared {
+                pot: SampleBuffer::new(FILTER_SHIFT),
+                input: SampleBuffer::new(FILTER_SHIFT),
+                mode: 0,
+                button,
+            },
+            Local {
+                exti,
+                sampler,
+                display,
+                trends: [Trend::new("POT", 0), Trend::new("IN", 4)],
+                ui_timer,
+                button_timer,
+                pot_channel,
+            },
+            init::Monotonics(),
+        )
+    }
+
+    #[task(binds = ADC, priority = 2, local = [sampler, pot_channel], shared = [pot, input])]
+    fn adc_sample(ctx: adc_sample::Context) {
+        match ctx.local.sampler.read() {
+            Some((channel, raw)) if channel == *ctx.local.pot_channel => {
+                let mut pot = ctx.shared.pot;
+                pot.lock(|pot| pot.push(raw));
+            }
+            Some((_, raw)) => {
+                let mut input = ctx.shared.input;
+                input.lock(|input| input.push(raw));
+            }
+            None => {}
+        }
+    }
+
+    #[task(binds = TIM17, local = [display, trends, ui_timer], shared = [pot, input, mode])]
+    fn ui_timer_tick(ctx: ui_timer_tick::Context) {
+        let (pot, input, mode) = (ctx.shared.pot, ctx.shared.input, ctx.shared.mode)
+            .lock(|pot, input, mode| (pot.filtered(), input.filtered(), *mode));
+        for (trend, value) in ctx.local.trends.iter_mut().zip([pot, input]) {
+            trend.chart.push(value);
+            trend.set_mode(mode);
+            trend.render(ctx.local.display);
+        }
+        ctx.local.ui_timer.clear_irq();
+    }
+
+    #[task(binds = EXTI4_15, local = [exti], shared = [button])]
+    fn button_edge(mut ctx: button_edge::Context) {
+        let exti = ctx.local.exti;
+        ctx.shared.button.lock(|button| button.on_edge(exti));
+    }
+
+    #[task(binds = TIM16, local = [button_timer], shared = [button, mode])]
+    fn button_tick(ctx: button_tick::Context) {
+        let (mut button, mut mode) = (ctx.shared.button, ctx.shared.mode);
+        if button.lock(|button| button.tick(BUTTON_TICK_MS)) == Some(Event::Short) {
+            mode.lock(|mode| *mode = (*mode + 1) % MODES.len());
+        }
+        ctx.local.button_timer.clear_irq();
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Prints telemetry frames read from a serial port or stdin.
+//!
+//! ```sh
+//! stty -F /dev/ttyACM0 115200 raw
+//! cargo run --target x86_64-unknown-linux-gnu --example telemetry_dump -- /dev/ttyACM0
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read};
+
+use robo_core::telemetry::Decoder;
+
+fn main() -> io::Result<()> {
+    let mut input: Box<dyn Read> = match std::env::args().nth(1) {
+        Some(path) => Box::new(File::open(path)?),
+        None => Box::new(io::stdin()),
+    };
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 256];
+    loop {
+        let len = input.read(&mut buf)?;
+        if len == 0 {
+            return Ok(());
+        }
+        for packet in decoder.feed(&buf[..len]) {
+            match packet {
+                Ok(packet) => println!(
+                    "{:5} {:10} ch{:<2} {:?}",
+                    packet.seq,
+                    packet.timestamp,
+                    packet.channel,
+                    packet.values()
+                ),
+                Err(err) => eprintln!("dropped frame: {}", err),
+            }
+        }
+        if decoder.lost() > 0 {
+            eprintln!("lost: {}", decoder.lost());
+        }
+    }
+}
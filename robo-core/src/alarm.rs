@@ -0,0 +1,143 @@
+/// Window a channel reading is expected to stay in, in raw ADC counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Thresholds {
+    pub low: u16,
+    pub high: u16,
+}
+
+impl Thresholds {
+    pub const fn new(low: u16, high: u16) -> Self {
+        Self { low, high }
+    }
+
+    pub const fn level(&self, value: u16) -> Level {
+        if value < self.low {
+            Level::Low
+        } else if value > self.high {
+            Level::High
+        } else {
+            Level::Normal
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Normal,
+    Low,
+    High,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlarmEvent {
+    pub channel: u8,
+    pub level: Level,
+    pub value: u16,
+}
+
+impl AlarmEvent {
+    pub fn is_alarm(&self) -> bool {
+        self.level != Level::Normal
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Watch {
+    channel: u8,
+    thresholds: Thresholds,
+    level: Level,
+}
+
+/// Tracks the window state of up to `N` channels and reports crossings.
+///
+/// The analog watchdog only fires while a conversion is out of window, so
+/// readings from regular sampling are fed here as well to notice the return
+/// to normal.
+pub struct Monitor<const N: usize> {
+    watches: [Option<Watch>; N],
+}
+
+impl<const N: usize> Monitor<N> {
+    pub const fn new() -> Self {
+        Self { watches: [None; N] }
+    }
+
+    /// Starts watching `channel` or replaces its thresholds. Returns the slot
+    /// used, or `None` when all slots are taken.
+    pub fn watch(&mut self, channel: u8, thresholds: Thresholds) -> Option<usize> {
+        let slot = self
+            .slot(channel)
+            .or_else(|| self.watches.iter().position(Option::is_none))?;
+        self.watches[slot] = Some(Watch {
+            channel,
+            thresholds,
+            level: Level::Normal,
+        });
+        Some(slot)
+    }
+
+    pub fn unwatch(&mut self, channel: u8) -> Option<usize> {
+        let slot = self.slot(channel)?;
+        self.watches[slot] = None;
+        Some(slot)
+    }
+
+    /// Channel watched in `slot`.
+    pub fn channel(&self, slot: usize) -> Option<u8> {
+        self.watches.get(slot)?.map(|watch| watch.channel)
+    }
+
+    pub fn thresholds(&self, channel: u8) -> Option<Thresholds> {
+        let slot = self.slot(channel)?;
+        self.watches[slot].map(|watch| watch.thresholds)
+    }
+
+    pub fn level(&self, channel: u8) -> Option<Level> {
+        let slot = self.slot(channel)?;
+        self.watches[slot].map(|watch| watch.level)
+    }
+
+    /// True while any watched channel is out of its window.
+    pub fn alarmed(&self) -> bool {
+        self.watches
+            .iter()
+            .flatten()
+            .any(|watch| watch.level != Level::Normal)
+    }
+
+    /// Feeds a new reading, returning an event when the channel crossed into
+    /// or out of its window.
+    pub fn update(&mut self, channel: u8, value: u16) -> Option<AlarmEvent> {
+        let slot = self.slot(channel)?;
+        let watch = self.watches[slot].as_mut()?;
+        let level = watch.thresholds.level(value);
+        if level == watch.level {
+            return None;
+        }
+        watch.level = level;
+        Some(AlarmEvent {
+            channel,
+            level,
+            value,
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u8, Thresholds, Level)> + '_ {
+        self.watches
+            .iter()
+            .flatten()
+            .map(|watch| (watch.channel, watch.thresholds, watch.level))
+    }
+
+    fn slot(&self, channel: u8) -> Option<usize> {
+        self.watches
+            .iter()
+            .position(|watch| matches!(watch, Some(watch) if watch.channel == channel))
+    }
+}
+
+impl<const N: usize> Default for Monitor<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,202 @@
+//! Push button debouncing and click classification.
+//!
+//! Raw level changes come in through `edge`, e.g. from an EXTI interrupt on
+//! both edges, or through `sample` when the pin is polled. `poll` runs from
+//! a periodic timer: it accepts levels that stayed stable for the debounce
+//! time and turns the timing of presses into events:
+//!
+//! - `Short`: pressed and released before the long press time, and no
+//!   second press within the double click window
+//! - `Double`: two short presses within the double click window
+//! - `Long`: held for the long press time, sent while still held
+//! - `Repeat(n)`: held on after `Long`, every repeat interval
+//!
+//! Times are milliseconds from any wrapping clock.
+
+/// Timing of a button, in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// A new level must last this long to count.
+    pub debounce_ms: u32,
+    /// Pause between the release of a short press and the next press to
+    /// count as double click. `0` reports short presses on release and
+    /// never reports double clicks.
+    pub double_ms: u32,
+    pub long_ms: u32,
+    /// Period of `Repeat` after `Long`, `0` for none.
+    pub repeat_ms: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 20,
+            double_ms: 250,
+            long_ms: 800,
+            repeat_ms: 200,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Short,
+    Double,
+    Long,
+    /// Repeats since `Long`, starting at 1.
+    Repeat(u16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// Pressed, `second` if a short press is waiting for a double click.
+    Pressed {
+        since: u32,
+        second: bool,
+    },
+    /// Held past `Long`, `next` is the time of the next repeat.
+    Held {
+        next: u32,
+        repeats: u16,
+    },
+    /// Short press released at `since`, waiting for a second one.
+    Released {
+        since: u32,
+    },
+}
+
+pub struct Button {
+    config: Config,
+    raw: bool,
+    raw_since: u32,
+    pressed: bool,
+    state: State,
+}
+
+impl Button {
+    pub const fn new(config: Config) -> Self {
+        Self {
+            config,
+            raw: false,
+            raw_since: 0,
+            pressed: false,
+            state: State::Idle,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Debounced level.
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Records a raw level change at `now`. Bounces restart the debounce
+    /// time.
+    pub fn edge(&mut self, now: u32, pressed: bool) {
+        self.raw = pressed;
+        self.raw_since = now;
+    }
+
+    /// Polling input: records the level if it changed, then polls.
+    pub fn sample(&mut self, now: u32, pressed: bool) -> Option<Event> {
+        if pressed != self.raw {
+            self.edge(now, pressed);
+        }
+        self.poll(now)
+    }
+
+    /// Advances debouncing and timeouts to `now`. Call at least every
+    /// debounce time, each call returns at most one event.
+    pub fn poll(&mut self, now: u32) -> Option<Event> {
+        let settled = now.wrapping_sub(self.raw_since) >= self.config.debounce_ms;
+        if self.raw != self.pressed && settled {
+            self.pressed = self.raw;
+            // Time from the edge the level settled at, not from the poll
+            let at = self.raw_since;
+            return if self.pressed {
+                self.press(at)
+            } else {
+                self.release(at)
+            };
+        }
+        self.timeout(now)
+    }
+
+    fn press(&mut self, at: u32) -> Option<Event> {
+        let pending = match self.state {
+            State::Released { since } => Some(at.wrapping_sub(since) < self.config.double_ms),
+            _ => None,
+        };
+        self.state = State::Pressed {
+            since: at,
+            second: pending == Some(true),
+        };
+        // A late poll must not turn a single click into a double one
+        (pending == Some(false)).then_some(Event::Short)
+    }
+
+    fn release(&mut self, at: u32) -> Option<Event> {
+        match self.state {
+            State::Pressed { second: true, .. } => {
+                self.state = State::Idle;
+                Some(Event::Double)
+            }
+            State::Pressed { .. } if self.config.double_ms == 0 => {
+                self.state = State::Idle;
+                Some(Event::Short)
+            }
+            State::Pressed { .. } => {
+                self.state = State::Released { since: at };
+                None
+            }
+            _ => {
+                self.state = State::Idle;
+                None
+            }
+        }
+    }
+
+    fn timeout(&mut self, now: u32) -> Option<Event> {
+        let config = self.config;
+        match self.state {
+            State::Released { since } if now.wrapping_sub(since) >= config.double_ms => {
+                self.state = State::Idle;
+                Some(Event::Short)
+            }
+            State::Pressed { since, second } if now.wrapping_sub(since) >= config.long_ms => {
+                if second {
+                    // The first click was a short one after all
+                    self.state = State::Pressed {
+                        since,
+                        second: false,
+                    };
+                    return Some(Event::Short);
+                }
+                self.state = State::Held {
+                    next: since.wrapping_add(config.long_ms + config.repeat_ms),
+                    repeats: 0,
+                };
+                Some(Event::Long)
+            }
+            State::Held { next, repeats }
+                if config.repeat_ms > 0 && now.wrapping_sub(next) < u32::MAX / 2 =>
+            {
+                let repeats = repeats.saturating_add(1);
+                self.state = State::Held {
+                    next: next.wrapping_add(config.repeat_ms),
+                    repeats,
+                };
+                Some(Event::Repeat(repeats))
+            }
+            _ => None,
+        }
+    }
+}
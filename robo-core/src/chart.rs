@@ -0,0 +1,137 @@
+use crate::page::PAGE_HEIGHT;
+use crate::ring::Ring;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Samples joined by vertical spans.
+    Line,
+    /// Columns filled from the bottom up to the sample.
+    Bar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    /// Fits the chart to the samples currently shown.
+    Auto,
+    /// Fixed range, samples outside are clipped to the edges.
+    Fixed { min: u16, max: u16 },
+}
+
+/// Strip chart of the latest `N` samples of a channel, newest on the right.
+///
+/// Renders one SSD1306 page at a time, one column per sample, so a chart
+/// `height` pixels high spans `height / 8` pages.
+pub struct Chart<const N: usize> {
+    samples: Ring<u16, N>,
+    height: u8,
+    pub style: Style,
+    pub scale: Scale,
+    /// Dotted lines at the lowest and highest sample shown.
+    pub markers: bool,
+}
+
+impl<const N: usize> Chart<N> {
+    /// `height` is rounded down to whole pages.
+    pub fn new(height: u8, style: Style, scale: Scale) -> Self {
+        Self {
+            samples: Ring::new(),
+            height: (height / PAGE_HEIGHT as u8).max(1) * PAGE_HEIGHT as u8,
+            style,
+            scale,
+            markers: false,
+        }
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn pages(&self) -> u8 {
+        self.height / PAGE_HEIGHT as u8
+    }
+
+    pub fn push(&mut self, value: u16) {
+        self.samples.push(value);
+    }
+
+    pub fn samples(&self) -> &Ring<u16, N> {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Lowest and highest of the latest `width` samples.
+    pub fn extremes(&self, width: usize) -> Option<(u16, u16)> {
+        self.visible(width).fold(None, |acc, value| match acc {
+            Some((min, max)) => Some((value.min(min), value.max(max))),
+            None => Some((value, value)),
+        })
+    }
+
+    /// Values at the bottom and top edge when drawn `width` columns wide.
+    pub fn range(&self, width: usize) -> (u16, u16) {
+        match self.scale {
+            Scale::Fixed { min, max } => (min, max.max(min)),
+            Scale::Auto => self.extremes(width).unwrap_or((0, 0)),
+        }
+    }
+
+    /// Renders page `page` of the chart, counted from its top, into `buf`
+    /// holding one byte per column.
+    pub fn render_page(&self, page: u8, buf: &mut [u8]) {
+        buf.fill(0);
+        let width = buf.len();
+        let (lo, hi) = self.range(width);
+        let bottom = self.height as u32 - 1;
+        let row = |value: u16| {
+            let value = value.clamp(lo, hi) - lo;
+            let span = (hi - lo).max(1) as u32;
+            bottom - value as u32 * bottom / span
+        };
+        let markers = match self.extremes(width) {
+            Some((min, max)) if self.markers => Some((row(min), row(max))),
+            _ => None,
+        };
+
+        let top = page as u32 * PAGE_HEIGHT as u32;
+        let offset = width - self.visible(width).len();
+        let mut prev = None;
+        for (x, value) in self.visible(width).enumerate() {
+            let y = row(value);
+            let (from, to) = match self.style {
+                Style::Line => {
+                    let prev = prev.unwrap_or(y);
+                    (prev.min(y), prev.max(y))
+                }
+                Style::Bar => (y, bottom),
+            };
+            prev = Some(y);
+            let col = &mut buf[offset + x];
+            for bit in 0..PAGE_HEIGHT as u32 {
+                if (from..=to).contains(&(top + bit)) {
+                    *col |= 1 << bit;
+                }
+            }
+        }
+
+        if let Some((min, max)) = markers {
+            for (x, col) in buf.iter_mut().enumerate() {
+                if !x.is_multiple_of(4) {
+                    continue;
+                }
+                for y in [min, max] {
+                    if (top..top + PAGE_HEIGHT as u32).contains(&y) {
+                        *col |= 1 << (y - top);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visible(&self, width: usize) -> impl ExactSizeIterator<Item = u16> + '_ {
+        let len = self.samples.len();
+        self.samples.iter().skip(len.saturating_sub(width))
+    }
+}
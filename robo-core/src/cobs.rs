@@ -0,0 +1,63 @@
+//! Consistent Overhead Byte Stuffing, frames never contain a zero byte so
+//! `0x00` can delimit them on the wire.
+
+/// Worst case encoded length of `len` bytes, without the delimiter.
+pub const fn max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// Encodes `src` into `dst` and returns the encoded length, or `None` when
+/// `dst` is too small. No delimiter is appended.
+pub fn encode(src: &[u8], dst: &mut [u8]) -> Option<usize> {
+    if dst.len() < max_encoded_len(src.len()) {
+        return None;
+    }
+    let mut code_idx = 0;
+    let mut code = 1u8;
+    let mut out = 1;
+    for &byte in src {
+        if byte == 0 {
+            dst[code_idx] = code;
+            code_idx = out;
+            code = 1;
+            out += 1;
+            continue;
+        }
+        dst[out] = byte;
+        out += 1;
+        code += 1;
+        if code == 0xff {
+            dst[code_idx] = code;
+            code_idx = out;
+            code = 1;
+            out += 1;
+        }
+    }
+    dst[code_idx] = code;
+    Some(out)
+}
+
+/// Decodes one frame, given without the delimiter, into `dst` and returns
+/// the decoded length. `None` if the frame is malformed or `dst` too small.
+pub fn decode(src: &[u8], dst: &mut [u8]) -> Option<usize> {
+    let mut out = 0;
+    let mut idx = 0;
+    while idx < src.len() {
+        let code = src[idx] as usize;
+        if code == 0 || idx + code > src.len() {
+            return None;
+        }
+        let block = &src[idx + 1..idx + code];
+        if block.contains(&0) {
+            return None;
+        }
+        dst.get_mut(out..out + block.len())?.copy_from_slice(block);
+        out += block.len();
+        idx += code;
+        if code < 0xff && idx < src.len() {
+            *dst.get_mut(out)? = 0;
+            out += 1;
+        }
+    }
+    Some(out)
+}
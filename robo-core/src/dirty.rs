@@ -0,0 +1,97 @@
+//! Bookkeeping to send only changed display content.
+
+use core::ops::Range;
+
+/// Copy of what the display shows, `PAGES` pages of `W` columns.
+///
+/// Starts out matching a cleared display. Pages marked stale are sent in
+/// full until a draw covers their whole width again.
+pub struct Shadow<const W: usize, const PAGES: usize> {
+    pages: [[u8; W]; PAGES],
+    stale: [bool; PAGES],
+}
+
+impl<const W: usize, const PAGES: usize> Shadow<W, PAGES> {
+    pub const fn new() -> Self {
+        Self {
+            pages: [[0; W]; PAGES],
+            stale: [false; PAGES],
+        }
+    }
+
+    /// Forgets the display content, e.g. after it was drawn behind our back.
+    pub fn invalidate(&mut self) {
+        self.stale = [true; PAGES];
+    }
+
+    pub fn invalidate_page(&mut self, page: usize) {
+        if let Some(stale) = self.stale.get_mut(page) {
+            *stale = true;
+        }
+    }
+
+    /// Records `bytes` drawn to `page` from column `x` and returns the part
+    /// of `bytes` that differs from the display, `None` if nothing does.
+    /// Content outside of the display is ignored.
+    pub fn update(&mut self, page: usize, x: usize, bytes: &[u8]) -> Option<Range<usize>> {
+        let row = self.pages.get_mut(page)?;
+        if x >= W {
+            return None;
+        }
+        let len = bytes.len().min(W.saturating_sub(x));
+        let (shown, bytes) = (&mut row[x..x + len], &bytes[..len]);
+        let changed = if self.stale[page] {
+            Some(0..len).filter(|range| !range.is_empty())
+        } else {
+            let first = shown.iter().zip(bytes).position(|(a, b)| a != b)?;
+            let last = shown.iter().zip(bytes).rposition(|(a, b)| a != b)?;
+            Some(first..last + 1)
+        };
+        shown.copy_from_slice(bytes);
+        if x == 0 && len == W {
+            self.stale[page] = false;
+        }
+        changed
+    }
+}
+
+impl<const W: usize, const PAGES: usize> Default for Shadow<W, PAGES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counters of the display traffic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Bitmap bytes sent to the display.
+    pub bytes_sent: u32,
+    /// Bitmap bytes dropped because the display already showed them.
+    pub bytes_skipped: u32,
+    pub frames: u32,
+    /// Frames not rendered at all since nothing changed.
+    pub frames_skipped: u32,
+}
+
+impl RenderStats {
+    pub const fn new() -> Self {
+        Self {
+            bytes_sent: 0,
+            bytes_skipped: 0,
+            frames: 0,
+            frames_skipped: 0,
+        }
+    }
+
+    pub fn frame(&mut self, rendered: bool) {
+        self.frames = self.frames.wrapping_add(1);
+        if !rendered {
+            self.frames_skipped = self.frames_skipped.wrapping_add(1);
+        }
+    }
+
+    pub fn sent(&mut self, sent: usize, skipped: usize) {
+        self.bytes_sent = self.bytes_sent.wrapping_add(sent as u32);
+        self.bytes_skipped = self.bytes_skipped.wrapping_add(skipped as u32);
+    }
+}
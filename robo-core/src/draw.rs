@@ -0,0 +1,166 @@
+//! Display draws queued for a transfer running in the background, e.g. DMA.
+//!
+//! The renderer fills one batch while the other one is being sent, so it
+//! never waits for the bus.
+
+/// Display area in SSD1306 page units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Area {
+    pub x: u8,
+    pub page: u8,
+    pub width: u8,
+    pub pages: u8,
+}
+
+impl Area {
+    pub fn len(&self) -> usize {
+        self.width as usize * self.pages as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Draws with their bitmaps stored back to back, at most `BYTES` bytes in
+/// `OPS` draws.
+pub struct Batch<const BYTES: usize, const OPS: usize> {
+    bytes: [u8; BYTES],
+    len: usize,
+    ops: [(Area, usize); OPS],
+    count: usize,
+}
+
+impl<const BYTES: usize, const OPS: usize> Batch<BYTES, OPS> {
+    const EMPTY: Area = Area {
+        x: 0,
+        page: 0,
+        width: 0,
+        pages: 0,
+    };
+
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; BYTES],
+            len: 0,
+            ops: [(Self::EMPTY, 0); OPS],
+            count: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Queues a draw, returns `false` if it doesn't fit.
+    pub fn push(&mut self, area: Area, bitmap: &[u8]) -> bool {
+        let end = self.len + bitmap.len();
+        if self.count == OPS || end > BYTES || bitmap.len() != area.len() {
+            return false;
+        }
+        self.bytes[self.len..end].copy_from_slice(bitmap);
+        self.ops[self.count] = (area, self.len);
+        self.len = end;
+        self.count += 1;
+        true
+    }
+
+    pub fn get(&self, index: usize) -> Option<(Area, &[u8])> {
+        if index >= self.count {
+            return None;
+        }
+        let (area, start) = self.ops[index];
+        Some((area, &self.bytes[start..start + area.len()]))
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.count = 0;
+    }
+}
+
+impl<const BYTES: usize, const OPS: usize> Default for Batch<BYTES, OPS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Double buffered draw queue.
+///
+/// The producer adds draws with `draw`, the transfer side takes them one by
+/// one with `pop`. The batch handed out by `pop` is left alone until
+/// `pop` is called again, so its bytes can be read by DMA meanwhile.
+pub struct DrawQueue<const BYTES: usize, const OPS: usize> {
+    batches: [Batch<BYTES, OPS>; 2],
+    back: usize,
+    sending: bool,
+    next: usize,
+    dropped: u32,
+}
+
+impl<const BYTES: usize, const OPS: usize> DrawQueue<BYTES, OPS> {
+    pub const fn new() -> Self {
+        Self {
+            batches: [Batch::new(), Batch::new()],
+            back: 0,
+            sending: false,
+            next: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Queues a draw for the next batch. Returns `false` and counts the draw
+    /// as dropped if the batch is full.
+    pub fn draw(&mut self, area: Area, bitmap: &[u8]) -> bool {
+        let queued = self.batches[self.back].push(area, bitmap);
+        if !queued {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+        queued
+    }
+
+    /// `true` while a batch is being sent.
+    pub fn is_sending(&self) -> bool {
+        self.sending
+    }
+
+    /// `true` if draws wait for the next batch.
+    pub fn is_pending(&self) -> bool {
+        !self.batches[self.back].is_empty()
+    }
+
+    /// Next draw to send. Finishes the batch in flight when it's done and
+    /// starts on the pending one, `None` once both are sent.
+    pub fn pop(&mut self) -> Option<(Area, &[u8])> {
+        if self.sending && self.next == self.front().count {
+            self.sending = false;
+            self.batches[1 - self.back].clear();
+        }
+        if !self.sending {
+            if !self.is_pending() {
+                return None;
+            }
+            self.back = 1 - self.back;
+            self.sending = true;
+            self.next = 0;
+        }
+        self.next += 1;
+        self.front().get(self.next - 1)
+    }
+
+    /// Draws dropped since the last call, a non zero count means the
+    /// display needs a full redraw.
+    pub fn take_dropped(&mut self) -> u32 {
+        core::mem::take(&mut self.dropped)
+    }
+
+    fn front(&self) -> &Batch<BYTES, OPS> {
+        &self.batches[1 - self.back]
+    }
+}
+
+impl<const BYTES: usize, const OPS: usize> Default for DrawQueue<BYTES, OPS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
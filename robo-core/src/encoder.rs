@@ -0,0 +1,161 @@
+//! Quadrature rotary encoder decoding on top of a 16 bit hardware counter,
+//! e.g. a timer in encoder interface mode.
+//!
+//! `update` runs from a periodic tick with the raw counter. It unwraps
+//! counter overflow, groups counts into detents and estimates the turning
+//! speed, so fast turns can scroll further per detent.
+
+use crate::button;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Counter steps per mechanical detent, 4 on most encoders in 4x mode.
+    pub counts_per_detent: u8,
+    /// Period of `update`.
+    pub tick_ms: u32,
+    /// Speed in detents per second above which turns are accelerated, `0`
+    /// disables acceleration.
+    pub accel_from: u16,
+    /// Largest acceleration factor, reached at twice `accel_from`.
+    pub accel_max: u8,
+    /// Swaps the turning direction.
+    pub reverse: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            counts_per_detent: 4,
+            tick_ms: 10,
+            accel_from: 20,
+            accel_max: 8,
+            reverse: false,
+        }
+    }
+}
+
+/// Detents turned since the previous event, positive clockwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Turn {
+    pub detents: i16,
+    /// `detents` times the acceleration factor.
+    pub accelerated: i16,
+    /// Speed in detents per second, averaged over the last detents.
+    pub velocity: i16,
+}
+
+/// Input of an encoder with push switch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Turn(Turn),
+    Switch(button::Event),
+}
+
+pub struct Encoder {
+    config: Config,
+    last: u16,
+    /// Counts short of a full detent.
+    partial: i32,
+    position: i32,
+    /// Detents per second.
+    velocity: i32,
+    since_detent: u32,
+}
+
+impl Encoder {
+    /// No detent for this long means the knob rests.
+    const REST_MS: u32 = 1_000;
+
+    /// `raw` is the current counter value.
+    pub fn new(config: Config, raw: u16) -> Self {
+        Self {
+            config,
+            last: raw,
+            partial: 0,
+            position: 0,
+            velocity: 0,
+            since_detent: Self::REST_MS,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Detents turned since start or the last `set_position`.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: i32) {
+        self.position = position;
+        self.partial = 0;
+    }
+
+    /// Speed in detents per second, positive clockwise.
+    pub fn velocity(&self) -> i32 {
+        self.velocity
+    }
+
+    /// Acceleration factor at the current speed.
+    pub fn acceleration(&self) -> i32 {
+        let Config {
+            accel_from,
+            accel_max,
+            ..
+        } = self.config;
+        let speed = self.velocity().unsigned_abs();
+        if accel_from == 0 || speed <= accel_from as u32 {
+            return 1;
+        }
+        let over = (speed - accel_from as u32) as i32;
+        let max = accel_max.max(1) as i32;
+        (1 + over * (max - 1) / accel_from as i32).min(max)
+    }
+
+    /// Takes the counter value, call every `tick_ms`. The counter may move
+    /// by less than half its range between calls.
+    pub fn update(&mut self, raw: u16) -> Option<Turn> {
+        let mut counts = raw.wrapping_sub(self.last) as i16 as i32;
+        self.last = raw;
+        if self.config.reverse {
+            counts = -counts;
+        }
+
+        let per_detent = self.config.counts_per_detent.max(1) as i32;
+        self.partial += counts;
+        let detents = self.partial / per_detent;
+        self.partial -= detents * per_detent;
+        self.position = self.position.wrapping_add(detents);
+
+        // Speed from the time between detents, which also works for turns
+        // much slower than the tick
+        self.since_detent = (self.since_detent + self.config.tick_ms).min(Self::REST_MS);
+        if detents != 0 {
+            let speed = detents * 1_000 / self.since_detent.max(1) as i32;
+            self.velocity = (self.velocity + speed) / 2;
+            self.since_detent = 0;
+        } else {
+            // Slows down to rest when the knob stops
+            let bound = match self.since_detent {
+                Self::REST_MS => 0,
+                since => (1_000 / since.max(1)) as i32,
+            };
+            self.velocity = self.velocity.clamp(-bound, bound);
+        }
+
+        if detents == 0 {
+            return None;
+        }
+        let clamp = |value: i32| value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        Some(Turn {
+            detents: clamp(detents),
+            accelerated: clamp(detents * self.acceleration()),
+            velocity: clamp(self.velocity()),
+        })
+    }
+}
@@ -0,0 +1,97 @@
+/// Exponential moving average with a smoothing factor of `1 / 2^shift`.
+#[derive(Clone, Copy, Debug)]
+pub struct Ema {
+    shift: u8,
+    acc: u32,
+    primed: bool,
+}
+
+impl Ema {
+    /// `shift` is clamped to 0..=16, 0 disables smoothing.
+    pub const fn new(shift: u8) -> Self {
+        Self {
+            shift: if shift > 16 { 16 } else { shift },
+            acc: 0,
+            primed: false,
+        }
+    }
+
+    pub fn shift(&self) -> u8 {
+        self.shift
+    }
+
+    pub fn update(&mut self, value: u16) -> u16 {
+        let sample = (value as u32) << 16;
+        if self.primed {
+            self.acc = self.acc - (self.acc >> self.shift) + (sample >> self.shift);
+        } else {
+            self.acc = sample;
+            self.primed = true;
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> u16 {
+        ((self.acc + 0x8000) >> 16) as u16
+    }
+
+    pub fn reset(&mut self) {
+        self.acc = 0;
+        self.primed = false;
+    }
+}
+
+/// Median of the last `window` values, up to `N`. Rejects single outliers
+/// that an average would smear over the following values.
+#[derive(Clone, Copy, Debug)]
+pub struct Median<const N: usize> {
+    values: [u16; N],
+    window: usize,
+    len: usize,
+    next: usize,
+}
+
+impl<const N: usize> Median<N> {
+    /// `window` is clamped to 1..=N.
+    pub fn new(window: usize) -> Self {
+        Self {
+            values: [0; N],
+            window: window.clamp(1, N),
+            len: 0,
+            next: 0,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Starts over with the new window.
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window.clamp(1, N);
+        self.reset();
+    }
+
+    pub fn update(&mut self, value: u16) -> u16 {
+        self.values[self.next] = value;
+        self.next = (self.next + 1) % self.window;
+        self.len = (self.len + 1).min(self.window);
+        self.value().unwrap_or(value)
+    }
+
+    /// Upper median of the values so far, `None` before the first.
+    pub fn value(&self) -> Option<u16> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut sorted = self.values;
+        let sorted = &mut sorted[..self.len];
+        sorted.sort_unstable();
+        Some(sorted[self.len / 2])
+    }
+
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.next = 0;
+    }
+}
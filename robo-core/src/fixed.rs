@@ -0,0 +1,181 @@
+//! Fixed-point numbers formatted for display labels, e.g. millivolts shown
+//! as `3.30V`, without floats.
+//!
+//! Units use the characters of the numeric glyph sheets, with `^` standing
+//! for the degree sign.
+
+use core::fmt;
+
+/// Unit written after the number.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unit {
+    #[default]
+    None,
+    Volt,
+    Millivolt,
+    Celsius,
+    Ampere,
+    Percent,
+}
+
+impl Unit {
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Unit::None => "",
+            Unit::Volt => "V",
+            Unit::Millivolt => "mV",
+            Unit::Celsius => "^C",
+            Unit::Ampere => "A",
+            Unit::Percent => "%",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    #[default]
+    Right,
+}
+
+/// Integer `value` with `scale` implied decimal digits, e.g. millivolts as
+/// volts with a scale of 3.
+///
+/// Shows `decimals` digits after the point, rounded half away from zero,
+/// followed by the unit and padded with spaces to `width` characters. Text
+/// that doesn't fit is replaced by dashes, so a label never shows a cut
+/// number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fixed {
+    value: i32,
+    scale: u8,
+    decimals: u8,
+    unit: Unit,
+    width: usize,
+    align: Align,
+}
+
+impl Fixed {
+    /// Longest text: sign, 19 digits, point and the unit.
+    const MAX_LEN: usize = 32;
+    const MAX_DECIMALS: u8 = 9;
+
+    /// Shows all `scale` decimals, without unit or padding.
+    pub const fn new(value: i32, scale: u8) -> Self {
+        Self {
+            value,
+            scale,
+            decimals: scale,
+            unit: Unit::None,
+            width: 0,
+            align: Align::Right,
+        }
+    }
+
+    /// Whole number.
+    pub const fn int(value: i32) -> Self {
+        Self::new(value, 0)
+    }
+
+    pub const fn decimals(mut self, decimals: u8) -> Self {
+        self.decimals = if decimals > Self::MAX_DECIMALS {
+            Self::MAX_DECIMALS
+        } else {
+            decimals
+        };
+        self
+    }
+
+    pub const fn unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Pads to `width` characters, unit included.
+    pub const fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub const fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Magnitude with `decimals` implied digits.
+    fn rescaled(&self) -> u64 {
+        let magnitude = self.value.unsigned_abs() as u64;
+        if self.decimals >= self.scale {
+            let factor = 10u64.saturating_pow((self.decimals - self.scale) as u32);
+            magnitude.saturating_mul(factor)
+        } else {
+            let divisor = 10u64.saturating_pow((self.scale - self.decimals) as u32);
+            (magnitude + divisor / 2) / divisor
+        }
+    }
+
+    fn write_text(&self, out: &mut Buf) -> fmt::Result {
+        use fmt::Write;
+
+        let rescaled = self.rescaled();
+        let unit = 10u64.pow(self.decimals as u32);
+        if self.value < 0 && rescaled != 0 {
+            out.write_str("-")?;
+        }
+        write!(out, "{}", rescaled / unit)?;
+        if self.decimals > 0 {
+            let decimals = self.decimals as usize;
+            write!(out, ".{:0decimals$}", rescaled % unit)?;
+        }
+        out.write_str(self.unit.symbol())
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = Buf::new();
+        if self.write_text(&mut text).is_err() || (self.width > 0 && text.len > self.width) {
+            for _ in 0..self.width.max(1) {
+                f.write_str("-")?;
+            }
+            return Ok(());
+        }
+        let width = self.width;
+        let text = text.as_str();
+        match self.align {
+            Align::Left => write!(f, "{: <width$}", text),
+            Align::Right => write!(f, "{: >width$}", text),
+        }
+    }
+}
+
+/// Stack buffer the text is measured in before padding.
+struct Buf {
+    buf: [u8; Fixed::MAX_LEN],
+    len: usize,
+}
+
+impl Buf {
+    fn new() -> Self {
+        Self {
+            buf: [0; Fixed::MAX_LEN],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for Buf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! One queue for all user input: button clicks, encoder turns, pot moves
+//! and shell commands, each stamped with the time it came in.
+//!
+//! Interrupt handlers push, one task pops and holds the application logic.
+//! `C` is the application's type of shell commands.
+
+use crate::button;
+use crate::encoder;
+use crate::ring::Ring;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputEvent<C> {
+    Button(button::Event),
+    Encoder(encoder::Event),
+    /// Pot moved past the threshold of its `PotMotion`, raw position.
+    Pot(u16),
+    Shell(C),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stamped<C> {
+    /// Milliseconds of the queue clock.
+    pub at: u32,
+    pub event: InputEvent<C>,
+}
+
+/// Counts of the queue since the last `take_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub pushed: u32,
+    /// Oldest events dropped to make room.
+    pub dropped: u32,
+    /// Most events waiting at once.
+    pub high_water: u16,
+}
+
+/// Event queue of `N` entries with a millisecond clock.
+///
+/// When full, the oldest event makes room: late input matters more than
+/// stale input.
+pub struct InputQueue<C: Copy, const N: usize> {
+    events: Ring<Stamped<C>, N>,
+    now: u32,
+    stats: QueueStats,
+}
+
+impl<C: Copy, const N: usize> InputQueue<C, N> {
+    pub fn new() -> Self {
+        Self {
+            events: Ring::new(),
+            now: 0,
+            stats: QueueStats::default(),
+        }
+    }
+
+    /// Advances the clock, call from a periodic tick.
+    pub fn tick(&mut self, elapsed_ms: u32) {
+        self.now = self.now.wrapping_add(elapsed_ms);
+    }
+
+    pub fn now(&self) -> u32 {
+        self.now
+    }
+
+    /// Stamps and queues an event. Returns `false` if an older event was
+    /// dropped for it.
+    pub fn push(&mut self, event: InputEvent<C>) -> bool {
+        let stamped = Stamped {
+            at: self.now,
+            event,
+        };
+        let dropped = self.events.push(stamped).is_some();
+        self.stats.pushed = self.stats.pushed.wrapping_add(1);
+        self.stats.dropped = self.stats.dropped.wrapping_add(dropped as u32);
+        self.stats.high_water = self.stats.high_water.max(self.events.len() as u16);
+        !dropped
+    }
+
+    /// Oldest event.
+    pub fn pop(&mut self) -> Option<Stamped<C>> {
+        self.events.pop_oldest()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.stats
+    }
+
+    pub fn take_stats(&mut self) -> QueueStats {
+        let stats = self.stats;
+        self.stats = QueueStats {
+            high_water: self.events.len() as u16,
+            ..QueueStats::default()
+        };
+        stats
+    }
+}
+
+impl<C: Copy, const N: usize> Default for InputQueue<C, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Turns pot samples into moves past a threshold, ignoring noise and slow
+/// drift.
+pub struct PotMotion {
+    threshold: u16,
+    reference: Option<u16>,
+}
+
+impl PotMotion {
+    pub const fn new(threshold: u16) -> Self {
+        Self {
+            threshold,
+            reference: None,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: u16) {
+        self.threshold = threshold;
+    }
+
+    /// Returns the position when it moved more than the threshold since the
+    /// last move. The first sample only sets the reference.
+    pub fn update(&mut self, position: u16) -> Option<u16> {
+        let reference = *self.reference.get_or_insert(position);
+        if position.abs_diff(reference) <= self.threshold {
+            return None;
+        }
+        self.reference = Some(position);
+        Some(position)
+    }
+}
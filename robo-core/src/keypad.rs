@@ -0,0 +1,159 @@
+//! Key matrix decoding: per key debouncing, ghosting detection and a limit
+//! on keys held at once.
+//!
+//! The driver drives one row at a time and reads the columns, `scan` takes
+//! one full pass as a bitmask of active columns per row. Without diodes in
+//! the matrix, three keys on the corners of a rectangle also close the
+//! fourth corner. Such scans are ambiguous: while they last no new key goes
+//! down, releases still count.
+
+/// Most keys a matrix can have.
+pub const MAX_KEYS: usize = 32;
+
+/// Labels of a common 4x4 keypad, row after row.
+pub const LAYOUT_4X4: &[u8; 16] = b"123A456B789C*0#D";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub row: u8,
+    pub col: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyEvent {
+    Down(Key),
+    Up(Key),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Scans a key must read the same new level to change.
+    pub debounce_scans: u8,
+    /// Most keys down at once, further keys stay up until others go up.
+    pub rollover: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debounce_scans: 4,
+            rollover: 2,
+        }
+    }
+}
+
+pub struct Matrix<const ROWS: usize, const COLS: usize> {
+    config: Config,
+    /// Debounced keys, bit `row * COLS + col`.
+    pressed: u32,
+    counters: [[u8; COLS]; ROWS],
+    ghosting: bool,
+    /// Raw keys held back by the rollover limit.
+    blocked: u32,
+}
+
+impl<const ROWS: usize, const COLS: usize> Matrix<ROWS, COLS> {
+    /// Up to `MAX_KEYS` keys in rows of up to 8.
+    pub fn new(config: Config) -> Self {
+        assert!(ROWS * COLS <= MAX_KEYS && COLS <= 8);
+        Self {
+            config,
+            pressed: 0,
+            counters: [[0; COLS]; ROWS],
+            ghosting: false,
+            blocked: 0,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    const fn bit(key: Key) -> u32 {
+        1 << (key.row as usize * COLS + key.col as usize)
+    }
+
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.pressed & Self::bit(key) != 0
+    }
+
+    /// Keys down, bit `row * COLS + col`.
+    pub fn pressed(&self) -> u32 {
+        self.pressed
+    }
+
+    pub fn pressed_count(&self) -> usize {
+        self.pressed.count_ones() as usize
+    }
+
+    /// Whether the last scan was ambiguous.
+    pub fn is_ghosting(&self) -> bool {
+        self.ghosting
+    }
+
+    /// Keys read as down but held back by the rollover limit.
+    pub fn blocked(&self) -> u32 {
+        self.blocked
+    }
+
+    /// Keys down, row by row.
+    pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+        (0..ROWS * COLS)
+            .filter(|index| self.pressed & (1 << index) != 0)
+            .map(|index| Key {
+                row: (index / COLS) as u8,
+                col: (index % COLS) as u8,
+            })
+    }
+
+    /// Two rows sharing two active columns close a rectangle, a key may be
+    /// a ghost.
+    fn is_ambiguous(raw: &[u8; ROWS]) -> bool {
+        let mask = ((1u16 << COLS) - 1) as u8;
+        (0..ROWS).any(|first| {
+            (first + 1..ROWS).any(|second| (raw[first] & raw[second] & mask).count_ones() >= 2)
+        })
+    }
+
+    /// Takes one pass over the matrix, `raw[row]` has bit `col` set for
+    /// keys read as down, and reports the keys that changed.
+    pub fn scan(&mut self, raw: &[u8; ROWS], mut on_event: impl FnMut(KeyEvent)) {
+        self.ghosting = Self::is_ambiguous(raw);
+        self.blocked = 0;
+        for (row, &cols) in raw.iter().enumerate() {
+            for col in 0..COLS {
+                let key = Key {
+                    row: row as u8,
+                    col: col as u8,
+                };
+                let down = cols & (1 << col) != 0;
+                let room = self.pressed_count() < self.config.rollover as usize;
+                let counter = &mut self.counters[row][col];
+                if down == (self.pressed & Self::bit(key) != 0) || (down && self.ghosting) {
+                    *counter = 0;
+                    continue;
+                }
+                *counter = counter.saturating_add(1);
+                if *counter < self.config.debounce_scans.max(1) {
+                    continue;
+                }
+                if !down {
+                    *counter = 0;
+                    self.pressed &= !Self::bit(key);
+                    on_event(KeyEvent::Up(key));
+                } else if room {
+                    *counter = 0;
+                    self.pressed |= Self::bit(key);
+                    on_event(KeyEvent::Down(key));
+                } else {
+                    // Stays ready to go down as soon as there's room
+                    self.blocked |= Self::bit(key);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod alarm;
+pub mod button;
+pub mod chart;
+pub mod cobs;
+pub mod dirty;
+pub mod draw;
+pub mod encoder;
+pub mod filter;
+pub mod fixed;
+pub mod input;
+pub mod keypad;
+pub mod line;
+pub mod menu;
+pub mod motor;
+pub mod odometry;
+pub mod page;
+pub mod pid;
+pub mod q16;
+pub mod ring;
+pub mod saver;
+pub mod sample;
+pub mod scope;
+pub mod screen;
+pub mod servo;
+pub mod sonar;
+pub mod stats;
+pub mod status;
+pub mod telemetry;
@@ -0,0 +1,258 @@
+//! Line following with an array of reflectance sensors: per-sensor
+//! calibration, normalized readings and the position of the line under the
+//! array.
+//!
+//! Readings are normalized to 0..=`SCALE`, where 0 is the floor and `SCALE`
+//! the line, whatever the raw polarity. Positions are centered on the middle
+//! of the array in steps of `SCALE` per sensor, negative towards the first
+//! sensor on the left, so an array of 5 spans -2000..=2000.
+
+/// Fewest sensors of an array.
+pub const MIN_SENSORS: usize = 3;
+/// Most sensors of an array.
+pub const MAX_SENSORS: usize = 8;
+/// Normalized reading of a sensor right over the line.
+pub const SCALE: u16 = 1_000;
+
+/// How the line reads compared to the floor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Polarity {
+    /// Dark line on a light floor, the line reads higher as it reflects less.
+    #[default]
+    Dark,
+    /// Light line on a dark floor.
+    Light,
+}
+
+/// Raw range a sensor was seen to cover, floor to line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl Calibration {
+    /// No readings yet, the first one sets both ends.
+    pub const EMPTY: Self = Self {
+        min: u16::MAX,
+        max: 0,
+    };
+
+    pub const fn new(min: u16, max: u16) -> Self {
+        Self { min, max }
+    }
+
+    /// Widens the range to cover `raw`.
+    pub fn extend(&mut self, raw: u16) {
+        self.min = self.min.min(raw);
+        self.max = self.max.max(raw);
+    }
+
+    pub fn span(&self) -> u16 {
+        self.max.saturating_sub(self.min)
+    }
+
+    /// `raw` scaled to 0..=`SCALE` across the range, 0 without a range.
+    pub fn normalize(&self, raw: u16, polarity: Polarity) -> u16 {
+        let span = self.span() as u32;
+        if span == 0 {
+            return 0;
+        }
+        let value = (raw.clamp(self.min, self.max) - self.min) as u32;
+        let value = ((value * SCALE as u32 + span / 2) / span) as u16;
+        match polarity {
+            Polarity::Dark => value,
+            Polarity::Light => SCALE - value,
+        }
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub polarity: Polarity,
+    /// Normalized reading a sensor needs to count as seeing the line.
+    pub threshold: u16,
+    /// Normalized readings below are left out of the position.
+    pub noise: u16,
+    /// Smallest raw range of a calibrated sensor, narrower ones never saw
+    /// both the floor and the line.
+    pub min_span: u16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            polarity: Polarity::Dark,
+            threshold: 300,
+            noise: 50,
+            min_span: 200,
+        }
+    }
+}
+
+/// Side of the array the line was last seen on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    /// Line under the array at this position.
+    Line(i16),
+    /// No sensor sees the line.
+    Lost(Side),
+}
+
+pub struct LineArray {
+    config: Config,
+    len: usize,
+    calibration: [Calibration; MAX_SENSORS],
+    values: [u16; MAX_SENSORS],
+    position: Option<Position>,
+    /// Last position the line was seen at.
+    last_seen: Option<i16>,
+}
+
+impl LineArray {
+    /// Sensors start uncalibrated and read 0 until calibrated. Panics unless
+    /// `sensors` is in `MIN_SENSORS..=MAX_SENSORS`.
+    pub fn new(sensors: usize, config: Config) -> Self {
+        assert!(
+            (MIN_SENSORS..=MAX_SENSORS).contains(&sensors),
+            "{} sensors, the array takes {} to {}",
+            sensors,
+            MIN_SENSORS,
+            MAX_SENSORS
+        );
+        Self {
+            config,
+            len: sensors,
+            calibration: [Calibration::EMPTY; MAX_SENSORS],
+            values: [0; MAX_SENSORS],
+            position: None,
+            last_seen: None,
+        }
+    }
+
+    pub fn sensors(&self) -> usize {
+        self.len
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Farthest position from the center, that of the outer sensors.
+    pub fn edge(&self) -> i16 {
+        ((self.len - 1) * SCALE as usize / 2) as i16
+    }
+
+    pub fn calibration(&self) -> &[Calibration] {
+        &self.calibration[..self.len]
+    }
+
+    pub fn set_calibration(&mut self, sensor: usize, calibration: Calibration) {
+        if let Some(slot) = self.calibration[..self.len].get_mut(sensor) {
+            *slot = calibration;
+        }
+    }
+
+    /// Forgets the calibration, e.g. before sweeping the array over the
+    /// line again.
+    pub fn reset_calibration(&mut self) {
+        self.calibration = [Calibration::EMPTY; MAX_SENSORS];
+    }
+
+    /// Widens the calibration with one raw reading per sensor.
+    pub fn calibrate(&mut self, raw: &[u16]) {
+        for (calibration, &raw) in self.calibration[..self.len].iter_mut().zip(raw) {
+            calibration.extend(raw);
+        }
+    }
+
+    /// Whether every sensor covered at least `min_span`.
+    pub fn is_calibrated(&self) -> bool {
+        self.calibration()
+            .iter()
+            .all(|calibration| calibration.span() >= self.config.min_span)
+    }
+
+    /// Takes one raw reading per sensor, in order from the left, and returns
+    /// the position of the line.
+    pub fn update(&mut self, raw: &[u16]) -> Position {
+        let polarity = self.config.polarity;
+        for ((value, calibration), &raw) in self.values[..self.len]
+            .iter_mut()
+            .zip(&self.calibration)
+            .zip(raw)
+        {
+            *value = calibration.normalize(raw, polarity);
+        }
+
+        let seen = self.values().iter().any(|&v| v >= self.config.threshold);
+        let position = match self.weighted() {
+            Some(position) if seen => {
+                self.last_seen = Some(position);
+                Position::Line(position)
+            }
+            _ => Position::Lost(match self.last_seen {
+                Some(last) if last < 0 => Side::Left,
+                Some(last) if last > 0 => Side::Right,
+                _ => Side::Center,
+            }),
+        };
+        self.position = Some(position);
+        position
+    }
+
+    /// Normalized readings of the last update.
+    pub fn values(&self) -> &[u16] {
+        &self.values[..self.len]
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Offset of the line from the center for steering: its position, or
+    /// the edge on the side it was lost on.
+    pub fn error(&self) -> i16 {
+        match self.position {
+            Some(Position::Line(position)) => position,
+            Some(Position::Lost(Side::Left)) => -self.edge(),
+            Some(Position::Lost(Side::Right)) => self.edge(),
+            Some(Position::Lost(Side::Center)) | None => 0,
+        }
+    }
+
+    /// Average of the sensor positions weighted by their readings.
+    fn weighted(&self) -> Option<i16> {
+        let edge = self.edge() as i32;
+        let (sum, weight) = self
+            .values()
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value >= self.config.noise)
+            .fold((0i32, 0i32), |(sum, weight), (sensor, &value)| {
+                let at = sensor as i32 * SCALE as i32 - edge;
+                (sum + at * value as i32, weight + value as i32)
+            });
+        match weight {
+            0 => None,
+            weight => Some(((sum + sum.signum() * weight / 2) / weight) as i16),
+        }
+    }
+}
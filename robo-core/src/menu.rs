@@ -0,0 +1,287 @@
+//! Menu driven by an absolute position input, e.g. a pot, and a confirm
+//! button.
+//!
+//! Outside of editing the position picks the selected item, while editing a
+//! field it picks the value. After every mode change the position has to move
+//! by a small amount before it takes effect again, so entering a field does
+//! not overwrite its value with wherever the pot happens to be.
+
+use core::fmt;
+
+/// Width of the value column, without the edit brackets.
+const VALUE_WIDTH: usize = 5;
+
+/// Values behind the menu fields, usually the app configuration.
+pub trait Settings {
+    fn get(&self, id: u8) -> i32;
+    fn set(&mut self, id: u8, value: i32);
+}
+
+pub struct Item {
+    pub label: &'static str,
+    pub kind: Kind,
+}
+
+impl Item {
+    pub const fn new(label: &'static str, kind: Kind) -> Self {
+        Self { label, kind }
+    }
+}
+
+pub enum Kind {
+    Submenu(&'static [Item]),
+    /// Integer field, `max` is rounded down to a whole number of steps.
+    Number {
+        id: u8,
+        min: i32,
+        max: i32,
+        step: i32,
+    },
+    /// Field holding the index of one of `choices`.
+    Choice {
+        id: u8,
+        choices: &'static [&'static str],
+    },
+    Action(u8),
+    /// Leaves the current submenu, or the menu itself at the top level.
+    Back,
+}
+
+impl Kind {
+    fn steps(&self) -> u32 {
+        match *self {
+            Kind::Number { min, max, step, .. } => ((max - min) / step.max(1)) as u32 + 1,
+            Kind::Choice { choices, .. } => choices.len() as u32,
+            _ => 0,
+        }
+    }
+
+    fn value_at(&self, step: u32) -> i32 {
+        match *self {
+            Kind::Number {
+                min, step: size, ..
+            } => min + step as i32 * size.max(1),
+            _ => step as i32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Changed { id: u8, value: i32 },
+    Action(u8),
+    Exit,
+}
+
+#[derive(Clone, Copy)]
+struct Level {
+    items: &'static [Item],
+    title: &'static str,
+    cursor: usize,
+    scroll: usize,
+}
+
+/// Menu state with up to `DEPTH` nested levels, the top level included.
+pub struct Menu<const DEPTH: usize> {
+    levels: [Level; DEPTH],
+    depth: usize,
+    rows: usize,
+    full_scale: u16,
+    position: u16,
+    pickup: Option<u16>,
+    edit: Option<i32>,
+}
+
+impl<const DEPTH: usize> Menu<DEPTH> {
+    /// `rows` is the number of items visible at once and `full_scale` the
+    /// largest position reading. Every level needs at least one item.
+    pub fn new(title: &'static str, items: &'static [Item], rows: usize, full_scale: u16) -> Self {
+        let top = Level {
+            items,
+            title,
+            cursor: 0,
+            scroll: 0,
+        };
+        Self {
+            levels: [top; DEPTH],
+            depth: 0,
+            rows: rows.max(1),
+            full_scale: full_scale.max(1),
+            position: 0,
+            pickup: Some(0),
+            edit: None,
+        }
+    }
+
+    /// Title of the current level.
+    pub fn title(&self) -> &'static str {
+        self.level().title
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.level().cursor
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.edit.is_some()
+    }
+
+    /// Back to the top level, dropping an unconfirmed edit.
+    pub fn reset(&mut self) {
+        self.depth = 0;
+        self.levels[0].cursor = 0;
+        self.levels[0].scroll = 0;
+        self.edit = None;
+        self.hold();
+    }
+
+    /// Feeds a position reading and returns `true` if the menu changed.
+    pub fn position(&mut self, position: u16) -> bool {
+        self.position = position;
+        if let Some(anchor) = self.pickup {
+            if position.abs_diff(anchor) <= self.full_scale / 32 {
+                return false;
+            }
+            self.pickup = None;
+        }
+        let scaled = |steps: u32| {
+            (position.min(self.full_scale) as u32 * steps / (self.full_scale as u32 + 1))
+                .min(steps.saturating_sub(1))
+        };
+        if self.edit.is_some() {
+            let kind = &self.item().kind;
+            let value = Some(kind.value_at(scaled(kind.steps())));
+            let changed = self.edit != value;
+            self.edit = value;
+            changed
+        } else {
+            let cursor = scaled(self.level().items.len() as u32) as usize;
+            let changed = cursor != self.cursor();
+            self.select(cursor);
+            changed
+        }
+    }
+
+    /// Acts on the selected item: enters submenus, starts editing a field or
+    /// stores the edited value in `settings`.
+    pub fn confirm(&mut self, settings: &mut impl Settings) -> Option<Event> {
+        if let Some(value) = self.edit.take() {
+            self.hold();
+            return match self.item().kind {
+                Kind::Number { id, .. } | Kind::Choice { id, .. } => {
+                    settings.set(id, value);
+                    Some(Event::Changed { id, value })
+                }
+                _ => None,
+            };
+        }
+        let item = self.item();
+        match item.kind {
+            Kind::Submenu(items) => {
+                if self.depth + 1 < DEPTH {
+                    self.depth += 1;
+                    self.levels[self.depth] = Level {
+                        items,
+                        title: item.label,
+                        cursor: 0,
+                        scroll: 0,
+                    };
+                    self.hold();
+                }
+                None
+            }
+            Kind::Number { id, .. } | Kind::Choice { id, .. } => {
+                self.edit = Some(settings.get(id));
+                self.hold();
+                None
+            }
+            Kind::Action(id) => Some(Event::Action(id)),
+            Kind::Back if self.depth > 0 => {
+                self.depth -= 1;
+                self.hold();
+                None
+            }
+            Kind::Back => Some(Event::Exit),
+        }
+    }
+
+    /// Writes visible row `row` as `width` characters: a cursor mark, the
+    /// label and the right aligned value, bracketed while being edited.
+    pub fn write_row(
+        &self,
+        row: usize,
+        settings: &impl Settings,
+        width: usize,
+        out: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        let level = self.level();
+        let index = level.scroll + row;
+        let label_width = width.saturating_sub(VALUE_WIDTH + 3);
+        let Some(item) = level.items.get(index) else {
+            return write!(out, "{: <width$}", "");
+        };
+        let selected = index == level.cursor;
+        let edit = self.edit.filter(|_| selected);
+        let value = match item.kind {
+            Kind::Submenu(_) => Value::Text(".."),
+            Kind::Number { id, .. } => Value::Number(edit.unwrap_or_else(|| settings.get(id))),
+            Kind::Choice { id, choices } => {
+                let index = edit.unwrap_or_else(|| settings.get(id));
+                Value::Text(choices.get(index as usize).copied().unwrap_or("?"))
+            }
+            Kind::Action(_) | Kind::Back => Value::Text(""),
+        };
+        let (open, close) = if edit.is_some() {
+            ('<', '>')
+        } else {
+            (' ', ' ')
+        };
+        write!(
+            out,
+            "{}{: <label_width$.label_width$}{}{: >VALUE_WIDTH$}{}",
+            if selected { '>' } else { ' ' },
+            item.label,
+            open,
+            value,
+            close,
+        )
+    }
+
+    fn level(&self) -> &Level {
+        &self.levels[self.depth]
+    }
+
+    fn item(&self) -> &'static Item {
+        let level = self.level();
+        &level.items[level.cursor]
+    }
+
+    fn select(&mut self, cursor: usize) {
+        let rows = self.rows;
+        let level = &mut self.levels[self.depth];
+        level.cursor = cursor.min(level.items.len().saturating_sub(1));
+        if level.cursor < level.scroll {
+            level.scroll = level.cursor;
+        } else if level.cursor >= level.scroll + rows {
+            level.scroll = level.cursor + 1 - rows;
+        }
+    }
+
+    fn hold(&mut self) {
+        self.pickup = Some(self.position);
+    }
+}
+
+enum Value {
+    Number(i32),
+    Text(&'static str),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(value) => fmt::Display::fmt(value, f),
+            Value::Text(text) => f.pad(text),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! DC motor control through an H-bridge: signed speed with acceleration
+//! ramps, brake or coast at standstill.
+//!
+//! Speeds and duty cycles are per mille of full scale. `Motor::tick` returns
+//! the duty cycles of the bridge inputs for the selected driver chip, the
+//! firmware maps them onto PWM channels or GPIOs.
+
+/// Full speed and full duty cycle.
+pub const DUTY_MAX: u16 = 1_000;
+
+/// Supported dual H-bridge chips.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// PWM on ENA/ENB, direction on IN1..IN4.
+    L298n,
+    /// PWM on PWMA/PWMB, direction on AIN/BIN, STBY held high. Drives like
+    /// the L298N.
+    Tb6612,
+    /// PWM on both inputs of a bridge, there's no enable input.
+    Drv8833,
+}
+
+/// What the bridge does with the motor at zero speed. On the DRV8833 it
+/// also selects the decay during PWM off time: slow decay for brake, fast
+/// decay for coast.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Stop {
+    /// Shorts the motor terminals, it stops quickly and holds.
+    #[default]
+    Brake,
+    /// Leaves the motor terminals open, it spins down freely.
+    Coast,
+}
+
+/// Duty cycles of one bridge: 0 is low, `DUTY_MAX` high.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Outputs {
+    pub enable: u16,
+    pub in1: u16,
+    pub in2: u16,
+}
+
+impl Mode {
+    /// Bridge inputs for `speed`, positive drives current from OUT1 to
+    /// OUT2.
+    pub fn outputs(self, speed: i16, stop: Stop) -> Outputs {
+        let duty = speed.unsigned_abs().min(DUTY_MAX);
+        let (high, low) = (DUTY_MAX, 0);
+        match (self, speed.signum(), stop) {
+            (Mode::L298n | Mode::Tb6612, 0, Stop::Brake) => Outputs {
+                enable: high,
+                in1: high,
+                in2: high,
+            },
+            (Mode::L298n | Mode::Tb6612, 0, Stop::Coast) => Outputs::default(),
+            (Mode::L298n | Mode::Tb6612, direction, _) => Outputs {
+                enable: duty,
+                in1: if direction > 0 { high } else { low },
+                in2: if direction < 0 { high } else { low },
+            },
+            (Mode::Drv8833, 0, Stop::Brake) => Outputs {
+                enable: high,
+                in1: high,
+                in2: high,
+            },
+            (Mode::Drv8833, 0, Stop::Coast) => Outputs {
+                enable: high,
+                ..Outputs::default()
+            },
+            // Slow decay: brakes during the off time of the other input
+            (Mode::Drv8833, direction, Stop::Brake) => {
+                let (drive, off) = (high, DUTY_MAX - duty);
+                let (in1, in2) = if direction > 0 {
+                    (drive, off)
+                } else {
+                    (off, drive)
+                };
+                Outputs {
+                    enable: high,
+                    in1,
+                    in2,
+                }
+            }
+            // Fast decay: coasts during the off time
+            (Mode::Drv8833, direction, Stop::Coast) => {
+                let (in1, in2) = if direction > 0 {
+                    (duty, low)
+                } else {
+                    (low, duty)
+                };
+                Outputs {
+                    enable: high,
+                    in1,
+                    in2,
+                }
+            }
+        }
+    }
+}
+
+/// One motor: ramps its speed towards the target at a limited
+/// acceleration.
+#[derive(Clone, Copy, Debug)]
+pub struct Motor {
+    mode: Mode,
+    stop: Stop,
+    /// Speed change per second, 0 for none.
+    accel: u16,
+    speed: i16,
+    target: i16,
+    /// Ramp progress short of a whole step, in thousandths of a step.
+    carry: u32,
+    reverse: bool,
+}
+
+impl Motor {
+    pub const fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            stop: Stop::Brake,
+            accel: 0,
+            speed: 0,
+            target: 0,
+            carry: 0,
+            reverse: false,
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn stop_mode(&self) -> Stop {
+        self.stop
+    }
+
+    pub fn set_stop_mode(&mut self, stop: Stop) {
+        self.stop = stop;
+    }
+
+    /// Largest speed change per second, e.g. 2000 goes from full reverse
+    /// to full forward in one second. 0 changes speed at once.
+    pub fn set_accel(&mut self, accel: u16) {
+        self.accel = accel;
+    }
+
+    pub fn accel(&self) -> u16 {
+        self.accel
+    }
+
+    /// Swaps the direction, for a motor mounted mirrored.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+
+    /// Target speed, clamped to `DUTY_MAX` either way.
+    pub fn set_speed(&mut self, speed: i16) {
+        let max = DUTY_MAX as i16;
+        self.target = speed.clamp(-max, max);
+    }
+
+    /// Stops right away, skipping the ramp.
+    pub fn halt(&mut self, stop: Stop) {
+        self.stop = stop;
+        self.speed = 0;
+        self.target = 0;
+        self.carry = 0;
+    }
+
+    /// Current speed on the ramp.
+    pub fn speed(&self) -> i16 {
+        self.speed
+    }
+
+    pub fn target(&self) -> i16 {
+        self.target
+    }
+
+    /// Moves along the ramp by `elapsed_ms` and returns the bridge inputs.
+    pub fn tick(&mut self, elapsed_ms: u32) -> Outputs {
+        if self.accel == 0 {
+            self.speed = self.target;
+        } else if self.speed != self.target {
+            self.carry += self.accel as u32 * elapsed_ms;
+            let step = (self.carry / 1_000).min(u16::MAX as u32) as i32;
+            self.carry %= 1_000;
+            let gap = self.target as i32 - self.speed as i32;
+            self.speed += gap.clamp(-step, step) as i16;
+        }
+        if self.speed == self.target {
+            self.carry = 0;
+        }
+        let speed = if self.reverse {
+            -self.speed
+        } else {
+            self.speed
+        };
+        self.mode.outputs(speed, self.stop)
+    }
+}
@@ -0,0 +1,292 @@
+//! Wheel odometry: encoder ticks to distance, wheel speed and the motion of
+//! a differential drive.
+//!
+//! Ticks come from a hardware counter (`Wheel::counter`) or one by one with
+//! the time they arrived (`Wheel::edge`), and `update` runs from a periodic
+//! tick with the current time in microseconds. Speed is estimated two ways:
+//! ticks over a window of recent updates, precise at speed but coarse when
+//! only a few ticks fall in the window, and the time between the last two
+//! ticks, which resolves a wheel turning a few ticks per second. The period
+//! method needs `edge`.
+
+/// Most updates a speed window spans.
+pub const MAX_WINDOW: usize = 16;
+
+/// Pi as 355/113, off by less than 1e-7.
+const PI_NUM: i64 = 355;
+const PI_DEN: i64 = 113;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Method {
+    /// Ticks counted over the window.
+    Window,
+    /// Time between the last two ticks.
+    Period,
+    /// The period method while fewer than `min_window_ticks` ticks fall in
+    /// the window, the window method above.
+    #[default]
+    Auto,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Ticks per wheel revolution as counted, e.g. both edges of a 20 slot
+    /// disk give 40.
+    pub ticks_per_rev: u16,
+    pub wheel_diameter_mm: u16,
+    /// Updates the window method spans, up to `MAX_WINDOW`.
+    pub window: u8,
+    pub method: Method,
+    pub min_window_ticks: u16,
+    /// No tick for this long means the wheel stands.
+    pub timeout_ms: u32,
+    /// Swaps the direction, for a wheel mounted mirrored.
+    pub reverse: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ticks_per_rev: 40,
+            wheel_diameter_mm: 65,
+            window: 10,
+            method: Method::Auto,
+            min_window_ticks: 8,
+            timeout_ms: 500,
+            reverse: false,
+        }
+    }
+}
+
+/// Count of one wheel at an update.
+#[derive(Clone, Copy, Debug, Default)]
+struct Snapshot {
+    count: i32,
+    at_us: u32,
+}
+
+pub struct Wheel {
+    config: Config,
+    count: i32,
+    /// Last raw value of a hardware counter.
+    raw: Option<u16>,
+    history: [Snapshot; MAX_WINDOW],
+    head: usize,
+    filled: usize,
+    last_edge: Option<u32>,
+    /// Time between the last two ticks, negative backwards.
+    period_us: i32,
+    /// Milliticks per second.
+    rate: i32,
+}
+
+impl Wheel {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            count: 0,
+            raw: None,
+            history: [Snapshot::default(); MAX_WINDOW],
+            head: 0,
+            filled: 0,
+            last_edge: None,
+            period_us: 0,
+            rate: 0,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Keeps the count, the speed estimate starts over.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+        self.filled = 0;
+        self.period_us = 0;
+    }
+
+    /// Ticks since start or the last `reset`.
+    pub fn count(&self) -> i32 {
+        self.count
+    }
+
+    /// Zeroes the count and the distance.
+    pub fn reset(&mut self) {
+        // Shifts the window along, so the speed doesn't jump
+        for snapshot in self.history.iter_mut() {
+            snapshot.count = snapshot.count.wrapping_sub(self.count);
+        }
+        self.count = 0;
+    }
+
+    /// Takes the value of a 16 bit hardware counter, which may move by less
+    /// than half its range between calls.
+    pub fn counter(&mut self, raw: u16) {
+        let last = self.raw.replace(raw).unwrap_or(raw);
+        let ticks = raw.wrapping_sub(last) as i16 as i32;
+        self.add(if self.config.reverse { -ticks } else { ticks });
+    }
+
+    /// Takes one tick at `at_us`, e.g. from an edge interrupt.
+    pub fn edge(&mut self, at_us: u32, forward: bool) {
+        let forward = forward != self.config.reverse;
+        self.add(if forward { 1 } else { -1 });
+        if let Some(last) = self.last_edge {
+            let period = at_us.wrapping_sub(last).min(i32::MAX as u32) as i32;
+            // A reversal leaves no period in the new direction yet
+            self.period_us = match (self.period_us.signum(), forward) {
+                (1, false) | (-1, true) => 0,
+                (_, true) => period,
+                (_, false) => -period,
+            };
+        }
+        self.last_edge = Some(at_us);
+    }
+
+    fn add(&mut self, ticks: i32) {
+        self.count = self.count.wrapping_add(ticks);
+    }
+
+    /// Estimates the speed, call periodically with the current time.
+    pub fn update(&mut self, now_us: u32) {
+        let window = (self.config.window as usize).clamp(1, MAX_WINDOW);
+        let oldest = match self.filled {
+            0 => None,
+            filled => {
+                let back = filled.min(window);
+                Some(self.history[(self.head + MAX_WINDOW - back) % MAX_WINDOW])
+            }
+        };
+        self.history[self.head] = Snapshot {
+            count: self.count,
+            at_us: now_us,
+        };
+        self.head = (self.head + 1) % MAX_WINDOW;
+        self.filled = (self.filled + 1).min(MAX_WINDOW);
+
+        let windowed = oldest.map(|oldest| {
+            let ticks = self.count.wrapping_sub(oldest.count);
+            let elapsed = now_us.wrapping_sub(oldest.at_us).max(1);
+            (
+                ticks,
+                (ticks as i64 * 1_000_000_000 / elapsed as i64) as i32,
+            )
+        });
+        let period = self.period_rate(now_us);
+        self.rate = match (self.config.method, windowed, period) {
+            (Method::Window, Some((_, rate)), _) => rate,
+            (Method::Period, _, Some(rate)) => rate,
+            (Method::Auto, Some((ticks, rate)), _)
+                if ticks.unsigned_abs() >= self.config.min_window_ticks as u32 =>
+            {
+                rate
+            }
+            (Method::Auto, _, Some(rate)) => rate,
+            (Method::Auto, Some((_, rate)), None) => rate,
+            _ => 0,
+        };
+    }
+
+    /// Speed from the last period, `None` without one.
+    fn period_rate(&mut self, now_us: u32) -> Option<i32> {
+        let last = self.last_edge?;
+        let since = now_us.wrapping_sub(last);
+        if since >= self.config.timeout_ms.saturating_mul(1_000) {
+            self.period_us = 0;
+            self.last_edge = None;
+            return Some(0);
+        }
+        if self.period_us == 0 {
+            return None;
+        }
+        // The wheel is at most as fast as the time since the last tick
+        // allows, so a stopping wheel slows down instead of holding its speed
+        let period = self.period_us.unsigned_abs().max(since) as i64;
+        Some((self.period_us.signum() as i64 * 1_000_000_000 / period) as i32)
+    }
+
+    /// Speed in milliticks per second.
+    pub fn rate(&self) -> i32 {
+        self.rate
+    }
+
+    /// Ticks to micrometers, rounded towards zero.
+    fn to_um(&self, ticks: i64) -> i64 {
+        let Config {
+            ticks_per_rev,
+            wheel_diameter_mm,
+            ..
+        } = self.config;
+        ticks * wheel_diameter_mm as i64 * 1_000 * PI_NUM / (PI_DEN * ticks_per_rev.max(1) as i64)
+    }
+
+    /// Distance travelled since start or the last `reset`.
+    pub fn distance_mm(&self) -> i32 {
+        (self.to_um(self.count as i64) / 1_000) as i32
+    }
+
+    /// Speed at the rim in millimeters per second.
+    pub fn speed(&self) -> i32 {
+        (self.to_um(self.rate as i64) / 1_000_000) as i32
+    }
+
+    /// Angular velocity of the wheel in milliradians per second.
+    pub fn angular_velocity(&self) -> i32 {
+        let ticks_per_rev = self.config.ticks_per_rev.max(1) as i64;
+        (self.rate as i64 * 2 * PI_NUM / (PI_DEN * ticks_per_rev)) as i32
+    }
+
+    /// Revolutions per minute, in tenths.
+    pub fn rpm(&self) -> i32 {
+        let ticks_per_rev = self.config.ticks_per_rev.max(1) as i64;
+        (self.rate as i64 * 60 / (100 * ticks_per_rev)) as i32
+    }
+}
+
+/// Two wheels of a differential drive, `track_mm` apart.
+pub struct Odometry {
+    pub left: Wheel,
+    pub right: Wheel,
+    track_mm: u16,
+}
+
+impl Odometry {
+    pub fn new(left: Config, right: Config, track_mm: u16) -> Self {
+        Self {
+            left: Wheel::new(left),
+            right: Wheel::new(right),
+            track_mm,
+        }
+    }
+
+    pub fn track_mm(&self) -> u16 {
+        self.track_mm
+    }
+
+    pub fn update(&mut self, now_us: u32) {
+        self.left.update(now_us);
+        self.right.update(now_us);
+    }
+
+    pub fn reset(&mut self) {
+        self.left.reset();
+        self.right.reset();
+    }
+
+    /// Distance of the midpoint between the wheels.
+    pub fn distance_mm(&self) -> i32 {
+        (self.left.distance_mm() + self.right.distance_mm()) / 2
+    }
+
+    /// Forward speed in millimeters per second.
+    pub fn speed(&self) -> i32 {
+        (self.left.speed() + self.right.speed()) / 2
+    }
+
+    /// Turn rate in milliradians per second, positive to the left.
+    pub fn yaw_rate(&self) -> i32 {
+        let diff = (self.right.speed() - self.left.speed()) as i64;
+        (diff * 1_000 / self.track_mm.max(1) as i64) as i32
+    }
+}
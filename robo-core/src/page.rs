@@ -0,0 +1,145 @@
+//! Helpers for SSD1306 display pages: 8 pixel high stripes with one byte per
+//! column and the top pixel in the least significant bit.
+
+/// Pixel rows in one display page.
+pub const PAGE_HEIGHT: usize = 8;
+
+/// Renders `text` into a page using a sprite font of 8x8 glyphs, stored in
+/// the order of `alphabet`. Characters missing from the font stay blank.
+pub fn text(font: &[u8], alphabet: &[u8], text: &[u8], buf: &mut [u8]) {
+    buf.fill(0);
+    for (cell, ch) in buf.chunks_mut(PAGE_HEIGHT).zip(text) {
+        if let Some(index) = alphabet.iter().position(|glyph| glyph == ch) {
+            let glyph = font.get(index * PAGE_HEIGHT..(index + 1) * PAGE_HEIGHT);
+            if let Some(glyph) = glyph {
+                cell.copy_from_slice(&glyph[..cell.len()]);
+            }
+        }
+    }
+}
+
+/// Inverts the columns in `range`, e.g. to highlight a selection.
+pub fn invert(buf: &mut [u8], range: core::ops::Range<usize>) {
+    for col in buf[range].iter_mut() {
+        *col = !*col;
+    }
+}
+
+/// Fixed size line of text, filled through `core::fmt::Write`. Output past
+/// the end is dropped.
+pub struct TextLine<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> TextLine<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [b' '; N],
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.buf = [b' '; N];
+        self.len = 0;
+    }
+
+    /// The whole line, padded with spaces.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl<const N: usize> Default for TextLine<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> core::fmt::Write for TextLine<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            if self.len < N {
+                self.buf[self.len] = byte;
+                self.len += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `PAGES` display pages of `W` columns, for rendering a frame in horizontal
+/// bands instead of keeping all of it in RAM.
+///
+/// Drawing uses display coordinates, whatever falls outside the band is
+/// clipped.
+pub struct Band<const W: usize, const PAGES: usize> {
+    pages: [[u8; W]; PAGES],
+    page: usize,
+}
+
+impl<const W: usize, const PAGES: usize> Band<W, PAGES> {
+    pub const fn new() -> Self {
+        Self {
+            pages: [[0; W]; PAGES],
+            page: 0,
+        }
+    }
+
+    /// Clears the band and moves it to display page `page`.
+    pub fn start(&mut self, page: usize) {
+        self.pages = [[0; W]; PAGES];
+        self.page = page;
+    }
+
+    /// First display page covered.
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    /// The band content, page after page.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.pages.as_flattened()
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, on: bool) {
+        let top = (self.page * PAGE_HEIGHT) as i32;
+        if x < 0 || x >= W as i32 || y < top || y >= top + (PAGES * PAGE_HEIGHT) as i32 {
+            return;
+        }
+        let row = (y - top) as usize;
+        let col = &mut self.pages[row / PAGE_HEIGHT][x as usize];
+        let mask = 1 << (row % PAGE_HEIGHT);
+        if on {
+            *col |= mask;
+        } else {
+            *col &= !mask;
+        }
+    }
+
+    /// Copies a bitmap in page format, `width` columns by `height` rows, to
+    /// `x`, `y`. Pixels off in the bitmap are cleared, like sprites overwrite
+    /// what the display showed.
+    pub fn blit(&mut self, x: i32, y: i32, width: usize, height: usize, bitmap: &[u8]) {
+        let top = (self.page * PAGE_HEIGHT) as i32;
+        let first = (top - y).max(0) as usize;
+        let last = ((top + (PAGES * PAGE_HEIGHT) as i32 - y).max(0) as usize).min(height);
+        for row in first..last {
+            let line = (row / PAGE_HEIGHT) * width;
+            let Some(cols) = bitmap.get(line..line + width) else {
+                return;
+            };
+            for (col, byte) in cols.iter().enumerate() {
+                let on = byte & (1 << (row % PAGE_HEIGHT)) != 0;
+                self.set_pixel(x + col as i32, y + row as i32, on);
+            }
+        }
+    }
+}
+
+impl<const W: usize, const PAGES: usize> Default for Band<W, PAGES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,161 @@
+//! PID controller in `Q16` fixed point.
+//!
+//! The derivative acts on the measurement rather than the error, so a
+//! setpoint step doesn't kick the output. The integral is kept as its
+//! contribution to the output: it stays within the output limits and stops
+//! growing while the output saturates in the direction of the error, and a
+//! gain change leaves the output where it was.
+
+use crate::q16::Q16;
+
+/// Gains in units of the controller output per unit of error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gains {
+    pub kp: Q16,
+    /// Per second of accumulated error.
+    pub ki: Q16,
+    /// Per unit of error change per second.
+    pub kd: Q16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub gains: Gains,
+    /// Period of `update`.
+    pub sample_ms: u32,
+    pub out_min: Q16,
+    pub out_max: Q16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            gains: Gains {
+                kp: Q16::ONE,
+                ki: Q16::ZERO,
+                kd: Q16::ZERO,
+            },
+            sample_ms: 10,
+            out_min: Q16::MIN,
+            out_max: Q16::MAX,
+        }
+    }
+}
+
+pub struct Pid {
+    config: Config,
+    /// `ki` and `kd` scaled to one sample.
+    ki: Q16,
+    kd: Q16,
+    integral: Q16,
+    last_measurement: Option<Q16>,
+    /// Error and measurement change of the last update, for bumpless gain
+    /// changes.
+    last_error: Q16,
+    last_delta: Q16,
+    output: Q16,
+}
+
+impl Pid {
+    pub fn new(config: Config) -> Self {
+        let mut pid = Self {
+            config,
+            ki: Q16::ZERO,
+            kd: Q16::ZERO,
+            integral: Q16::ZERO,
+            last_measurement: None,
+            last_error: Q16::ZERO,
+            last_delta: Q16::ZERO,
+            output: Q16::ZERO,
+        };
+        pid.scale_gains();
+        pid
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn scale_gains(&mut self) {
+        let Config {
+            gains, sample_ms, ..
+        } = self.config;
+        let sample_ms = sample_ms.clamp(1, i32::MAX as u32) as i32;
+        self.ki = gains.ki * Q16::from_ratio(sample_ms, 1_000);
+        self.kd = gains.kd * Q16::from_ratio(1_000, sample_ms);
+    }
+
+    /// Changes the gains without a step in the output: the integral takes
+    /// up the difference in the proportional and derivative terms.
+    pub fn set_gains(&mut self, gains: Gains) {
+        let before = self.config.gains.kp * self.last_error - self.kd * self.last_delta;
+        self.config.gains = gains;
+        self.scale_gains();
+        let after = gains.kp * self.last_error - self.kd * self.last_delta;
+        if self.last_measurement.is_some() {
+            self.integral =
+                (self.integral + before - after).clamp(self.config.out_min, self.config.out_max);
+        }
+    }
+
+    /// Keeps the gains per second, so the response stays the same.
+    pub fn set_sample_ms(&mut self, sample_ms: u32) {
+        self.config.sample_ms = sample_ms;
+        self.scale_gains();
+    }
+
+    pub fn set_limits(&mut self, out_min: Q16, out_max: Q16) {
+        self.config.out_min = out_min;
+        self.config.out_max = out_max.max(out_min);
+        self.integral = self.integral.clamp(out_min, self.config.out_max);
+        self.output = self.output.clamp(out_min, self.config.out_max);
+    }
+
+    /// Starts over from `output`, e.g. when switching from manual control,
+    /// so the first update continues from there.
+    pub fn reset(&mut self, output: Q16) {
+        self.output = output.clamp(self.config.out_min, self.config.out_max);
+        self.integral = self.output;
+        self.last_measurement = None;
+        self.last_error = Q16::ZERO;
+        self.last_delta = Q16::ZERO;
+    }
+
+    pub fn output(&self) -> Q16 {
+        self.output
+    }
+
+    /// Integral contribution to the output.
+    pub fn integral(&self) -> Q16 {
+        self.integral
+    }
+
+    /// Call every `sample_ms`, returns the new output.
+    pub fn update(&mut self, setpoint: Q16, measurement: Q16) -> Q16 {
+        let Config {
+            gains,
+            out_min,
+            out_max,
+            ..
+        } = self.config;
+        let error = setpoint - measurement;
+        let delta = measurement - self.last_measurement.unwrap_or(measurement);
+
+        let integral = (self.integral + self.ki * error).clamp(out_min, out_max);
+        let unclamped = gains.kp * error + integral - self.kd * delta;
+        let output = unclamped.clamp(out_min, out_max);
+        // Integrates only while that doesn't drive the output further into
+        // saturation
+        let winding = (unclamped > out_max && error.is_positive())
+            || (unclamped < out_min && error.is_negative());
+        if !winding {
+            self.integral = integral;
+        }
+
+        self.last_measurement = Some(measurement);
+        self.last_error = error;
+        self.last_delta = delta;
+        self.output = output;
+        output
+    }
+}
@@ -0,0 +1,168 @@
+//! Saturating Q16.16 fixed-point numbers for control loops.
+//!
+//! Values range from -32768 to just below 32768 in steps of 1/65536.
+//! Arithmetic saturates at the ends of the range instead of wrapping, so an
+//! overflowing controller term pins at its limit rather than flipping sign.
+
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+const FRAC_BITS: u32 = 16;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Q16(i32);
+
+impl Q16 {
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(1 << FRAC_BITS);
+    pub const MIN: Self = Self(i32::MIN);
+    pub const MAX: Self = Self(i32::MAX);
+
+    pub const fn from_raw(raw: i32) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> i32 {
+        self.0
+    }
+
+    /// Saturates outside the range.
+    pub const fn from_int(value: i32) -> Self {
+        Self::saturate((value as i64) << FRAC_BITS)
+    }
+
+    /// `num / den`, e.g. `from_ratio(3, 2)` is 1.5. Rounds to nearest.
+    pub const fn from_ratio(num: i32, den: i32) -> Self {
+        Self::div_raw((num as i64) << FRAC_BITS, den as i64)
+    }
+
+    const fn saturate(raw: i64) -> Self {
+        if raw > i32::MAX as i64 {
+            Self::MAX
+        } else if raw < i32::MIN as i64 {
+            Self::MIN
+        } else {
+            Self(raw as i32)
+        }
+    }
+
+    /// Rounds to nearest, division by zero saturates by the sign of `num`.
+    const fn div_raw(num: i64, den: i64) -> Self {
+        if den == 0 {
+            return if num < 0 { Self::MIN } else { Self::MAX };
+        }
+        let half = den.abs() / 2;
+        let num = if (num < 0) == (den < 0) {
+            num + half * den.signum()
+        } else {
+            num - half * den.signum()
+        };
+        Self::saturate(num / den)
+    }
+
+    /// Rounds to nearest, division by zero saturates by the sign of `self`.
+    pub const fn quotient(self, rhs: Self) -> Self {
+        Self::div_raw((self.0 as i64) << FRAC_BITS, rhs.0 as i64)
+    }
+
+    /// Rounded to the nearest integer, halves away from zero.
+    pub const fn to_int(self) -> i32 {
+        let half = 1 << (FRAC_BITS - 1);
+        let raw = self.0 as i64;
+        let rounded = if raw < 0 { raw - half } else { raw + half };
+        (rounded / Self::ONE.0 as i64) as i32
+    }
+
+    pub const fn abs(self) -> Self {
+        Self::saturate((self.0 as i64).abs())
+    }
+
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        Self(self.0.clamp(min.0, max.0))
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub const fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+}
+
+impl From<i16> for Q16 {
+    fn from(value: i16) -> Self {
+        Self((value as i32) << FRAC_BITS)
+    }
+}
+
+impl Add for Q16 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl Sub for Q16 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Mul for Q16 {
+    type Output = Self;
+
+    /// Rounds to nearest.
+    fn mul(self, rhs: Self) -> Self {
+        let product = self.0 as i64 * rhs.0 as i64;
+        Self::saturate((product + (1 << (FRAC_BITS - 1))) >> FRAC_BITS)
+    }
+}
+
+impl Div for Q16 {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        Self::quotient(self, rhs)
+    }
+}
+
+impl Neg for Q16 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
+
+impl AddAssign for Q16 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Q16 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/// Four decimals, rounded towards zero.
+impl fmt::Display for Q16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw = self.0 as i64;
+        let sign = if raw < 0 { "-" } else { "" };
+        let raw = raw.unsigned_abs();
+        let frac = ((raw & (Self::ONE.0 as u64 - 1)) * 10_000) >> FRAC_BITS;
+        write!(f, "{}{}.{:04}", sign, raw >> FRAC_BITS, frac)
+    }
+}
+
+impl fmt::Debug for Q16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Q16({})", self)
+    }
+}
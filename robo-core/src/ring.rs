@@ -0,0 +1,88 @@
+/// Fixed capacity buffer keeping the latest `N` values.
+#[derive(Clone)]
+pub struct Ring<T, const N: usize> {
+    buf: [Option<T>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [None; N],
+            head: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> Default for Ring<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Appends a value, returning the oldest one when it had to make room.
+    pub fn push(&mut self, value: T) -> Option<T> {
+        let evicted = if self.is_full() {
+            self.buf[self.head]
+        } else {
+            self.len += 1;
+            None
+        };
+        self.buf[self.head] = Some(value);
+        self.head = (self.head + 1) % N;
+        evicted
+    }
+
+    /// Removes and returns the oldest value.
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        let oldest = self.oldest()?;
+        self.len -= 1;
+        Some(oldest)
+    }
+
+    /// Value at `index`, counting from the oldest one.
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        self.buf[(self.head + N - self.len + index) % N]
+    }
+
+    pub fn oldest(&self) -> Option<T> {
+        self.get(0)
+    }
+
+    pub fn latest(&self) -> Option<T> {
+        self.len.checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    /// Values from the oldest to the latest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = T> + ExactSizeIterator + '_ {
+        // Every slot between the oldest and the latest value is filled
+        (0..self.len).map(|index| self.get(index).unwrap())
+    }
+}
@@ -0,0 +1,53 @@
+use crate::filter::Ema;
+use crate::ring::Ring;
+
+/// Samples of one channel as produced by the sampling interrupt, with the
+/// history kept for consumers running at their own rate.
+pub struct SampleBuffer<const N: usize> {
+    samples: Ring<u16, N>,
+    filter: Ema,
+    count: u32,
+}
+
+impl<const N: usize> SampleBuffer<N> {
+    pub fn new(filter_shift: u8) -> Self {
+        Self {
+            samples: Ring::new(),
+            filter: Ema::new(filter_shift),
+            count: 0,
+        }
+    }
+
+    pub fn push(&mut self, value: u16) {
+        self.samples.push(value);
+        self.filter.update(value);
+        self.count = self.count.wrapping_add(1);
+    }
+
+    /// Changes the smoothing of `filtered`, restarting the filter.
+    pub fn set_filter_shift(&mut self, shift: u8) {
+        self.filter = Ema::new(shift);
+    }
+
+    pub fn latest(&self) -> Option<u16> {
+        self.samples.latest()
+    }
+
+    pub fn filtered(&self) -> u16 {
+        self.filter.value()
+    }
+
+    /// Total number of samples pushed, wrapping on overflow.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn samples(&self) -> &Ring<u16, N> {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.filter.reset();
+    }
+}
@@ -0,0 +1,83 @@
+/// Panel state driven by the screensaver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Power {
+    On,
+    /// Lowest contrast, content still readable.
+    Dim,
+    /// Panel switched off, the UI can skip rendering.
+    Off,
+}
+
+/// Inactivity screensaver protecting OLEDs from burn-in.
+///
+/// Dims the panel after `dim_after` UI ticks without activity and switches
+/// it off after `off_after` ticks, a timeout of 0 never fires. Button
+/// presses and pot moves larger than `threshold` count as activity.
+pub struct Screensaver {
+    dim_after: u32,
+    off_after: u32,
+    threshold: u16,
+    idle: u32,
+    /// Off by `sleep` regardless of the timeouts.
+    asleep: bool,
+    position: Option<u16>,
+}
+
+impl Screensaver {
+    pub const fn new(dim_after: u32, off_after: u32, threshold: u16) -> Self {
+        Self {
+            dim_after,
+            off_after,
+            threshold,
+            idle: 0,
+            asleep: false,
+            position: None,
+        }
+    }
+
+    pub fn set_timeouts(&mut self, dim_after: u32, off_after: u32) {
+        self.dim_after = dim_after;
+        self.off_after = off_after;
+    }
+
+    pub fn power(&self) -> Power {
+        let expired = |timeout| timeout > 0 && self.idle >= timeout;
+        if self.asleep || expired(self.off_after) {
+            Power::Off
+        } else if expired(self.dim_after) {
+            Power::Dim
+        } else {
+            Power::On
+        }
+    }
+
+    /// Counts a UI tick without activity.
+    pub fn tick(&mut self) {
+        self.idle = self.idle.saturating_add(1);
+    }
+
+    /// Records activity, e.g. a button press. Returns `true` if the panel
+    /// wasn't on, so the press can be used up for waking it.
+    pub fn wake(&mut self) -> bool {
+        let asleep = self.power() != Power::On;
+        self.idle = 0;
+        self.asleep = false;
+        asleep
+    }
+
+    /// Switches the panel off right away, until the next activity.
+    pub fn sleep(&mut self) {
+        self.asleep = true;
+    }
+
+    /// Feeds the pot position, moves past the threshold count as activity
+    /// like `wake`. Slow drift below it doesn't.
+    pub fn position(&mut self, position: u16) -> bool {
+        let reference = *self.position.get_or_insert(position);
+        if position.abs_diff(reference) <= self.threshold {
+            return false;
+        }
+        self.position = Some(position);
+        self.wake()
+    }
+}
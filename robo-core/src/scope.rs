@@ -0,0 +1,195 @@
+use crate::ring::Ring;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trigger {
+    pub edge: Edge,
+    pub level: u16,
+}
+
+impl Trigger {
+    pub const fn new(edge: Edge, level: u16) -> Self {
+        Self { edge, level }
+    }
+
+    pub fn crossed(&self, prev: u16, value: u16) -> bool {
+        match self.edge {
+            Edge::Rising => prev < self.level && value >= self.level,
+            Edge::Falling => prev > self.level && value <= self.level,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Waiting for the trigger while keeping the pre-trigger history.
+    Armed,
+    /// Collecting the samples after the trigger.
+    Triggered,
+    /// Capture complete until re-armed.
+    Done,
+}
+
+/// Captures a burst of `N` samples around a trigger event.
+///
+/// Once done, the capture holds `pre_trigger` samples before the trigger
+/// point, the trigger sample itself and the samples following it.
+pub struct Capture<const N: usize> {
+    samples: Ring<u16, N>,
+    trigger: Trigger,
+    pre_trigger: usize,
+    auto: Option<usize>,
+    state: State,
+    prev: Option<u16>,
+    waited: usize,
+    remaining: usize,
+}
+
+impl<const N: usize> Capture<N> {
+    pub fn new(trigger: Trigger, pre_trigger: usize) -> Self {
+        Self {
+            samples: Ring::new(),
+            trigger,
+            pre_trigger: pre_trigger.min(N - 1),
+            auto: None,
+            state: State::Armed,
+            prev: None,
+            waited: 0,
+            remaining: 0,
+        }
+    }
+
+    pub fn trigger(&self) -> Trigger {
+        self.trigger
+    }
+
+    pub fn set_trigger(&mut self, trigger: Trigger) {
+        self.trigger = trigger;
+    }
+
+    pub fn pre_trigger(&self) -> usize {
+        self.pre_trigger
+    }
+
+    pub fn set_pre_trigger(&mut self, pre_trigger: usize) {
+        self.pre_trigger = pre_trigger.min(N - 1);
+        self.arm();
+    }
+
+    /// Forces a capture after `samples` samples without a trigger, so a flat
+    /// signal still shows up. `None` waits for the trigger forever.
+    pub fn set_auto(&mut self, samples: Option<usize>) {
+        self.auto = samples;
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn arm(&mut self) {
+        self.samples.clear();
+        self.state = State::Armed;
+        self.prev = None;
+        self.waited = 0;
+    }
+
+    pub fn push(&mut self, value: u16) -> State {
+        match self.state {
+            State::Armed => {
+                self.samples.push(value);
+                let ready = self.samples.len() > self.pre_trigger;
+                let crossed = matches!(self.prev, Some(prev) if self.trigger.crossed(prev, value));
+                self.prev = Some(value);
+                self.waited += 1;
+                let forced = matches!(self.auto, Some(auto) if self.waited >= auto.max(N));
+                if ready && (crossed || forced) {
+                    self.remaining = N - self.pre_trigger - 1;
+                    self.state = if self.remaining == 0 {
+                        State::Done
+                    } else {
+                        State::Triggered
+                    };
+                }
+            }
+            State::Triggered => {
+                self.samples.push(value);
+                self.remaining -= 1;
+                if self.remaining == 0 {
+                    self.state = State::Done;
+                }
+            }
+            State::Done => {}
+        }
+        self.state
+    }
+
+    /// Captured samples, oldest first. Complete only in the `Done` state.
+    pub fn samples(&self) -> &Ring<u16, N> {
+        &self.samples
+    }
+}
+
+/// Maps samples onto the rows of a plot area made of whole display pages.
+#[derive(Clone, Copy, Debug)]
+pub struct Plot {
+    /// First display page of the plot area.
+    pub page: u8,
+    /// Height of the plot area in pixels, a multiple of 8.
+    pub height: u8,
+    /// Sample value shown at the top edge.
+    pub full_scale: u16,
+    /// Horizontal grid spacing in pixels.
+    pub grid_x: u8,
+    /// Vertical grid spacing in pixels.
+    pub grid_y: u8,
+}
+
+impl Plot {
+    /// Row of `value`, counted from the top of the plot area.
+    pub fn row(&self, value: u16) -> u8 {
+        let bottom = self.height as u32 - 1;
+        let value = value.min(self.full_scale) as u32;
+        (bottom - value * bottom / self.full_scale.max(1) as u32) as u8
+    }
+
+    /// Renders one display page of the trace, given as one row per column.
+    ///
+    /// Consecutive columns are joined with vertical spans so steep edges stay
+    /// visible. `marker` draws a dashed horizontal line, e.g. the trigger
+    /// level.
+    pub fn render_page(&self, rows: &[u8], marker: Option<u8>, page: u8, buf: &mut [u8]) {
+        let top = page.wrapping_sub(self.page) as u32 * 8;
+        for (x, col) in buf.iter_mut().enumerate() {
+            let mut bits = 0u8;
+            for bit in 0..8 {
+                let y = top + bit;
+                if y >= self.height as u32 {
+                    break;
+                }
+                let on_grid_x = self.grid_x > 0 && x.is_multiple_of(self.grid_x as usize);
+                let on_grid_y = self.grid_y > 0 && y.is_multiple_of(self.grid_y as u32);
+                let grid = (on_grid_x && y.is_multiple_of(2)) || (on_grid_y && x.is_multiple_of(4));
+                let level = matches!(marker, Some(row) if row as u32 == y && x % 4 < 2);
+                if grid || level {
+                    bits |= 1 << bit;
+                }
+            }
+            if let Some(&row) = rows.get(x) {
+                let prev = if x > 0 { rows[x - 1] } else { row };
+                let (from, to) = (prev.min(row) as u32, prev.max(row) as u32);
+                for bit in 0..8 {
+                    let y = top + bit;
+                    if y >= from && y <= to {
+                        bits |= 1 << bit;
+                    }
+                }
+            }
+            *col = bits;
+        }
+    }
+}
@@ -0,0 +1,124 @@
+/// What a screen has to do on a UI tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Refresh {
+    /// The screen was just switched to, everything must be drawn.
+    Full,
+    /// Regular update, only changed widgets need drawing.
+    Update,
+}
+
+/// Cycles through a fixed set of screens, each refreshed at its own rate.
+///
+/// Screens are identified by any `Copy` value, usually an app enum, paired
+/// with an update period in UI ticks. A period of 0 draws the screen once
+/// when it's switched to.
+pub struct Screens<S, const N: usize> {
+    screens: [(S, u16); N],
+    current: usize,
+    elapsed: u16,
+    redraw: bool,
+}
+
+impl<S: Copy + PartialEq, const N: usize> Screens<S, N> {
+    /// Starts on the first screen with a full redraw pending.
+    pub const fn new(screens: [(S, u16); N]) -> Self {
+        Self {
+            screens,
+            current: 0,
+            elapsed: 0,
+            redraw: true,
+        }
+    }
+
+    pub fn current(&self) -> S {
+        self.screens[self.current].0
+    }
+
+    /// Switches to the following screen, wrapping to the first.
+    pub fn next(&mut self) {
+        self.select((self.current + 1) % N);
+    }
+
+    /// Switches to `screen`, e.g. when an alert needs attention. Returns
+    /// `false` for a screen that isn't managed.
+    pub fn show(&mut self, screen: S) -> bool {
+        match self.screens.iter().position(|(s, _)| *s == screen) {
+            Some(idx) => {
+                if idx != self.current {
+                    self.select(idx);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forces a full redraw of the current screen on the next tick.
+    pub fn invalidate(&mut self) {
+        self.redraw = true;
+    }
+
+    /// Advances by one UI tick and tells if the current screen is due.
+    pub fn tick(&mut self) -> Option<Refresh> {
+        if self.redraw {
+            self.redraw = false;
+            self.elapsed = 0;
+            return Some(Refresh::Full);
+        }
+        let period = self.screens[self.current].1;
+        if period == 0 {
+            return None;
+        }
+        self.elapsed += 1;
+        if self.elapsed < period {
+            return None;
+        }
+        self.elapsed = 0;
+        Some(Refresh::Update)
+    }
+
+    fn select(&mut self, idx: usize) {
+        self.current = idx;
+        self.redraw = true;
+    }
+}
+
+/// Boot splash shown for a number of UI ticks before the app screens.
+pub struct Splash {
+    ticks: u16,
+    elapsed: u16,
+}
+
+impl Splash {
+    pub const fn new(ticks: u16) -> Self {
+        Self { ticks, elapsed: 0 }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.ticks
+    }
+
+    /// Counts a UI tick, returns `true` on the tick the splash ends so the
+    /// app screen can be drawn in full.
+    pub fn tick(&mut self) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        self.elapsed += 1;
+        self.is_done()
+    }
+
+    /// Ends the splash early, e.g. on a button press.
+    pub fn skip(&mut self) {
+        self.elapsed = self.ticks;
+    }
+
+    /// Elapsed part of the splash scaled to `0..=full`, e.g. for a progress
+    /// bar.
+    pub fn progress(&self, full: usize) -> usize {
+        match self.ticks {
+            0 => full,
+            ticks => self.elapsed.min(ticks) as usize * full / ticks as usize,
+        }
+    }
+}
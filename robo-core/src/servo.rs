@@ -0,0 +1,176 @@
+//! RC servo commands: angles to pulse widths with per-servo calibration,
+//! slew rate limiting and a detached state without pulses.
+//!
+//! Servos take a pulse every `PERIOD_US`, its width sets the position.
+//! Angles run from 0 to 180 degrees: 0 maps to `min_us`, 90 to `center_us`
+//! and 180 to `max_us`, linearly in between, so a servo with an off-center
+//! neutral still centers at 90.
+
+/// Pulse period of the 50 Hz servo frame.
+pub const PERIOD_US: u16 = 20_000;
+/// Largest angle.
+pub const MAX_ANGLE: u16 = 180;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    pub min_us: u16,
+    pub center_us: u16,
+    pub max_us: u16,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            min_us: 1_000,
+            center_us: 1_500,
+            max_us: 2_000,
+        }
+    }
+}
+
+impl Calibration {
+    /// Whether the widths ascend and fit in the period.
+    pub const fn is_valid(&self) -> bool {
+        self.min_us < self.center_us && self.center_us < self.max_us && self.max_us < PERIOD_US
+    }
+
+    /// Pulse width for `angle`, clamped to 0..=180.
+    pub fn to_us(&self, angle: u16) -> u16 {
+        let half = MAX_ANGLE / 2;
+        let angle = angle.min(MAX_ANGLE);
+        let (from, to, offset) = if angle < half {
+            (self.min_us, self.center_us, angle)
+        } else {
+            (self.center_us, self.max_us, angle - half)
+        };
+        let span = to as i32 - from as i32;
+        (from as i32 + (span * offset as i32 + half as i32 / 2) / half as i32) as u16
+    }
+
+    /// Angle of a pulse width, rounded to the nearest degree. Stays in
+    /// 0..=180 even for an invalid calibration.
+    pub fn to_angle(&self, us: u16) -> u16 {
+        let half = MAX_ANGLE / 2;
+        let us = us.max(self.min_us).min(self.max_us);
+        let (from, to, base) = if us < self.center_us {
+            (self.min_us, self.center_us, 0)
+        } else {
+            (self.center_us, self.max_us, half)
+        };
+        let span = to.saturating_sub(from).max(1) as u32;
+        let offset = (us.saturating_sub(from) as u32 * half as u32 + span / 2) / span;
+        base + offset.min(half as u32) as u16
+    }
+}
+
+pub struct Servo {
+    calibration: Calibration,
+    /// Largest pulse width change per second, 0 for none.
+    slew: u16,
+    target: Option<u16>,
+    pulse: Option<u16>,
+    /// Slew progress short of a whole microsecond, in thousandths.
+    carry: u32,
+}
+
+impl Servo {
+    /// Starts detached. Panics on an invalid calibration.
+    pub const fn new(calibration: Calibration) -> Self {
+        assert!(calibration.is_valid(), "invalid servo calibration");
+        Self {
+            calibration,
+            slew: 0,
+            target: None,
+            pulse: None,
+            carry: 0,
+        }
+    }
+
+    pub fn calibration(&self) -> &Calibration {
+        &self.calibration
+    }
+
+    /// Keeps the pulse width, clamped to the new limits. An invalid
+    /// calibration is refused and the current one kept.
+    pub fn set_calibration(&mut self, calibration: Calibration) -> bool {
+        if !calibration.is_valid() {
+            return false;
+        }
+        self.calibration = calibration;
+        let clamp = |us: u16| us.clamp(calibration.min_us, calibration.max_us);
+        self.target = self.target.map(clamp);
+        self.pulse = self.pulse.map(clamp);
+        true
+    }
+
+    /// Microseconds of pulse width change per second, 0 moves at once.
+    /// About 1000 µs sweep the full range of common servos.
+    pub fn set_slew(&mut self, slew: u16) {
+        self.slew = slew;
+    }
+
+    pub fn slew(&self) -> u16 {
+        self.slew
+    }
+
+    pub fn set_angle(&mut self, angle: u16) {
+        self.set_us(self.calibration.to_us(angle));
+    }
+
+    /// Pulse width, clamped to the calibration. A detached servo jumps to
+    /// it, its position is unknown.
+    pub fn set_us(&mut self, us: u16) {
+        let us = us.clamp(self.calibration.min_us, self.calibration.max_us);
+        self.target = Some(us);
+        if self.pulse.is_none() {
+            self.pulse = Some(us);
+        }
+    }
+
+    /// Stops the pulses, the servo goes limp.
+    pub fn detach(&mut self) {
+        self.target = None;
+        self.pulse = None;
+        self.carry = 0;
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.pulse.is_some()
+    }
+
+    /// Current pulse width on the slew, `None` when detached.
+    pub fn pulse_us(&self) -> Option<u16> {
+        self.pulse
+    }
+
+    pub fn target_us(&self) -> Option<u16> {
+        self.target
+    }
+
+    /// Angle of the current pulse width.
+    pub fn angle(&self) -> Option<u16> {
+        self.pulse.map(|us| self.calibration.to_angle(us))
+    }
+
+    /// Moves along the slew by `elapsed_ms` and returns the pulse width.
+    pub fn tick(&mut self, elapsed_ms: u32) -> Option<u16> {
+        let (pulse, target) = match (self.pulse, self.target) {
+            (Some(pulse), Some(target)) => (pulse, target),
+            _ => return None,
+        };
+        let pulse = if self.slew == 0 {
+            target
+        } else {
+            self.carry += self.slew as u32 * elapsed_ms;
+            let step = (self.carry / 1_000).min(u16::MAX as u32) as i32;
+            self.carry %= 1_000;
+            let gap = target as i32 - pulse as i32;
+            (pulse as i32 + gap.clamp(-step, step)) as u16
+        };
+        if pulse == target {
+            self.carry = 0;
+        }
+        self.pulse = Some(pulse);
+        self.pulse
+    }
+}
@@ -0,0 +1,148 @@
+//! Ultrasonic ranging, e.g. with an HC-SR04: echo widths to distances with
+//! temperature compensation, missing echoes and median filtering.
+//!
+//! The sensor answers a trigger pulse with an echo pulse as long as the
+//! sound takes to the obstacle and back. Without an obstacle in range it
+//! holds the echo for about 38 ms, without a sensor there's no echo at all.
+
+use crate::filter::Median;
+
+/// Longest median window.
+pub const MAX_WINDOW: usize = 9;
+
+/// Outcome of one trigger as captured by the driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capture {
+    /// Echo pulse of this many microseconds.
+    Echo(u32),
+    /// The echo started but didn't end within the measurement period.
+    Unfinished,
+    /// No echo started.
+    Missing,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reading {
+    Distance(u16),
+    /// Nothing within range.
+    NoEcho,
+    /// No answer from the sensor.
+    Timeout,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Closer obstacles read as this distance, the sensor is blind below.
+    pub min_mm: u16,
+    /// Farther echoes count as no echo.
+    pub max_mm: u16,
+    /// Readings the median spans, up to `MAX_WINDOW`.
+    pub window: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            min_mm: 20,
+            max_mm: 4_000,
+            window: 5,
+        }
+    }
+}
+
+/// Speed of sound in air in mm/s at `temp_dc` tenths of a degree Celsius.
+pub fn speed_of_sound(temp_dc: i16) -> u32 {
+    (331_300 + temp_dc as i32 * 606 / 10).max(0) as u32
+}
+
+/// Distance to the obstacle for an echo of `echo_us`, half the way the
+/// sound travelled.
+pub fn echo_to_mm(echo_us: u32, temp_dc: i16) -> u32 {
+    (echo_us as u64 * speed_of_sound(temp_dc) as u64 / 2_000_000) as u32
+}
+
+pub struct Sonar {
+    config: Config,
+    temp_dc: i16,
+    /// Filtered distances, `u16::MAX` standing for no echo.
+    median: Median<MAX_WINDOW>,
+    last: Option<Reading>,
+    timeouts: u8,
+}
+
+impl Sonar {
+    /// Assumes 20 °C until `set_temperature`.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            temp_dc: 200,
+            median: Median::new(config.window as usize),
+            last: None,
+            timeouts: 0,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Restarts the filter.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+        self.median.set_window(config.window as usize);
+    }
+
+    /// Air temperature in tenths of a degree Celsius.
+    pub fn set_temperature(&mut self, temp_dc: i16) {
+        self.temp_dc = temp_dc;
+    }
+
+    pub fn temperature(&self) -> i16 {
+        self.temp_dc
+    }
+
+    /// Takes one capture and returns its unfiltered reading.
+    pub fn update(&mut self, capture: Capture) -> Reading {
+        let Config { min_mm, max_mm, .. } = self.config;
+        let reading = match capture {
+            Capture::Echo(echo_us) => match echo_to_mm(echo_us, self.temp_dc) {
+                mm if mm > max_mm as u32 => Reading::NoEcho,
+                mm => Reading::Distance((mm as u16).max(min_mm)),
+            },
+            Capture::Unfinished => Reading::NoEcho,
+            Capture::Missing => Reading::Timeout,
+        };
+        match reading {
+            Reading::Distance(mm) => {
+                self.median.update(mm);
+            }
+            Reading::NoEcho => {
+                self.median.update(u16::MAX);
+            }
+            Reading::Timeout => {}
+        }
+        self.timeouts = match reading {
+            Reading::Timeout => self.timeouts.saturating_add(1),
+            _ => 0,
+        };
+        self.last = Some(reading);
+        reading
+    }
+
+    /// Last unfiltered reading.
+    pub fn last(&self) -> Option<Reading> {
+        self.last
+    }
+
+    /// Median of the recent readings. Times out once a window of captures
+    /// in a row got no answer.
+    pub fn reading(&self) -> Option<Reading> {
+        if self.timeouts as usize >= self.median.window() {
+            return Some(Reading::Timeout);
+        }
+        self.median.value().map(|mm| match mm {
+            u16::MAX => Reading::NoEcho,
+            mm => Reading::Distance(mm),
+        })
+    }
+}
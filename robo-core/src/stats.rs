@@ -0,0 +1,137 @@
+use crate::ring::Ring;
+
+/// Summary of the samples in the window, in sample units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: u16,
+    pub max: u16,
+    pub mean: u16,
+    pub rms: u16,
+    pub std_dev: u16,
+}
+
+/// Extremes seen since the last reset, regardless of the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeakHold {
+    pub min: u16,
+    pub max: u16,
+}
+
+/// Running statistics over the last `window` samples, `window <= N`.
+///
+/// Sums are updated as samples enter and leave the window, so pushing is
+/// cheap enough for the sampling interrupt. Min and max scan the window when
+/// queried.
+pub struct WindowStats<const N: usize> {
+    samples: Ring<u16, N>,
+    window: usize,
+    sum: u64,
+    sum_sq: u64,
+    peak: Option<PeakHold>,
+}
+
+impl<const N: usize> WindowStats<N> {
+    pub fn new(window: usize) -> Self {
+        Self {
+            samples: Ring::new(),
+            window: window.clamp(1, N),
+            sum: 0,
+            sum_sq: 0,
+            peak: None,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Changes the window length, clamped to `1..=N`, and restarts the window.
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window.clamp(1, N);
+        self.clear();
+    }
+
+    pub fn push(&mut self, value: u16) {
+        while self.samples.len() >= self.window {
+            if let Some(oldest) = self.samples.pop_oldest() {
+                self.sum -= oldest as u64;
+                self.sum_sq -= oldest as u64 * oldest as u64;
+            }
+        }
+        self.samples.push(value);
+        self.sum += value as u64;
+        self.sum_sq += value as u64 * value as u64;
+        self.peak = Some(match self.peak {
+            Some(peak) => PeakHold {
+                min: peak.min.min(value),
+                max: peak.max.max(value),
+            },
+            None => PeakHold {
+                min: value,
+                max: value,
+            },
+        });
+    }
+
+    pub fn stats(&self) -> Stats {
+        let count = self.samples.len();
+        if count == 0 {
+            return Stats::default();
+        }
+        let n = count as u64;
+        let (min, max) = self
+            .samples
+            .iter()
+            .fold((u16::MAX, u16::MIN), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        let mean = (self.sum + n / 2) / n;
+        let rms = isqrt((self.sum_sq + n / 2) / n);
+        // n^2 * variance = n * sum(x^2) - sum(x)^2, exact in integers
+        let variance = (n * self.sum_sq).saturating_sub(self.sum * self.sum) / (n * n);
+        Stats {
+            count,
+            min,
+            max,
+            mean: mean as u16,
+            rms: rms as u16,
+            std_dev: isqrt(variance) as u16,
+        }
+    }
+
+    pub fn peak(&self) -> Option<PeakHold> {
+        self.peak
+    }
+
+    pub fn reset_peak(&mut self) {
+        self.peak = None;
+    }
+
+    /// Empties the window, the peak hold is kept.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.sum = 0;
+        self.sum_sq = 0;
+    }
+}
+
+/// Integer square root, rounded down.
+pub fn isqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+    let mut root = 0u64;
+    let mut bit = 1u64 << ((63 - value.leading_zeros()) & !1);
+    let mut rest = value;
+    while bit != 0 {
+        if rest >= root + bit {
+            rest -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
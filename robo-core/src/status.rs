@@ -0,0 +1,108 @@
+//! Status bar content shared by all screens of an app.
+
+use core::fmt;
+
+/// Indicator lit for a few UI ticks after something happened, e.g. serial
+/// traffic, so short bursts stay visible.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Activity {
+    remaining: u8,
+}
+
+impl Activity {
+    pub const fn new() -> Self {
+        Self { remaining: 0 }
+    }
+
+    pub fn hit(&mut self, ticks: u8) {
+        self.remaining = self.remaining.max(ticks);
+    }
+
+    pub fn tick(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0
+    }
+}
+
+/// Uptime, serial link activity, alarm flag and firmware version.
+#[derive(Clone, Copy, Debug)]
+pub struct Status {
+    ticks: u32,
+    tick_ms: u32,
+    rx: Activity,
+    tx: Activity,
+    pub alarm: bool,
+    version: &'static str,
+}
+
+impl Status {
+    /// Activity stays lit for at least this long.
+    const ACTIVITY_MS: u32 = 300;
+
+    /// `tick_ms` is the period of `tick`, `version` should be short, e.g.
+    /// major and minor only.
+    pub const fn new(tick_ms: u32, version: &'static str) -> Self {
+        Self {
+            ticks: 0,
+            tick_ms,
+            rx: Activity::new(),
+            tx: Activity::new(),
+            alarm: false,
+            version,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+        self.rx.tick();
+        self.tx.tick();
+    }
+
+    pub fn uptime_secs(&self) -> u32 {
+        (self.ticks as u64 * self.tick_ms as u64 / 1_000) as u32
+    }
+
+    pub fn received(&mut self) {
+        let ticks = self.activity_ticks();
+        self.rx.hit(ticks);
+    }
+
+    pub fn sent(&mut self) {
+        let ticks = self.activity_ticks();
+        self.tx.hit(ticks);
+    }
+
+    /// Writes the bar as `HH:MM:SS <>! 0.1`: uptime, `<` on receive, `>` on
+    /// send, `!` on alarm and the version right aligned in what's left of
+    /// `width` characters.
+    pub fn write_line(&self, width: usize, out: &mut impl fmt::Write) -> fmt::Result {
+        let secs = self.uptime_secs();
+        let flag = |on: bool, ch: char| if on { ch } else { ' ' };
+        write!(
+            out,
+            "{:02}:{:02}:{:02} {}{}{}",
+            secs / 3_600 % 100,
+            secs / 60 % 60,
+            secs % 60,
+            flag(self.rx.is_active(), '<'),
+            flag(self.tx.is_active(), '>'),
+            flag(self.alarm, '!'),
+        )?;
+        const USED: usize = 12;
+        let width = width.saturating_sub(USED);
+        if width > 0 {
+            let version = &self.version[..self.version.len().min(width - 1)];
+            write!(out, " {: >w$}", version, w = width - 1)?;
+        }
+        Ok(())
+    }
+
+    fn activity_ticks(&self) -> u8 {
+        Self::ACTIVITY_MS
+            .div_ceil(self.tick_ms.max(1))
+            .clamp(1, u8::MAX as u32) as u8
+    }
+}
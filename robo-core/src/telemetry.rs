@@ -0,0 +1,232 @@
+//! Framed binary telemetry.
+//!
+//! A packet is laid out little endian as
+//!
+//! | seq | timestamp | channel | values       | crc16 |
+//! |-----|-----------|---------|--------------|-------|
+//! | u16 | u32       | u8      | u16 x 0..=8  | u16   |
+//!
+//! The CRC (CRC-16/CCITT-FALSE) covers everything before it. The packet is
+//! COBS encoded and terminated with a `0x00` byte, so a receiver joining a
+//! running stream resynchronizes at the next delimiter.
+
+use crate::cobs;
+
+/// Values carried by one packet at most.
+pub const MAX_VALUES: usize = 8;
+/// Frame delimiter on the wire.
+pub const DELIMITER: u8 = 0x00;
+
+const HEADER_LEN: usize = 7;
+const CRC_LEN: usize = 2;
+const MAX_PACKET_LEN: usize = HEADER_LEN + MAX_VALUES * 2 + CRC_LEN;
+
+/// Longest encoded frame, delimiter included.
+pub const MAX_FRAME_LEN: usize = cobs::max_encoded_len(MAX_PACKET_LEN) + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// COBS decoding failed.
+    Framing,
+    /// Packet shorter than a header and CRC, or with an odd payload.
+    Length,
+    /// CRC mismatch.
+    Crc { expected: u16, actual: u16 },
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Framing => write!(f, "malformed frame"),
+            Error::Length => write!(f, "invalid packet length"),
+            Error::Crc { expected, actual } => {
+                write!(f, "crc mismatch: {:04x} != {:04x}", actual, expected)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub seq: u16,
+    /// Milliseconds since start, wrapping.
+    pub timestamp: u32,
+    pub channel: u8,
+    values: [u16; MAX_VALUES],
+    len: u8,
+}
+
+impl Packet {
+    /// Values beyond `MAX_VALUES` are dropped.
+    pub fn new(seq: u16, timestamp: u32, channel: u8, values: &[u16]) -> Self {
+        let len = values.len().min(MAX_VALUES);
+        let mut packet = Self {
+            seq,
+            timestamp,
+            channel,
+            values: [0; MAX_VALUES],
+            len: len as u8,
+        };
+        packet.values[..len].copy_from_slice(&values[..len]);
+        packet
+    }
+
+    pub fn values(&self) -> &[u16] {
+        &self.values[..self.len as usize]
+    }
+
+    /// Writes the delimited frame into `buf` and returns its length.
+    pub fn encode(&self, buf: &mut [u8; MAX_FRAME_LEN]) -> usize {
+        let mut raw = [0; MAX_PACKET_LEN];
+        raw[0..2].copy_from_slice(&self.seq.to_le_bytes());
+        raw[2..6].copy_from_slice(&self.timestamp.to_le_bytes());
+        raw[6] = self.channel;
+        let mut len = HEADER_LEN;
+        for value in self.values() {
+            raw[len..len + 2].copy_from_slice(&value.to_le_bytes());
+            len += 2;
+        }
+        let crc = crc16(&raw[..len]);
+        raw[len..len + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+        len += CRC_LEN;
+
+        let encoded = cobs::encode(&raw[..len], &mut buf[..]).unwrap_or(0);
+        buf[encoded] = DELIMITER;
+        encoded + 1
+    }
+
+    /// Decodes one frame, with or without its trailing delimiter.
+    pub fn decode(frame: &[u8]) -> Result<Self, Error> {
+        let frame = frame.strip_suffix(&[DELIMITER]).unwrap_or(frame);
+        let mut raw = [0; MAX_PACKET_LEN];
+        let len = cobs::decode(frame, &mut raw).ok_or(Error::Framing)?;
+        if len < HEADER_LEN + CRC_LEN || !(len - HEADER_LEN - CRC_LEN).is_multiple_of(2) {
+            return Err(Error::Length);
+        }
+        let (raw, crc) = raw[..len].split_at(len - CRC_LEN);
+        let expected = u16::from_le_bytes([crc[0], crc[1]]);
+        let actual = crc16(raw);
+        if actual != expected {
+            return Err(Error::Crc { expected, actual });
+        }
+
+        let mut values = [0; MAX_VALUES];
+        let payload = raw[HEADER_LEN..].chunks_exact(2);
+        let count = payload.len();
+        for (value, bytes) in values.iter_mut().zip(payload) {
+            *value = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(Self {
+            seq: u16::from_le_bytes([raw[0], raw[1]]),
+            timestamp: u32::from_le_bytes([raw[2], raw[3], raw[4], raw[5]]),
+            channel: raw[6],
+            values,
+            len: count as u8,
+        })
+    }
+}
+
+/// Numbers outgoing packets.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Encoder {
+    seq: u16,
+}
+
+impl Encoder {
+    pub const fn new() -> Self {
+        Self { seq: 0 }
+    }
+
+    /// Sequence number of the next packet.
+    pub fn seq(&self) -> u16 {
+        self.seq
+    }
+
+    /// Encodes the next packet into `buf` and returns the frame to send.
+    pub fn encode<'a>(
+        &mut self,
+        timestamp: u32,
+        channel: u8,
+        values: &[u16],
+        buf: &'a mut [u8; MAX_FRAME_LEN],
+    ) -> &'a [u8] {
+        let packet = Packet::new(self.seq, timestamp, channel, values);
+        self.seq = self.seq.wrapping_add(1);
+        let len = packet.encode(buf);
+        &buf[..len]
+    }
+}
+
+/// CRC-16/CCITT-FALSE: polynomial `0x1021`, initial value `0xffff`.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(feature = "std")]
+pub use self::stream::Decoder;
+
+#[cfg(feature = "std")]
+mod stream {
+    use super::*;
+
+    /// Splits a byte stream into frames and decodes them, for host tools.
+    #[derive(Debug, Default)]
+    pub struct Decoder {
+        frame: Vec<u8>,
+        last_seq: Option<u16>,
+        lost: u32,
+    }
+
+    impl Decoder {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Feeds received bytes and returns the packets completed by them.
+        /// Bytes before the first delimiter may be a partial frame and yield
+        /// an error.
+        pub fn feed(&mut self, bytes: &[u8]) -> Vec<Result<Packet, Error>> {
+            let mut packets = Vec::new();
+            for &byte in bytes {
+                if byte != DELIMITER {
+                    // Longer frames fail to decode anyway, don't grow on noise
+                    if self.frame.len() <= MAX_FRAME_LEN {
+                        self.frame.push(byte);
+                    }
+                    continue;
+                }
+                if self.frame.is_empty() {
+                    continue;
+                }
+                let packet = Packet::decode(&self.frame);
+                self.frame.clear();
+                if let Ok(packet) = &packet {
+                    if let Some(last) = self.last_seq {
+                        self.lost += packet.seq.wrapping_sub(last).wrapping_sub(1) as u32;
+                    }
+                    self.last_seq = Some(packet.seq);
+                }
+                packets.push(packet);
+            }
+            packets
+        }
+
+        /// Packets missing from the sequence numbers seen so far.
+        pub fn lost(&self) -> u32 {
+            self.lost
+        }
+    }
+
+    impl std::error::Error for Error {}
+}
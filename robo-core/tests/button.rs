@@ -0,0 +1,147 @@
+use robo_core::button::{Button, Config, Event};
+
+const CONFIG: Config = Config {
+    debounce_ms: 20,
+    double_ms: 250,
+    long_ms: 800,
+    repeat_ms: 200,
+};
+
+/// Feeds raw edges `(time, pressed)` and polls every 5 ms until `end`,
+/// returning the events with the time they were reported.
+fn run(config: Config, edges: &[(u32, bool)], end: u32) -> Vec<(u32, Event)> {
+    let mut button = Button::new(config);
+    let mut edges = edges.iter().peekable();
+    let mut events = Vec::new();
+    for now in 0..=end {
+        while let Some(&&(at, pressed)) = edges.peek() {
+            if at > now {
+                break;
+            }
+            button.edge(at, pressed);
+            edges.next();
+        }
+        if now % 5 == 0 {
+            if let Some(event) = button.poll(now) {
+                events.push((now, event));
+            }
+        }
+    }
+    events
+}
+
+fn kinds(events: &[(u32, Event)]) -> Vec<Event> {
+    events.iter().map(|&(_, event)| event).collect()
+}
+
+/// A press at `at` bouncing for a few ms, held for `held` ms and bouncing
+/// again on release.
+fn click(at: u32, held: u32) -> Vec<(u32, bool)> {
+    let release = at + held;
+    vec![
+        (at, true),
+        (at + 1, false),
+        (at + 3, true),
+        (at + 4, false),
+        (at + 6, true),
+        (release, false),
+        (release + 2, true),
+        (release + 3, false),
+    ]
+}
+
+#[test]
+fn bouncy_press_is_one_short_click() {
+    let events = run(CONFIG, &click(100, 120), 1_000);
+    assert_eq!(kinds(&events), [Event::Short]);
+    // Reported once the double click window after the release is over
+    assert!(events[0].0 >= 100 + 120 + 250);
+}
+
+#[test]
+fn glitches_shorter_than_debounce_are_ignored() {
+    let edges = [(100, true), (110, false), (300, true), (315, false)];
+    assert!(run(CONFIG, &edges, 1_000).is_empty());
+}
+
+#[test]
+fn short_without_double_window_is_reported_on_release() {
+    let config = Config {
+        double_ms: 0,
+        ..CONFIG
+    };
+    let events = run(config, &click(100, 120), 1_000);
+    assert_eq!(kinds(&events), [Event::Short]);
+    assert!(events[0].0 < 100 + 120 + 50);
+}
+
+#[test]
+fn two_quick_clicks_are_a_double() {
+    let mut edges = click(100, 100);
+    edges.extend(click(350, 100));
+    assert_eq!(kinds(&run(CONFIG, &edges, 2_000)), [Event::Double]);
+}
+
+#[test]
+fn slow_clicks_are_two_shorts() {
+    let mut edges = click(100, 100);
+    edges.extend(click(600, 100));
+    assert_eq!(
+        kinds(&run(CONFIG, &edges, 2_000)),
+        [Event::Short, Event::Short]
+    );
+}
+
+#[test]
+fn long_press_repeats_while_held() {
+    let events = run(CONFIG, &click(100, 1_500), 3_000);
+    assert_eq!(
+        kinds(&events),
+        [
+            Event::Long,
+            Event::Repeat(1),
+            Event::Repeat(2),
+            Event::Repeat(3),
+        ]
+    );
+    // Timed from the last bounce at 106, polled every 5 ms
+    assert_eq!(events[0].0, 910);
+    assert_eq!(events[1].0, 1_110);
+}
+
+#[test]
+fn click_then_hold_is_short_then_long() {
+    let mut edges = click(100, 100);
+    edges.extend(click(300, 1_000));
+    let events = run(CONFIG, &edges, 3_000);
+    assert_eq!(kinds(&events)[..2], [Event::Short, Event::Long]);
+}
+
+#[test]
+fn late_poll_keeps_single_clicks_apart() {
+    let mut button = Button::new(CONFIG);
+    button.edge(0, true);
+    assert_eq!(button.poll(30), None);
+    button.edge(100, false);
+    assert_eq!(button.poll(130), None);
+    // Second press long after the window, polled before the timeout fired
+    button.edge(1_000, true);
+    assert_eq!(button.poll(1_030), Some(Event::Short));
+    button.edge(1_100, false);
+    assert_eq!(button.poll(1_130), None);
+    assert_eq!(button.poll(1_400), Some(Event::Short));
+}
+
+#[test]
+fn polled_input_and_clock_wrap() {
+    let mut button = Button::new(CONFIG);
+    let start = u32::MAX - 500;
+    let mut events = Vec::new();
+    for step in 0..400u32 {
+        let now = start.wrapping_add(step * 5);
+        let pressed = (100..1_200).contains(&(step * 5));
+        events.extend(button.sample(now, pressed));
+    }
+    assert_eq!(events, [Event::Long, Event::Repeat(1)]);
+    assert!(!button.is_pressed());
+}
@@ -0,0 +1,78 @@
+use robo_core::encoder::{Config, Encoder, Turn};
+
+const CONFIG: Config = Config {
+    counts_per_detent: 4,
+    tick_ms: 10,
+    accel_from: 20,
+    accel_max: 8,
+    reverse: false,
+};
+
+#[test]
+fn counts_are_grouped_into_detents() {
+    let mut encoder = Encoder::new(CONFIG, 100);
+    assert_eq!(encoder.update(102), None);
+    let turn = encoder.update(105).unwrap();
+    assert_eq!(turn.detents, 1);
+    // The extra count carries over
+    assert_eq!(encoder.update(108).unwrap().detents, 1);
+    assert_eq!(encoder.update(106), None);
+    assert_eq!(encoder.update(103).unwrap().detents, -1);
+    assert_eq!(encoder.position(), 1);
+}
+
+#[test]
+fn counter_overflow_is_unwrapped() {
+    let mut encoder = Encoder::new(CONFIG, u16::MAX - 3);
+    assert_eq!(encoder.update(4).unwrap().detents, 2);
+    assert_eq!(encoder.update(u16::MAX - 3).unwrap().detents, -2);
+    assert_eq!(encoder.position(), 0);
+
+    let mut reversed = Encoder::new(
+        Config {
+            reverse: true,
+            ..CONFIG
+        },
+        2,
+    );
+    assert_eq!(reversed.update(u16::MAX - 5).unwrap().detents, 2);
+}
+
+#[test]
+fn slow_turns_are_not_accelerated() {
+    let mut encoder = Encoder::new(CONFIG, 0);
+    let mut raw = 0u16;
+    // One detent every 100 ms, 10 detents per second
+    for tick in 1..=100 {
+        if tick % 10 == 0 {
+            raw = raw.wrapping_add(4);
+        }
+        if let Some(Turn { accelerated, .. }) = encoder.update(raw) {
+            assert_eq!(accelerated, 1);
+        }
+    }
+    assert_eq!(encoder.acceleration(), 1);
+}
+
+#[test]
+fn fast_turns_are_accelerated_and_decay() {
+    let mut encoder = Encoder::new(CONFIG, 0);
+    let mut raw = 0u16;
+    let mut last = None;
+    // One detent every tick, 100 detents per second
+    for _ in 0..20 {
+        raw = raw.wrapping_sub(4);
+        last = encoder.update(raw);
+    }
+    let turn = last.unwrap();
+    assert_eq!(turn.detents, -1);
+    assert_eq!(turn.accelerated, -8);
+    assert!(turn.velocity < -90, "{:?}", turn);
+
+    // At rest after a second without detents
+    for _ in 0..100 {
+        assert_eq!(encoder.update(raw), None);
+    }
+    assert_eq!(encoder.velocity(), 0);
+    assert_eq!(encoder.acceleration(), 1);
+}
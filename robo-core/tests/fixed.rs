@@ -0,0 +1,50 @@
+use robo_core::fixed::{Align, Fixed, Unit};
+
+#[test]
+fn decimals_and_rounding() {
+    assert_eq!(Fixed::new(3300, 3).decimals(2).to_string(), "3.30");
+    assert_eq!(Fixed::new(3305, 3).decimals(2).to_string(), "3.31");
+    assert_eq!(Fixed::new(3304, 3).decimals(1).to_string(), "3.3");
+    assert_eq!(Fixed::new(999, 3).decimals(0).to_string(), "1");
+    assert_eq!(Fixed::new(5, 3).to_string(), "0.005");
+    assert_eq!(Fixed::new(25, 0).decimals(1).to_string(), "25.0");
+}
+
+#[test]
+fn signs() {
+    assert_eq!(Fixed::int(-12).to_string(), "-12");
+    assert_eq!(Fixed::new(-1250, 2).decimals(1).to_string(), "-12.5");
+    assert_eq!(Fixed::new(-1255, 2).decimals(1).to_string(), "-12.6");
+    assert_eq!(Fixed::new(-4, 3).decimals(2).to_string(), "0.00");
+    assert_eq!(Fixed::int(i32::MIN).to_string(), "-2147483648");
+}
+
+#[test]
+fn units_and_alignment() {
+    let volts = Fixed::new(3300, 3).decimals(2).unit(Unit::Volt);
+    assert_eq!(volts.width(6).to_string(), " 3.30V");
+    assert_eq!(volts.width(6).align(Align::Left).to_string(), "3.30V ");
+    assert_eq!(
+        Fixed::int(45).unit(Unit::Percent).width(4).to_string(),
+        " 45%"
+    );
+    assert_eq!(Fixed::new(215, 1).unit(Unit::Celsius).to_string(), "21.5^C");
+    assert_eq!(Fixed::int(-800).unit(Unit::Millivolt).to_string(), "-800mV");
+}
+
+#[test]
+fn overflow_shows_dashes() {
+    assert_eq!(Fixed::int(12345).width(4).to_string(), "----");
+    assert_eq!(
+        Fixed::new(-1000, 3).unit(Unit::Ampere).width(4).to_string(),
+        "----"
+    );
+    assert_eq!(
+        Fixed::new(-1000, 3)
+            .decimals(1)
+            .unit(Unit::Ampere)
+            .width(5)
+            .to_string(),
+        "-1.0A"
+    );
+}
@@ -0,0 +1,63 @@
+use robo_core::button;
+use robo_core::input::{InputEvent, InputQueue, PotMotion, QueueStats, Stamped};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Reset,
+}
+
+#[test]
+fn events_are_stamped_in_order() {
+    let mut queue = InputQueue::<Command, 4>::new();
+    queue.push(InputEvent::Button(button::Event::Short));
+    queue.tick(10);
+    queue.push(InputEvent::Shell(Command::Reset));
+    assert_eq!(
+        queue.pop(),
+        Some(Stamped {
+            at: 0,
+            event: InputEvent::Button(button::Event::Short)
+        })
+    );
+    assert_eq!(
+        queue.pop(),
+        Some(Stamped {
+            at: 10,
+            event: InputEvent::Shell(Command::Reset)
+        })
+    );
+    assert_eq!(queue.pop(), None);
+}
+
+#[test]
+fn overflow_drops_oldest_and_is_counted() {
+    let mut queue = InputQueue::<Command, 2>::new();
+    assert!(queue.push(InputEvent::Pot(1)));
+    assert!(queue.push(InputEvent::Pot(2)));
+    assert!(!queue.push(InputEvent::Pot(3)));
+    assert_eq!(queue.pop().unwrap().event, InputEvent::Pot(2));
+    assert_eq!(
+        queue.take_stats(),
+        QueueStats {
+            pushed: 3,
+            dropped: 1,
+            high_water: 2
+        }
+    );
+    assert_eq!(queue.stats().high_water, 1);
+    assert_eq!(queue.pop().unwrap().event, InputEvent::Pot(3));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn pot_motion_ignores_drift() {
+    let mut pot = PotMotion::new(20);
+    assert_eq!(pot.update(1_000), None);
+    // Slow drift never gets past the threshold from the last move
+    for position in 1_001..=1_020 {
+        assert_eq!(pot.update(position), None);
+    }
+    assert_eq!(pot.update(1_021), Some(1_021));
+    assert_eq!(pot.update(1_002), None);
+    assert_eq!(pot.update(1_000), Some(1_000));
+}
@@ -0,0 +1,84 @@
+use robo_core::keypad::{Config, Key, KeyEvent, Matrix};
+
+const CONFIG: Config = Config {
+    debounce_scans: 3,
+    rollover: 2,
+};
+
+fn key(row: u8, col: u8) -> Key {
+    Key { row, col }
+}
+
+/// Scans `raw` `times` times, collecting the events.
+fn scan(matrix: &mut Matrix<4, 4>, raw: [u8; 4], times: usize) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    for _ in 0..times {
+        matrix.scan(&raw, |event| events.push(event));
+    }
+    events
+}
+
+#[test]
+fn keys_go_down_and_up_after_debouncing() {
+    let mut matrix = Matrix::<4, 4>::new(CONFIG);
+    assert!(scan(&mut matrix, [0, 0b0100, 0, 0], 2).is_empty());
+    assert_eq!(
+        scan(&mut matrix, [0, 0b0100, 0, 0], 1),
+        [KeyEvent::Down(key(1, 2))]
+    );
+    assert!(matrix.is_pressed(key(1, 2)));
+    assert_eq!(matrix.keys().collect::<Vec<_>>(), [key(1, 2)]);
+    assert_eq!(scan(&mut matrix, [0; 4], 3), [KeyEvent::Up(key(1, 2))]);
+    assert_eq!(matrix.pressed(), 0);
+}
+
+#[test]
+fn bounces_restart_debouncing() {
+    let mut matrix = Matrix::<4, 4>::new(CONFIG);
+    let mut events = Vec::new();
+    for raw in [1, 0, 1, 1, 0, 1, 1] {
+        events.extend(scan(&mut matrix, [raw, 0, 0, 0], 1));
+    }
+    assert!(events.is_empty());
+    assert_eq!(
+        scan(&mut matrix, [1, 0, 0, 0], 1),
+        [KeyEvent::Down(key(0, 0))]
+    );
+}
+
+#[test]
+fn rollover_holds_back_extra_keys() {
+    let mut matrix = Matrix::<4, 4>::new(CONFIG);
+    let events = scan(&mut matrix, [0b0001, 0b0010, 0b0100, 0], 3);
+    assert_eq!(
+        events,
+        [KeyEvent::Down(key(0, 0)), KeyEvent::Down(key(1, 1))]
+    );
+    assert_eq!(matrix.blocked(), 1 << (2 * 4 + 2));
+
+    // Room again once a key goes up
+    let events = scan(&mut matrix, [0, 0b0010, 0b0100, 0], 3);
+    assert_eq!(events, [KeyEvent::Up(key(0, 0)), KeyEvent::Down(key(2, 2))]);
+    assert_eq!(matrix.pressed_count(), 2);
+}
+
+#[test]
+fn ghost_corners_are_not_pressed() {
+    let mut matrix = Matrix::<4, 4>::new(Config {
+        rollover: 4,
+        ..CONFIG
+    });
+    scan(&mut matrix, [0b0011, 0b0001, 0, 0], 3);
+    assert_eq!(matrix.pressed_count(), 3);
+
+    // The third corner makes row 1 read column 1 too
+    let events = scan(&mut matrix, [0b0011, 0b0011, 0, 0], 5);
+    assert!(events.is_empty());
+    assert!(matrix.is_ghosting());
+    assert!(!matrix.is_pressed(key(1, 1)));
+
+    // Releases count again once the scan is unambiguous
+    let events = scan(&mut matrix, [0b0010, 0b0011, 0b0001, 0], 3);
+    assert!(events.contains(&KeyEvent::Up(key(0, 0))));
+    assert!(!matrix.is_ghosting());
+}
@@ -0,0 +1,96 @@
+use robo_core::line::{Calibration, Config, LineArray, Polarity, Position, Side, SCALE};
+
+fn calibrated(sensors: usize) -> LineArray {
+    let mut array = LineArray::new(sensors, Config::default());
+    array.calibrate(&[400; 8]);
+    array.calibrate(&[3_400; 8]);
+    array
+}
+
+#[test]
+fn calibration_normalizes_readings() {
+    let mut calibration = Calibration::EMPTY;
+    assert_eq!(calibration.normalize(1_000, Polarity::Dark), 0);
+    for raw in [900, 2_100, 300, 1_200] {
+        calibration.extend(raw);
+    }
+    assert_eq!(calibration, Calibration::new(300, 2_100));
+    assert_eq!(calibration.normalize(300, Polarity::Dark), 0);
+    assert_eq!(calibration.normalize(1_200, Polarity::Dark), 500);
+    assert_eq!(calibration.normalize(4_000, Polarity::Dark), SCALE);
+    assert_eq!(calibration.normalize(100, Polarity::Light), SCALE);
+    assert_eq!(calibration.normalize(1_650, Polarity::Light), 250);
+
+    let mut array = LineArray::new(8, Config::default());
+    assert_eq!(array.sensors(), 8);
+    assert!(!array.is_calibrated());
+    array.calibrate(&[400, 400, 400, 400, 400, 400, 400, 400]);
+    array.calibrate(&[500, 3_400, 3_400, 3_400, 3_400, 3_400, 3_400, 3_400]);
+    assert!(!array.is_calibrated());
+    array.set_calibration(0, Calibration::new(400, 3_400));
+    assert!(array.is_calibrated());
+    array.reset_calibration();
+    assert_eq!(array.calibration()[0], Calibration::EMPTY);
+}
+
+#[test]
+fn weighted_position_across_the_array() {
+    let mut array = calibrated(5);
+    assert_eq!(array.edge(), 2_000);
+    // Right under the middle sensor
+    assert_eq!(
+        array.update(&[400, 400, 3_400, 400, 400]),
+        Position::Line(0)
+    );
+    // Halfway between the two rightmost sensors
+    assert_eq!(
+        array.update(&[400, 400, 400, 1_900, 1_900]),
+        Position::Line(1_500)
+    );
+    assert_eq!(array.values(), &[0, 0, 0, 500, 500]);
+    // Readings in the noise don't pull the position
+    assert_eq!(
+        array.update(&[460, 3_400, 1_900, 400, 460]),
+        Position::Line(-667)
+    );
+    assert_eq!(array.error(), -667);
+
+    // An even count has no sensor in the middle
+    let mut array = calibrated(4);
+    assert_eq!(array.edge(), 1_500);
+    assert_eq!(array.update(&[400, 3_400, 3_400, 400]), Position::Line(0));
+    assert_eq!(
+        array.update(&[3_400, 400, 400, 400]),
+        Position::Line(-1_500)
+    );
+}
+
+#[test]
+fn lost_line_remembers_the_side() {
+    let mut array = calibrated(3);
+    assert_eq!(array.position(), None);
+    assert_eq!(array.update(&[400, 400, 400]), Position::Lost(Side::Center));
+    assert_eq!(array.error(), 0);
+
+    array.update(&[400, 1_900, 3_400]);
+    // Faint readings below the threshold don't count as the line
+    assert_eq!(
+        array.update(&[400, 400, 1_000]),
+        Position::Lost(Side::Right)
+    );
+    assert_eq!(array.error(), 1_000);
+    array.update(&[3_400, 400, 400]);
+    assert_eq!(array.update(&[400, 400, 400]), Position::Lost(Side::Left));
+    assert_eq!(array.error(), -1_000);
+}
+
+#[test]
+fn light_line_on_dark_floor() {
+    let mut array = calibrated(3);
+    array.set_config(Config {
+        polarity: Polarity::Light,
+        ..Config::default()
+    });
+    assert_eq!(array.update(&[3_400, 3_400, 400]), Position::Line(1_000));
+    assert_eq!(array.values(), &[0, 0, SCALE]);
+}
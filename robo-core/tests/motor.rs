@@ -0,0 +1,91 @@
+use robo_core::motor::{Mode, Motor, Outputs, Stop, DUTY_MAX};
+
+fn outputs(enable: u16, in1: u16, in2: u16) -> Outputs {
+    Outputs { enable, in1, in2 }
+}
+
+#[test]
+fn enable_and_direction_bridges() {
+    for mode in [Mode::L298n, Mode::Tb6612] {
+        assert_eq!(mode.outputs(600, Stop::Brake), outputs(600, DUTY_MAX, 0));
+        assert_eq!(mode.outputs(-250, Stop::Coast), outputs(250, 0, DUTY_MAX));
+        assert_eq!(
+            mode.outputs(0, Stop::Brake),
+            outputs(DUTY_MAX, DUTY_MAX, DUTY_MAX)
+        );
+        assert_eq!(mode.outputs(0, Stop::Coast), outputs(0, 0, 0));
+        assert_eq!(
+            mode.outputs(i16::MIN, Stop::Coast),
+            outputs(DUTY_MAX, 0, DUTY_MAX)
+        );
+    }
+}
+
+#[test]
+fn dual_pwm_bridge_decay() {
+    let mode = Mode::Drv8833;
+    assert_eq!(mode.outputs(300, Stop::Coast), outputs(DUTY_MAX, 300, 0));
+    assert_eq!(mode.outputs(-300, Stop::Coast), outputs(DUTY_MAX, 0, 300));
+    assert_eq!(
+        mode.outputs(300, Stop::Brake),
+        outputs(DUTY_MAX, DUTY_MAX, 700)
+    );
+    assert_eq!(
+        mode.outputs(-300, Stop::Brake),
+        outputs(DUTY_MAX, 700, DUTY_MAX)
+    );
+    assert_eq!(
+        mode.outputs(0, Stop::Brake),
+        outputs(DUTY_MAX, DUTY_MAX, DUTY_MAX)
+    );
+    assert_eq!(mode.outputs(0, Stop::Coast), outputs(DUTY_MAX, 0, 0));
+}
+
+#[test]
+fn speed_ramps_through_zero() {
+    let mut motor = Motor::new(Mode::L298n);
+    motor.set_accel(1_000);
+    motor.set_speed(500);
+    for _ in 0..10 {
+        motor.tick(10);
+    }
+    assert_eq!(motor.speed(), 100);
+    for _ in 0..50 {
+        motor.tick(10);
+    }
+    assert_eq!(motor.speed(), 500);
+
+    motor.set_speed(-2_000);
+    assert_eq!(motor.target(), -(DUTY_MAX as i16));
+    let mut crossed = false;
+    for _ in 0..200 {
+        let out = motor.tick(10);
+        crossed |= motor.speed() == 0 && out == outputs(DUTY_MAX, DUTY_MAX, DUTY_MAX);
+    }
+    assert!(crossed);
+    assert_eq!(motor.speed(), -1_000);
+}
+
+#[test]
+fn slow_ramps_carry_fractions() {
+    let mut motor = Motor::new(Mode::Drv8833);
+    // 1 per mille every 3 ms
+    motor.set_accel(300);
+    motor.set_speed(10);
+    for _ in 0..10 {
+        motor.tick(3);
+    }
+    assert_eq!(motor.speed(), 9);
+}
+
+#[test]
+fn halt_and_reverse() {
+    let mut motor = Motor::new(Mode::L298n);
+    motor.set_reverse(true);
+    motor.set_speed(400);
+    assert_eq!(motor.tick(10), outputs(400, 0, DUTY_MAX));
+    motor.set_accel(100);
+    motor.halt(Stop::Coast);
+    assert_eq!(motor.tick(10), outputs(0, 0, 0));
+    assert_eq!(motor.stop_mode(), Stop::Coast);
+}
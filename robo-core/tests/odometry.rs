@@ -0,0 +1,111 @@
+use robo_core::odometry::{Config, Method, Odometry, Wheel};
+
+const TICK_US: u32 = 10_000;
+
+fn config(method: Method) -> Config {
+    Config {
+        ticks_per_rev: 40,
+        wheel_diameter_mm: 65,
+        method,
+        ..Config::default()
+    }
+}
+
+/// Runs `updates` updates of 10 ms with a tick every `period_us`, starting
+/// at `start_us`, and returns the time reached.
+fn run(wheel: &mut Wheel, start_us: u32, updates: u32, period_us: u32, forward: bool) -> u32 {
+    let mut next = start_us + period_us;
+    let mut now = start_us;
+    for _ in 0..updates {
+        now += TICK_US;
+        while next <= now {
+            wheel.edge(next, forward);
+            next += period_us;
+        }
+        wheel.update(now);
+    }
+    now
+}
+
+#[test]
+fn ticks_to_distance() {
+    let mut wheel = Wheel::new(config(Method::Window));
+    for at in 0..40 {
+        wheel.edge(at * 1_000, true);
+    }
+    // One revolution of a 65 mm wheel
+    assert_eq!(wheel.count(), 40);
+    assert_eq!(wheel.distance_mm(), 204);
+    wheel.reset();
+    assert_eq!(wheel.distance_mm(), 0);
+
+    let mut wheel = Wheel::new(Config {
+        reverse: true,
+        ..config(Method::Window)
+    });
+    wheel.counter(65_530);
+    wheel.counter(4);
+    assert_eq!(wheel.count(), -10);
+    assert_eq!(wheel.distance_mm(), -51);
+}
+
+#[test]
+fn windowed_speed_at_a_steady_rate() {
+    let mut wheel = Wheel::new(config(Method::Window));
+    // 200 ticks per second is 5 revolutions per second
+    run(&mut wheel, 0, 50, 5_000, true);
+    assert_eq!(wheel.rate(), 200_000);
+    assert_eq!(wheel.rpm(), 3_000);
+    assert_eq!(wheel.angular_velocity(), 31_415);
+    assert_eq!(wheel.speed(), 1_021);
+}
+
+#[test]
+fn period_speed_resolves_slow_wheels() {
+    // 5 ticks per second, one tick per 20 updates
+    let mut window = Wheel::new(config(Method::Window));
+    let mut period = Wheel::new(config(Method::Period));
+    let mut auto = Wheel::new(config(Method::Auto));
+    let now = run(&mut window, 0, 99, 200_000, false);
+    run(&mut period, 0, 99, 200_000, false);
+    run(&mut auto, 0, 99, 200_000, false);
+    // The window of 100 ms sees no tick 190 ms after one
+    assert_eq!(window.rate(), 0);
+    assert_eq!(period.rate(), -5_000);
+    assert_eq!(auto.rate(), -5_000);
+
+    // Stopping, the estimate decays with the time since the last tick and
+    // drops to zero after the timeout
+    let now = run(&mut period, now, 20, u32::MAX / 2, false);
+    assert_eq!(period.rate(), -2_564);
+    run(&mut period, now, 20, u32::MAX / 2, false);
+    assert_eq!(period.rate(), 0);
+}
+
+#[test]
+fn reversal_restarts_the_period() {
+    let mut wheel = Wheel::new(config(Method::Period));
+    wheel.edge(0, true);
+    wheel.edge(100_000, true);
+    wheel.update(100_000);
+    assert_eq!(wheel.rate(), 10_000);
+    wheel.edge(150_000, false);
+    wheel.update(150_000);
+    assert_eq!(wheel.rate(), 0);
+    wheel.edge(250_000, false);
+    wheel.update(250_000);
+    assert_eq!(wheel.rate(), -10_000);
+    assert_eq!(wheel.count(), 0);
+}
+
+#[test]
+fn differential_drive() {
+    let mut odometry = Odometry::new(config(Method::Window), config(Method::Window), 130);
+    run(&mut odometry.left, 0, 50, 10_000, true);
+    run(&mut odometry.right, 0, 50, 5_000, true);
+    assert_eq!(odometry.left.speed(), 510);
+    assert_eq!(odometry.right.speed(), 1_021);
+    assert_eq!(odometry.speed(), 765);
+    assert_eq!(odometry.yaw_rate(), 3_930);
+    assert_eq!(odometry.distance_mm(), (255 + 510) / 2);
+}
@@ -0,0 +1,142 @@
+use robo_core::pid::{Config, Gains, Pid};
+use robo_core::q16::Q16;
+
+const SAMPLE_MS: u32 = 10;
+
+fn q(value: f64) -> Q16 {
+    Q16::from_raw((value * 65_536.0).round() as i32)
+}
+
+fn f(value: Q16) -> f64 {
+    value.raw() as f64 / 65_536.0
+}
+
+/// First order lag: gain 2, time constant 0.5 s.
+struct Plant {
+    y: f64,
+}
+
+impl Plant {
+    const GAIN: f64 = 2.0;
+    const TAU: f64 = 0.5;
+
+    fn step(&mut self, u: Q16, dt_ms: u32) -> Q16 {
+        let dt = dt_ms as f64 / 1_000.0;
+        self.y += (Self::GAIN * f(u) - self.y) * dt / Self::TAU;
+        q(self.y)
+    }
+}
+
+fn config(kp: f64, ki: f64, kd: f64) -> Config {
+    Config {
+        gains: Gains {
+            kp: q(kp),
+            ki: q(ki),
+            kd: q(kd),
+        },
+        sample_ms: SAMPLE_MS,
+        out_min: q(-10.0),
+        out_max: q(10.0),
+    }
+}
+
+/// Runs the loop for `ms` and returns the largest measurement.
+fn run(pid: &mut Pid, plant: &mut Plant, setpoint: f64, ms: u32) -> f64 {
+    let sample_ms = pid.config().sample_ms;
+    let mut peak = f64::MIN;
+    let mut measurement = q(plant.y);
+    for _ in 0..ms / sample_ms {
+        let output = pid.update(q(setpoint), measurement);
+        measurement = plant.step(output, sample_ms);
+        peak = peak.max(plant.y);
+    }
+    peak
+}
+
+#[test]
+fn settles_without_steady_state_error() {
+    let mut pid = Pid::new(config(1.0, 2.0, 0.05));
+    let mut plant = Plant { y: 0.0 };
+    let peak = run(&mut pid, &mut plant, 5.0, 5_000);
+    assert!((plant.y - 5.0).abs() < 0.01, "{}", plant.y);
+    assert!(peak < 5.5, "overshoot to {}", peak);
+
+    // Proportional only leaves an offset
+    let mut pid = Pid::new(config(1.0, 0.0, 0.0));
+    let mut plant = Plant { y: 0.0 };
+    run(&mut pid, &mut plant, 5.0, 5_000);
+    assert!((plant.y - 10.0 / 3.0).abs() < 0.01, "{}", plant.y);
+}
+
+#[test]
+fn clamps_output_and_stops_winding_up() {
+    let mut pid = Pid::new(config(2.0, 5.0, 0.0));
+    let mut plant = Plant { y: 0.0 };
+    // Out of reach: the plant tops out at 20
+    run(&mut pid, &mut plant, 30.0, 10_000);
+    assert_eq!(pid.output(), q(10.0));
+    assert!(pid.integral() <= q(10.0));
+
+    // Back in reach, the integral doesn't have to unwind first
+    let peak = run(&mut pid, &mut plant, 4.0, 100);
+    assert!(pid.output() < q(10.0));
+    assert!(peak <= plant.y.max(20.0));
+    run(&mut pid, &mut plant, 4.0, 5_000);
+    assert!((plant.y - 4.0).abs() < 0.01, "{}", plant.y);
+}
+
+#[test]
+fn setpoint_step_doesnt_kick() {
+    let mut pid = Pid::new(config(1.0, 0.0, 1.0));
+    let mut plant = Plant { y: 1.0 };
+    run(&mut pid, &mut plant, 1.0, 100);
+    let measurement = q(plant.y);
+    pid.update(q(1.0), measurement);
+    let before = pid.update(q(1.0), measurement);
+    // Only the proportional term follows the step
+    let after = pid.update(q(3.0), measurement);
+    assert!((f(after - before) - 2.0).abs() < 0.01, "{}", after - before);
+}
+
+#[test]
+fn gain_change_is_bumpless() {
+    let mut pid = Pid::new(config(1.0, 1.0, 0.1));
+    let mut plant = Plant { y: 0.0 };
+    run(&mut pid, &mut plant, 2.0, 300);
+    let measurement = q(plant.y);
+    pid.update(q(2.0), measurement);
+    let output = pid.update(q(2.0), measurement);
+    pid.set_gains(Gains {
+        kp: q(3.0),
+        ki: q(4.0),
+        kd: q(0.2),
+    });
+    // With the same inputs, the next update moves the output by the new
+    // integral step only, not by the tripled proportional term
+    let next = pid.update(q(2.0), measurement);
+    let integral_step = 4.0 * 0.01 * (2.0 - f(measurement));
+    assert!(
+        (f(next - output) - integral_step).abs() < 0.001,
+        "{} {}",
+        output,
+        next
+    );
+}
+
+#[test]
+fn sample_time_keeps_the_response() {
+    let mut fast = Pid::new(config(1.0, 2.0, 0.05));
+    let mut slow = Pid::new(config(1.0, 2.0, 0.05));
+    slow.set_sample_ms(20);
+    let (mut fast_plant, mut slow_plant) = (Plant { y: 0.0 }, Plant { y: 0.0 });
+    for ms in [200, 400, 1_000] {
+        run(&mut fast, &mut fast_plant, 5.0, ms);
+        run(&mut slow, &mut slow_plant, 5.0, ms);
+        assert!(
+            (fast_plant.y - slow_plant.y).abs() < 0.2,
+            "{} {}",
+            fast_plant.y,
+            slow_plant.y
+        );
+    }
+}
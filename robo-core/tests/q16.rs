@@ -0,0 +1,16 @@
+use robo_core::q16::Q16;
+
+#[test]
+fn q16_saturates() {
+    assert_eq!(Q16::from_int(40_000), Q16::MAX);
+    assert_eq!(Q16::MAX + Q16::ONE, Q16::MAX);
+    assert_eq!(Q16::MIN - Q16::ONE, Q16::MIN);
+    assert_eq!(-Q16::MIN, Q16::MAX);
+    assert_eq!(Q16::from_int(300) * Q16::from_int(300), Q16::MAX);
+    assert_eq!(Q16::ONE / Q16::ZERO, Q16::MAX);
+    assert_eq!(Q16::from_ratio(3, 2) * Q16::from(-4), Q16::from_int(-6));
+    assert_eq!(Q16::from_int(7) / Q16::from_int(2), Q16::from_ratio(7, 2));
+    assert_eq!(Q16::from_ratio(-5, 2).to_int(), -3);
+    assert_eq!(Q16::from_ratio(1, 3).to_string(), "0.3333");
+    assert_eq!(Q16::from_ratio(-9, 4).to_string(), "-2.2500");
+}
@@ -0,0 +1,23 @@
+use robo_core::saver::{Power, Screensaver};
+
+#[test]
+fn sleep_ignores_disabled_timeouts() {
+    let mut saver = Screensaver::new(0, 0, 100);
+    for _ in 0..1_000 {
+        saver.tick();
+    }
+    assert_eq!(saver.power(), Power::On);
+    saver.sleep();
+    assert_eq!(saver.power(), Power::Off);
+    assert!(saver.wake());
+    assert_eq!(saver.power(), Power::On);
+    assert!(!saver.wake());
+
+    let mut saver = Screensaver::new(2, 0, 100);
+    saver.sleep();
+    assert_eq!(saver.power(), Power::Off);
+    // The first position is only the reference
+    assert!(!saver.position(2_000));
+    assert!(saver.position(200));
+    assert_eq!(saver.power(), Power::On);
+}
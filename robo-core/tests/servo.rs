@@ -0,0 +1,93 @@
+use robo_core::servo::{Calibration, Servo};
+
+fn calibration(min_us: u16, center_us: u16, max_us: u16) -> Calibration {
+    Calibration {
+        min_us,
+        center_us,
+        max_us,
+    }
+}
+
+#[test]
+fn angles_follow_the_calibration() {
+    let default = Calibration::default();
+    assert_eq!(default.to_us(0), 1_000);
+    assert_eq!(default.to_us(45), 1_250);
+    assert_eq!(default.to_us(90), 1_500);
+    assert_eq!(default.to_us(180), 2_000);
+    assert_eq!(default.to_us(250), 2_000);
+
+    // Off-center neutral: each half scales on its own
+    let skewed = calibration(600, 1_400, 2_400);
+    assert!(skewed.is_valid());
+    assert_eq!(skewed.to_us(45), 1_000);
+    assert_eq!(skewed.to_us(90), 1_400);
+    assert_eq!(skewed.to_us(135), 1_900);
+    for angle in 0..=180 {
+        assert_eq!(skewed.to_angle(skewed.to_us(angle)), angle);
+    }
+    assert_eq!(skewed.to_angle(100), 0);
+    assert!(!calibration(1_500, 1_500, 2_000).is_valid());
+    assert!(!calibration(1_000, 1_500, 20_000).is_valid());
+
+    // Invalid calibrations still give angles in range
+    for invalid in [
+        calibration(2_000, 1_500, 1_000),
+        calibration(1_500, 1_000, 2_000),
+        calibration(1_000, 2_500, 2_000),
+    ] {
+        for us in (0..=3_000).step_by(100) {
+            assert!(invalid.to_angle(us) <= 180);
+        }
+    }
+}
+
+#[test]
+fn detached_until_commanded() {
+    let mut servo = Servo::new(Calibration::default());
+    assert!(!servo.is_attached());
+    assert_eq!(servo.tick(20), None);
+
+    servo.set_slew(1_000);
+    // Jumps to the first command, the position is unknown
+    servo.set_angle(90);
+    assert_eq!(servo.tick(20), Some(1_500));
+    assert_eq!(servo.angle(), Some(90));
+
+    servo.detach();
+    assert_eq!(servo.tick(20), None);
+    assert_eq!(servo.pulse_us(), None);
+    assert_eq!(servo.angle(), None);
+}
+
+#[test]
+fn slew_limits_the_speed() {
+    let mut servo = Servo::new(Calibration::default());
+    servo.set_us(1_000);
+    servo.set_slew(250);
+    servo.set_us(1_500);
+    // 250 µs per second is 5 µs per 20 ms frame
+    assert_eq!(servo.tick(20), Some(1_005));
+    for _ in 0..98 {
+        servo.tick(20);
+    }
+    assert_eq!(servo.tick(20), Some(1_500));
+    assert_eq!(servo.tick(20), Some(1_500));
+
+    // Fractions of a microsecond carry over
+    servo.set_slew(30);
+    servo.set_us(1_400);
+    let moved: Vec<_> = (0..5).map(|_| servo.tick(20).unwrap()).collect();
+    assert_eq!(moved, [1_500, 1_499, 1_499, 1_498, 1_497]);
+
+    // Out of range commands clamp to the calibration
+    servo.set_slew(0);
+    servo.set_us(3_000);
+    assert_eq!(servo.tick(20), Some(2_000));
+    assert!(servo.set_calibration(calibration(900, 1_500, 1_800)));
+    assert_eq!(servo.pulse_us(), Some(1_800));
+    // An invalid calibration leaves the servo as it was
+    assert!(!servo.set_calibration(calibration(2_000, 1_500, 1_000)));
+    assert_eq!(servo.calibration(), &calibration(900, 1_500, 1_800));
+    assert_eq!(servo.pulse_us(), Some(1_800));
+}
@@ -0,0 +1,62 @@
+use robo_core::filter::Median;
+use robo_core::sonar::{echo_to_mm, speed_of_sound, Capture, Config, Reading, Sonar};
+
+#[test]
+fn echo_to_distance_with_temperature() {
+    assert_eq!(speed_of_sound(0), 331_300);
+    assert_eq!(speed_of_sound(200), 343_420);
+    assert_eq!(speed_of_sound(-100), 325_240);
+    // 1 m takes 5.82 ms there and back at 20 °C
+    assert_eq!(echo_to_mm(5_824, 200), 1_000);
+    // The same echo reads shorter in cold air
+    assert_eq!(echo_to_mm(5_824, -100), 947);
+}
+
+#[test]
+fn median_rejects_outliers() {
+    let mut median = Median::<5>::new(3);
+    assert_eq!(median.value(), None);
+    assert_eq!(median.update(100), 100);
+    assert_eq!(median.update(102), 102);
+    assert_eq!(median.update(900), 102);
+    assert_eq!(median.update(101), 102);
+    assert_eq!(median.update(99), 101);
+    assert_eq!(median.update(98), 99);
+    median.set_window(8);
+    assert_eq!(median.window(), 5);
+    assert_eq!(median.value(), None);
+}
+
+#[test]
+fn readings_in_and_out_of_range() {
+    let mut sonar = Sonar::new(Config {
+        min_mm: 20,
+        max_mm: 2_000,
+        window: 3,
+    });
+    sonar.set_temperature(200);
+    assert_eq!(sonar.reading(), None);
+    assert_eq!(sonar.update(Capture::Echo(5_824)), Reading::Distance(1_000));
+    assert_eq!(sonar.update(Capture::Echo(50)), Reading::Distance(20));
+    assert_eq!(sonar.update(Capture::Echo(20_000)), Reading::NoEcho);
+    assert_eq!(sonar.reading(), Some(Reading::Distance(1_000)));
+    assert_eq!(sonar.update(Capture::Unfinished), Reading::NoEcho);
+    assert_eq!(sonar.reading(), Some(Reading::NoEcho));
+    assert_eq!(sonar.last(), Some(Reading::NoEcho));
+}
+
+#[test]
+fn missing_echoes_time_out() {
+    let mut sonar = Sonar::new(Config::default());
+    sonar.update(Capture::Echo(2_912));
+    assert_eq!(sonar.reading(), Some(Reading::Distance(500)));
+    // A few dropouts keep the last distance
+    for _ in 0..4 {
+        assert_eq!(sonar.update(Capture::Missing), Reading::Timeout);
+        assert_eq!(sonar.reading(), Some(Reading::Distance(500)));
+    }
+    sonar.update(Capture::Missing);
+    assert_eq!(sonar.reading(), Some(Reading::Timeout));
+    sonar.update(Capture::Echo(2_912));
+    assert_eq!(sonar.reading(), Some(Reading::Distance(500)));
+}
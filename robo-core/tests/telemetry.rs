@@ -0,0 +1,152 @@
+use robo_core::cobs;
+use robo_core::telemetry::{crc16, Decoder, Encoder, Error, Packet, MAX_FRAME_LEN, MAX_VALUES};
+
+#[test]
+fn crc16_check_value() {
+    assert_eq!(crc16(b"123456789"), 0x29b1);
+    assert_eq!(crc16(&[]), 0xffff);
+}
+
+#[test]
+fn cobs_round_trip() {
+    let inputs: [&[u8]; 6] = [
+        &[],
+        &[0],
+        &[0, 0],
+        &[1, 2, 0, 3],
+        &[0x11, 0x22, 0x00, 0x33, 0x00],
+        &[0xff; 300],
+    ];
+    for input in inputs {
+        let mut encoded = [0; 512];
+        let len = cobs::encode(input, &mut encoded).unwrap();
+        assert!(len <= cobs::max_encoded_len(input.len()));
+        assert!(!encoded[..len].contains(&0), "{:?}", input);
+
+        let mut decoded = [0; 512];
+        let len = cobs::decode(&encoded[..len], &mut decoded).unwrap();
+        assert_eq!(&decoded[..len], input);
+    }
+}
+
+#[test]
+fn cobs_long_runs() {
+    for len in [253, 254, 255, 508, 509] {
+        let input: Vec<u8> = (0..len).map(|i| (i % 255 + 1) as u8).collect();
+        let mut encoded = vec![0; cobs::max_encoded_len(len)];
+        let encoded_len = cobs::encode(&input, &mut encoded).unwrap();
+        let mut decoded = vec![0; len];
+        let decoded_len = cobs::decode(&encoded[..encoded_len], &mut decoded).unwrap();
+        assert_eq!(decoded[..decoded_len], input[..], "len {}", len);
+    }
+}
+
+#[test]
+fn cobs_rejects_malformed() {
+    let mut buf = [0; 16];
+    assert_eq!(cobs::decode(&[5, 1, 2], &mut buf), None);
+    assert_eq!(cobs::decode(&[3, 0, 1], &mut buf), None);
+    assert_eq!(cobs::encode(&[1, 2, 3], &mut buf[..3]), None);
+}
+
+#[test]
+fn packet_round_trip() {
+    let mut buf = [0; MAX_FRAME_LEN];
+    for values in [&[][..], &[0], &[0x1234, 0, 0xffff], &[7; MAX_VALUES]] {
+        let packet = Packet::new(0x0100, 0xdead_0000, 0, values);
+        let len = packet.encode(&mut buf);
+        assert_eq!(buf[len - 1], 0);
+        assert!(!buf[..len - 1].contains(&0));
+        assert_eq!(Packet::decode(&buf[..len]), Ok(packet));
+        assert_eq!(Packet::decode(&buf[..len - 1]), Ok(packet));
+        assert_eq!(packet.values(), values);
+    }
+}
+
+#[test]
+fn packet_drops_extra_values() {
+    let packet = Packet::new(1, 2, 3, &[1; MAX_VALUES + 2]);
+    assert_eq!(packet.values().len(), MAX_VALUES);
+}
+
+#[test]
+fn corrupted_packet_fails_crc() {
+    let mut buf = [0; MAX_FRAME_LEN];
+    let len = Packet::new(42, 1_000, 1, &[2048, 1650]).encode(&mut buf);
+    // flip a payload bit, keeping the COBS structure intact
+    buf[len - 4] ^= 0x01;
+    assert!(matches!(
+        Packet::decode(&buf[..len]),
+        Err(Error::Crc { .. })
+    ));
+}
+
+#[test]
+fn short_packet_is_rejected() {
+    let mut frame = [0; 8];
+    let len = cobs::encode(&[1, 2, 3], &mut frame).unwrap();
+    assert_eq!(Packet::decode(&frame[..len]), Err(Error::Length));
+}
+
+#[test]
+fn encoder_numbers_packets() {
+    let mut encoder = Encoder::new();
+    let mut buf = [0; MAX_FRAME_LEN];
+    for seq in 0..3 {
+        let frame = encoder.encode(seq as u32 * 10, 0, &[seq], &mut buf);
+        let packet = Packet::decode(frame).unwrap();
+        assert_eq!(packet.seq, seq);
+        assert_eq!(packet.timestamp, seq as u32 * 10);
+    }
+    assert_eq!(encoder.seq(), 3);
+}
+
+#[test]
+fn stream_decoder_round_trip() {
+    let mut encoder = Encoder::new();
+    let mut stream = Vec::new();
+    let mut buf = [0; MAX_FRAME_LEN];
+    for i in 0..100u16 {
+        stream.extend_from_slice(encoder.encode(i as u32, (i % 3) as u8, &[i, !i], &mut buf));
+    }
+
+    // arbitrary read boundaries, as a serial port delivers them
+    let mut decoder = Decoder::new();
+    let mut packets = Vec::new();
+    for chunk in stream.chunks(7) {
+        packets.extend(decoder.feed(chunk));
+    }
+    assert_eq!(packets.len(), 100);
+    for (i, packet) in packets.into_iter().enumerate() {
+        let packet = packet.unwrap();
+        let i = i as u16;
+        assert_eq!(packet.seq, i);
+        assert_eq!(packet.timestamp, i as u32);
+        assert_eq!(packet.channel, (i % 3) as u8);
+        assert_eq!(packet.values(), &[i, !i]);
+    }
+    assert_eq!(decoder.lost(), 0);
+}
+
+#[test]
+fn stream_decoder_resyncs_and_counts_lost() {
+    let mut encoder = Encoder::new();
+    let mut buf = [0; MAX_FRAME_LEN];
+    let frames: Vec<Vec<u8>> = (0..4)
+        .map(|i| encoder.encode(i, 0, &[i as u16], &mut buf).to_vec())
+        .collect();
+
+    let mut decoder = Decoder::new();
+    // joined mid-frame
+    let packets = decoder.feed(&frames[0][3..]);
+    assert_eq!(packets.len(), 1);
+    assert!(packets[0].is_err());
+
+    // frame 2 lost on the wire
+    let stream = [&frames[1][..], &frames[3][..]].concat();
+    let packets = decoder.feed(&stream);
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].as_ref().unwrap().seq, 1);
+    assert_eq!(packets[1].as_ref().unwrap().seq, 3);
+    assert_eq!(decoder.lost(), 1);
+}
@@ -0,0 +1,369 @@
+use hal::analog::adc::Adc;
+use hal::hal::adc::Channel;
+use hal::stm32;
+use hal::time::Hertz;
+use hal::timer::Timer;
+
+use robo_core::alarm::Thresholds;
+
+use crate::hal;
+
+/// Largest value the watchdog thresholds can hold.
+pub const MAX_THRESHOLD: u16 = 0x0fff;
+
+/// `EXTSEL` value selecting TIM3 TRGO as the external conversion trigger.
+const EXTSEL_TIM3_TRGO: u8 = 0b011;
+/// `EXTEN` value for conversions on the rising edge of the trigger.
+const EXTEN_RISING: u8 = 0b01;
+/// `MMS` value routing the update event to TRGO.
+const MMS_UPDATE: u8 = 0b010;
+/// Number of ADC input channels.
+const MAX_CHANNELS: usize = 23;
+/// ADC channel of the internal temperature sensor.
+pub const TEMPERATURE_CHANNEL: u8 = 9;
+/// Factory conversion of the temperature sensor at 30 °C and VDDA 3.0 V.
+const TS_CAL1: *const u16 = 0x1fff_7568 as *const u16;
+const TS_CAL1_TEMP_DC: i64 = 300;
+const TS_CAL_VDDA_MV: i64 = 3_000;
+/// Typical sensor slope from the datasheet, there's no second calibration
+/// point.
+const TS_AVG_SLOPE_UV_PER_C: i64 = 2_530;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watchdog {
+    Awd1,
+    Awd2,
+    Awd3,
+}
+
+impl Watchdog {
+    pub const ALL: [Watchdog; 3] = [Watchdog::Awd1, Watchdog::Awd2, Watchdog::Awd3];
+
+    pub fn from_slot(slot: usize) -> Option<Self> {
+        Self::ALL.get(slot).copied()
+    }
+
+    pub fn slot(self) -> usize {
+        self as usize
+    }
+}
+
+/// `CHSELR` bits of `channels`, which must exist.
+fn channel_mask(channels: &[u8]) -> u32 {
+    channels.iter().fold(0, |mask, &channel| {
+        assert!(
+            (channel as usize) < MAX_CHANNELS,
+            "no ADC channel {}",
+            channel
+        );
+        mask | 1 << channel
+    })
+}
+
+/// ADC channel number of an analog pin.
+pub fn channel_of<PIN: Channel<Adc, ID = u8>>(_pin: &PIN) -> u8 {
+    PIN::channel()
+}
+
+/// Analog watchdogs of the ADC, which the HAL does not expose.
+///
+/// AWD1 guards a single channel, AWD2 and AWD3 are set up the same way so
+/// that every watchdog owns one channel with its own thresholds.
+/// Reconfiguration borrows the HAL `Adc` mutably, so it can't race with a
+/// conversion in progress.
+pub struct AnalogWatchdog {
+    _private: (),
+}
+
+impl AnalogWatchdog {
+    pub fn new(_adc: &mut Adc) -> Self {
+        Self { _private: () }
+    }
+
+    pub fn configure(
+        &mut self,
+        _adc: &mut Adc,
+        watchdog: Watchdog,
+        channel: u8,
+        thresholds: Thresholds,
+    ) {
+        let regs = regs();
+        let low = thresholds.low.min(MAX_THRESHOLD);
+        let high = thresholds.high.min(MAX_THRESHOLD);
+        match watchdog {
+            Watchdog::Awd1 => {
+                regs.awd1tr
+                    .write(|w| unsafe { w.lt1().bits(low).ht1().bits(high) });
+                regs.cfgr1.modify(|_, w| unsafe {
+                    w.awd1ch()
+                        .bits(channel)
+                        .awd1sgl()
+                        .set_bit()
+                        .awd1en()
+                        .set_bit()
+                });
+            }
+            Watchdog::Awd2 => {
+                regs.awd2tr
+                    .write(|w| unsafe { w.lt2().bits(low).ht2().bits(high) });
+                regs.awd2cr.write(|w| unsafe { w.bits(1 << channel) });
+            }
+            Watchdog::Awd3 => {
+                regs.awd3tr
+                    .write(|w| unsafe { w.lt3().bits(low).ht3().bits(high) });
+                regs.awd3cr.write(|w| unsafe { w.bits(1 << channel) });
+            }
+        }
+        self.unpend(watchdog);
+        self.listen(watchdog, true);
+    }
+
+    pub fn disable(&mut self, _adc: &mut Adc, watchdog: Watchdog) {
+        let regs = regs();
+        self.listen(watchdog, false);
+        match watchdog {
+            Watchdog::Awd1 => regs.cfgr1.modify(|_, w| w.awd1en().clear_bit()),
+            Watchdog::Awd2 => regs.awd2cr.reset(),
+            Watchdog::Awd3 => regs.awd3cr.reset(),
+        }
+        self.unpend(watchdog);
+    }
+
+    pub fn is_pending(&self, watchdog: Watchdog) -> bool {
+        let isr = regs().isr.read();
+        match watchdog {
+            Watchdog::Awd1 => isr.awd1().bit_is_set(),
+            Watchdog::Awd2 => isr.awd2().bit_is_set(),
+            Watchdog::Awd3 => isr.awd3().bit_is_set(),
+        }
+    }
+
+    pub fn unpend(&mut self, watchdog: Watchdog) {
+        regs().isr.write(|w| match watchdog {
+            Watchdog::Awd1 => w.awd1().set_bit(),
+            Watchdog::Awd2 => w.awd2().set_bit(),
+            Watchdog::Awd3 => w.awd3().set_bit(),
+        });
+    }
+
+    /// Result of the conversion that tripped the watchdog.
+    pub fn value(&self) -> u16 {
+        regs().dr.read().data().bits()
+    }
+
+    fn listen(&mut self, watchdog: Watchdog, enable: bool) {
+        regs().ier.modify(|_, w| match watchdog {
+            Watchdog::Awd1 => w.awd1ie().bit(enable),
+            Watchdog::Awd2 => w.awd2ie().bit(enable),
+            Watchdog::Awd3 => w.awd3ie().bit(enable),
+        });
+    }
+}
+
+/// Powers the internal temperature sensor, to be sampled on
+/// `TEMPERATURE_CHANNEL` with a sample time of at least 5 µs.
+pub fn enable_temperature_sensor(_adc: &mut Adc) {
+    regs().ccr.modify(|_, w| w.tsen().set_bit());
+}
+
+/// Temperature in tenths of a degree Celsius from a 12 bit conversion of
+/// the internal sensor at `vdda_mv`. The sensor measures the die, which
+/// runs a little warmer than the air around the board.
+pub fn temperature_dc(raw: u16, vdda_mv: u32) -> i16 {
+    let cal = unsafe { core::ptr::read_volatile(TS_CAL1) } as i64;
+    let sense_uv = raw as i64 * vdda_mv as i64 * 1_000 / 4_095;
+    let cal_uv = cal * TS_CAL_VDDA_MV * 1_000 / 4_095;
+    let temp_dc = TS_CAL1_TEMP_DC + (sense_uv - cal_uv) * 10 / TS_AVG_SLOPE_UV_PER_C;
+    temp_dc.clamp(i16::MIN as i64, i16::MAX as i64) as i16
+}
+
+/// Conversions paced by TIM3 instead of by software.
+///
+/// Every TIM3 update triggers one scan of the selected channels, in ascending
+/// channel order, and each result is fetched with [`TriggeredSampler::read`]
+/// from the `ADC` interrupt. The sample rate is thus independent of whoever
+/// consumes the values. The HAL `Adc` settings such as sample time and
+/// oversampling stay in effect and bound the reachable rate.
+pub struct TriggeredSampler {
+    adc: Adc,
+    timer: Timer<stm32::TIM3>,
+    rate: Hertz,
+    running: bool,
+    overruns: u32,
+    sequence: [u8; MAX_CHANNELS],
+    len: usize,
+    index: usize,
+}
+
+impl TriggeredSampler {
+    /// Panics if `channel` is not an ADC channel.
+    pub fn new(adc: Adc, mut timer: Timer<stm32::TIM3>, channel: u8) -> Self {
+        let mask = channel_mask(&[channel]);
+        timer.pause();
+        let tim = unsafe { &*stm32::TIM3::ptr() };
+        tim.cr2.modify(|_, w| unsafe { w.mms().bits(MMS_UPDATE) });
+
+        let regs = regs();
+        regs.cfgr1.modify(|_, w| unsafe {
+            w.extsel()
+                .bits(EXTSEL_TIM3_TRGO)
+                .exten()
+                .bits(EXTEN_RISING)
+                .cont()
+                .clear_bit()
+                .ovrmod()
+                .set_bit()
+        });
+
+        let mut sampler = Self {
+            adc,
+            timer,
+            rate: Hertz::Hz(1),
+            running: false,
+            overruns: 0,
+            sequence: [0; MAX_CHANNELS],
+            len: 0,
+            index: 0,
+        };
+        sampler.select(mask);
+        sampler
+    }
+
+    pub fn start(&mut self, rate: Hertz) {
+        self.rate = rate;
+        self.timer.start(rate.into_duration());
+        self.arm();
+    }
+
+    pub fn stop(&mut self) {
+        self.halt();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn rate(&self) -> Hertz {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: Hertz) {
+        self.rate = rate;
+        if self.running {
+            self.timer.start(rate.into_duration());
+        }
+    }
+
+    pub fn set_channel(&mut self, channel: u8) {
+        self.set_channels(&[channel]);
+    }
+
+    /// Selects the channels converted on every trigger. Panics on a channel
+    /// the ADC doesn't have.
+    pub fn set_channels(&mut self, channels: &[u8]) {
+        let mask = channel_mask(channels);
+        let running = self.running;
+        if running {
+            self.halt();
+        }
+        self.select(mask);
+        if running {
+            self.arm();
+        }
+    }
+
+    /// Stops conversions while `f` changes ADC settings, then resumes them.
+    pub fn reconfigure<R>(&mut self, f: impl FnOnce(&mut Adc) -> R) -> R {
+        let running = self.running;
+        if running {
+            self.halt();
+        }
+        let res = f(&mut self.adc);
+        if running {
+            self.arm();
+        }
+        res
+    }
+
+    /// Takes the finished conversion along with its channel, to be called
+    /// from the `ADC` interrupt.
+    pub fn read(&mut self) -> Option<(u8, u16)> {
+        let regs = regs();
+        let isr = regs.isr.read();
+        if isr.ovr().bit_is_set() {
+            regs.isr.write(|w| w.ovr().set_bit());
+            self.overruns = self.overruns.wrapping_add(1);
+        }
+        if isr.eoc().bit_is_clear() || self.len == 0 {
+            return None;
+        }
+        let value = regs.dr.read().data().bits();
+        // End of sequence resyncs the channel index after lost conversions
+        let channel = if isr.eos().bit_is_set() {
+            regs.isr.write(|w| w.eos().set_bit());
+            self.index = 0;
+            self.sequence[self.len - 1]
+        } else {
+            let channel = self.sequence[self.index];
+            self.index = (self.index + 1) % self.len;
+            channel
+        };
+        Some((channel, value))
+    }
+
+    /// Conversions lost because the previous result was not read in time.
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+
+    pub fn release(mut self) -> (Adc, Timer<stm32::TIM3>) {
+        self.halt();
+        (self.adc, self.timer)
+    }
+
+    fn select(&mut self, mask: u32) {
+        self.len = 0;
+        self.index = 0;
+        for channel in 0..MAX_CHANNELS as u8 {
+            if mask & 1 << channel != 0 {
+                self.sequence[self.len] = channel;
+                self.len += 1;
+            }
+        }
+        let regs = regs();
+        regs.isr.write(|w| w.ccrdy().set_bit());
+        regs.chselr0().write(|w| unsafe { w.bits(mask) });
+        while regs.isr.read().ccrdy().bit_is_clear() {}
+    }
+
+    fn arm(&mut self) {
+        let regs = regs();
+        if regs.cr.read().aden().bit_is_clear() {
+            regs.isr.write(|w| w.adrdy().set_bit());
+            regs.cr.modify(|_, w| w.aden().set_bit());
+            while regs.isr.read().adrdy().bit_is_clear() {}
+        }
+        regs.isr
+            .write(|w| w.eoc().set_bit().eos().set_bit().ovr().set_bit());
+        self.index = 0;
+        regs.ier.modify(|_, w| w.eocie().set_bit());
+        regs.cr.modify(|_, w| w.adstart().set_bit());
+        self.timer.resume();
+        self.running = true;
+    }
+
+    fn halt(&mut self) {
+        let regs = regs();
+        self.timer.pause();
+        if regs.cr.read().adstart().bit_is_set() {
+            regs.cr.modify(|_, w| w.adstp().set_bit());
+            while regs.cr.read().adstart().bit_is_set() {}
+        }
+        regs.ier.modify(|_, w| w.eocie().clear_bit());
+        self.running = false;
+    }
+}
+
+fn regs() -> &'static stm32::adc::RegisterBlock {
+    unsafe { &*stm32::ADC::ptr() }
+}
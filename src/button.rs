@@ -0,0 +1,47 @@
+//! User button B1 on PC13, debounced and classified by
+//! `robo_core::button`.
+//!
+//! The EXTI interrupt of the pin records edges, a periodic timer task calls
+//! `tick` and forwards the events, e.g. by spawning a software task.
+
+use hal::exti::Event as ExtiEvent;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::stm32;
+use robo_core::button::{Button, Config, Event};
+
+use crate::hal;
+
+pub struct UserButton {
+    pin: PC13<Input<Floating>>,
+    button: Button,
+    now: u32,
+}
+
+impl UserButton {
+    /// Listens to both edges of the pin, the button pulls it low.
+    pub fn new(pin: PC13<DefaultMode>, exti: &mut stm32::EXTI, config: Config) -> Self {
+        Self {
+            pin: pin.listen(SignalEdge::All, exti),
+            button: Button::new(config),
+            now: 0,
+        }
+    }
+
+    /// Call from the EXTI interrupt.
+    pub fn on_edge(&mut self, exti: &mut stm32::EXTI) {
+        let pressed = self.pin.is_low().unwrap_or_default();
+        self.button.edge(self.now, pressed);
+        exti.unpend(ExtiEvent::GPIO13);
+    }
+
+    /// Call every `elapsed_ms`, at most the debounce time of the config.
+    pub fn tick(&mut self, elapsed_ms: u32) -> Option<Event> {
+        self.now = self.now.wrapping_add(elapsed_ms);
+        self.button.poll(self.now)
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.button.is_pressed()
+    }
+}
@@ -0,0 +1,387 @@
+use core::cell::{Cell, RefCell};
+
+use cortex_m::interrupt::{self, Mutex};
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::OriginDimensions;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::Pixel;
+use hal::hal::digital::v2::OutputPin;
+use hal::spi::Spi;
+use hal::stm32;
+use klaptik::{Canvas, Point, Rectangle, Size};
+use robo_core::dirty::{RenderStats, Shadow};
+use robo_core::draw::{Area, DrawQueue};
+use robo_core::page::{Band, PAGE_HEIGHT};
+
+use crate::hal;
+
+/// Geometry of the 128x64 SSD1306.
+pub const WIDTH: usize = 128;
+pub const PAGES: usize = 8;
+
+/// Queued bytes per batch, a full frame with room for the widgets on top.
+pub const QUEUE_BYTES: usize = WIDTH * PAGES + 256;
+pub const QUEUE_OPS: usize = 32;
+
+/// Pages rendered at a time by `HybridCanvas`, must divide `PAGES`.
+pub const BAND_PAGES: usize = 2;
+
+/// DMAMUX request of SPI1 TX.
+const DMAREQ_SPI1_TX: u8 = 17;
+/// SSD1306 commands setting the draw area in horizontal addressing mode.
+const SET_COLUMN_ADDRESS: u8 = 0x21;
+const SET_PAGE_ADDRESS: u8 = 0x22;
+
+/// Render counters, meant for a `static` so the app can read them while the
+/// canvas itself is owned by a `SpriteDisplay`.
+pub struct RenderCounters(Mutex<Cell<RenderStats>>);
+
+impl RenderCounters {
+    pub const fn new() -> Self {
+        Self(Mutex::new(Cell::new(RenderStats::new())))
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut RenderStats)) {
+        interrupt::free(|cs| {
+            let cell = self.0.borrow(cs);
+            let mut stats = cell.get();
+            f(&mut stats);
+            cell.set(stats);
+        });
+    }
+
+    pub fn get(&self) -> RenderStats {
+        interrupt::free(|cs| self.0.borrow(cs).get())
+    }
+
+    /// Returns the counters and restarts them from zero.
+    pub fn take(&self) -> RenderStats {
+        interrupt::free(|cs| self.0.borrow(cs).replace(RenderStats::new()))
+    }
+}
+
+impl Default for RenderCounters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Canvas forwarding only the columns that differ from what the display
+/// already shows.
+///
+/// Keeps a 1K copy of the display. Draws not aligned to display pages are
+/// passed through as they are.
+pub struct DirtyCanvas<C> {
+    canvas: C,
+    shadow: Shadow<WIDTH, PAGES>,
+    counters: &'static RenderCounters,
+}
+
+impl<C: Canvas> DirtyCanvas<C> {
+    /// The display must be cleared, like after `init`.
+    pub fn new(canvas: C, counters: &'static RenderCounters) -> Self {
+        Self {
+            canvas,
+            shadow: Shadow::new(),
+            counters,
+        }
+    }
+
+    /// Sends the next draws in full, e.g. after the display was reset.
+    pub fn invalidate(&mut self) {
+        self.shadow.invalidate();
+    }
+}
+
+impl<C: Canvas> Canvas for DirtyCanvas<C> {
+    fn draw(&mut self, bounds: Rectangle, bitmap: &[u8]) {
+        let (start, end) = (bounds.start(), bounds.end());
+        let (x, y) = (start.x as usize, start.y as usize);
+        let (width, height) = ((end.x - start.x) as usize, (end.y - start.y) as usize);
+        if width == 0 {
+            return;
+        }
+        let aligned = y.is_multiple_of(PAGE_HEIGHT) && height.is_multiple_of(PAGE_HEIGHT);
+        if !aligned || bitmap.len() != width * height / PAGE_HEIGHT {
+            for page in y / PAGE_HEIGHT..(y + height).div_ceil(PAGE_HEIGHT) {
+                self.shadow.invalidate_page(page);
+            }
+            self.canvas.draw(bounds, bitmap);
+            self.counters.update(|stats| stats.sent(bitmap.len(), 0));
+            return;
+        }
+
+        let (mut sent, mut skipped) = (0, 0);
+        for (idx, row) in bitmap.chunks_exact(width).enumerate() {
+            let page = y / PAGE_HEIGHT + idx;
+            match self.shadow.update(page, x, row) {
+                Some(changed) => {
+                    let area = Rectangle::new(
+                        Point::new((x + changed.start) as _, (page * PAGE_HEIGHT) as _),
+                        Size::new(changed.len() as _, PAGE_HEIGHT as _),
+                    );
+                    sent += changed.len();
+                    skipped += row.len() - changed.len();
+                    self.canvas.draw(area, &row[changed]);
+                }
+                None => skipped += row.len(),
+            }
+        }
+        self.counters.update(|stats| stats.sent(sent, skipped));
+    }
+}
+
+/// Canvas for frames mixing klaptik sprites with embedded-graphics.
+///
+/// Renders the frame in bands of `BAND_PAGES` pages, so it needs 256 bytes
+/// instead of a 1K frame buffer. The frame is drawn once per band, clipped to
+/// it, and the band is then sent through the wrapped canvas. Wrap a
+/// `DirtyCanvas` to send only what changed since the last frame.
+pub struct HybridCanvas<C> {
+    canvas: C,
+    target: HybridTarget,
+}
+
+impl<C: Canvas> HybridCanvas<C> {
+    pub fn new(canvas: C) -> Self {
+        Self {
+            canvas,
+            target: HybridTarget(Band::new()),
+        }
+    }
+
+    /// Renders a frame, `draw` runs once per band and has to draw all of
+    /// the frame every time. Widgets only render when invalid, so invalidate
+    /// them before each run:
+    ///
+    /// ```ignore
+    /// canvas.render(|target| {
+    ///     ui.invalidate();
+    ///     ui.render(&mut SpriteDisplay::new(&mut *target, SPRITES));
+    ///     Text::new("Hello", Point::new(0, 40), style).draw(target).ok();
+    /// });
+    /// ```
+    pub fn render(&mut self, mut draw: impl FnMut(&mut HybridTarget)) {
+        for page in (0..PAGES).step_by(BAND_PAGES) {
+            self.target.0.start(page);
+            draw(&mut self.target);
+            let band = Rectangle::new(
+                Point::new(0, (page * PAGE_HEIGHT) as _),
+                Size::new(WIDTH as _, (BAND_PAGES * PAGE_HEIGHT) as _),
+            );
+            self.canvas.draw(band, self.target.0.as_bytes());
+        }
+    }
+}
+
+/// Current band of a `HybridCanvas`. A klaptik `Canvas` for sprites and an
+/// embedded-graphics `DrawTarget` of the whole display size.
+pub struct HybridTarget(Band<WIDTH, BAND_PAGES>);
+
+impl Canvas for &mut HybridTarget {
+    fn draw(&mut self, bounds: Rectangle, bitmap: &[u8]) {
+        let (start, end) = (bounds.start(), bounds.end());
+        let (width, height) = ((end.x - start.x) as usize, (end.y - start.y) as usize);
+        self.0
+            .blit(start.x as _, start.y as _, width, height, bitmap);
+    }
+}
+
+impl OriginDimensions for HybridTarget {
+    fn size(&self) -> embedded_graphics::geometry::Size {
+        embedded_graphics::geometry::Size::new(WIDTH as _, (PAGES * PAGE_HEIGHT) as _)
+    }
+}
+
+impl DrawTarget for HybridTarget {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            self.0.set_pixel(point.x, point.y, color.is_on());
+        }
+        Ok(())
+    }
+}
+
+pub type FrameQueue = DrawQueue<QUEUE_BYTES, QUEUE_OPS>;
+
+/// Draw queue between the renderer and `DmaDisplay`, meant for a `static`.
+pub struct SharedQueue(Mutex<RefCell<FrameQueue>>);
+
+impl SharedQueue {
+    pub const fn new() -> Self {
+        Self(Mutex::new(RefCell::new(DrawQueue::new())))
+    }
+
+    pub fn lock<R>(&self, f: impl FnOnce(&mut FrameQueue) -> R) -> R {
+        interrupt::free(|cs| f(&mut self.0.borrow(cs).borrow_mut()))
+    }
+}
+
+impl Default for SharedQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Canvas queueing page aligned draws for `DmaDisplay` instead of sending
+/// them.
+pub struct QueueCanvas {
+    queue: &'static SharedQueue,
+}
+
+impl QueueCanvas {
+    pub fn new(queue: &'static SharedQueue) -> Self {
+        Self { queue }
+    }
+}
+
+impl Canvas for QueueCanvas {
+    fn draw(&mut self, bounds: Rectangle, bitmap: &[u8]) {
+        let (start, end) = (bounds.start(), bounds.end());
+        let area = Area {
+            x: start.x as u8,
+            page: start.y as u8 / PAGE_HEIGHT as u8,
+            width: (end.x - start.x) as u8,
+            pages: (end.y - start.y) as u8 / PAGE_HEIGHT as u8,
+        };
+        self.queue.lock(|queue| queue.draw(area, bitmap));
+    }
+}
+
+/// SSD1306 on SPI fed from a `SharedQueue` by DMA1 channel 1.
+///
+/// The display must be initialized already, e.g. by the `ssd1306` driver
+/// before releasing the interface. `on_interrupt` has to run from the
+/// `DMA1_CHANNEL1` interrupt, pend that interrupt after queueing draws to
+/// start a transfer.
+pub struct DmaDisplay<PINS, DC, CS> {
+    spi: Spi<stm32::SPI, PINS>,
+    dc: DC,
+    cs: CS,
+    queue: &'static SharedQueue,
+}
+
+impl<PINS, DC: OutputPin, CS: OutputPin> DmaDisplay<PINS, DC, CS> {
+    pub fn new(spi: Spi<stm32::SPI, PINS>, dc: DC, cs: CS, queue: &'static SharedQueue) -> Self {
+        let rcc = unsafe { &*stm32::RCC::ptr() };
+        rcc.ahbenr.modify(|_, w| w.dma1en().set_bit());
+
+        let dmamux = unsafe { &*stm32::DMAMUX::ptr() };
+        dmamux
+            .c0cr
+            .write(|w| unsafe { w.dmareq_id().bits(DMAREQ_SPI1_TX) });
+
+        let dma = dma();
+        dma.ccr1.write(|w| unsafe {
+            w.dir()
+                .set_bit()
+                .minc()
+                .set_bit()
+                .psize()
+                .bits(0b00)
+                .msize()
+                .bits(0b00)
+                .tcie()
+                .set_bit()
+        });
+        dma.cpar1
+            .write(|w| unsafe { w.bits(&spi_regs().dr as *const _ as u32) });
+        spi_regs().cr2.modify(|_, w| w.txdmaen().set_bit());
+
+        Self { spi, dc, cs, queue }
+    }
+
+    /// Completes the transfer in flight and starts the next queued draw.
+    pub fn on_interrupt(&mut self) {
+        let dma = dma();
+        if dma.ccr1.read().en().bit_is_set() {
+            if dma.isr.read().tcif1().bit_is_clear() {
+                return;
+            }
+            dma.ifcr.write(|w| w.cgif1().set_bit());
+            dma.ccr1.modify(|_, w| w.en().clear_bit());
+            self.flush();
+            self.cs.set_high().ok();
+        }
+
+        loop {
+            // The batch stays untouched until the next call of `pop`, DMA
+            // reads it from the static queue meanwhile
+            let next = self.queue.lock(|queue| {
+                queue
+                    .pop()
+                    .map(|(area, bytes)| (area, bytes.as_ptr() as u32, bytes.len()))
+            });
+            match next {
+                Some((_, _, 0)) => continue,
+                Some((area, addr, len)) => {
+                    self.start(area, addr, len);
+                    return;
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Gives back the SPI and pins. Call with no transfer in flight.
+    pub fn release(self) -> (Spi<stm32::SPI, PINS>, DC, CS) {
+        spi_regs().cr2.modify(|_, w| w.txdmaen().clear_bit());
+        (self.spi, self.dc, self.cs)
+    }
+
+    fn start(&mut self, area: Area, addr: u32, len: usize) {
+        self.cs.set_low().ok();
+        self.dc.set_low().ok();
+        self.command(&[
+            SET_COLUMN_ADDRESS,
+            area.x,
+            area.x + area.width - 1,
+            SET_PAGE_ADDRESS,
+            area.page,
+            area.page + area.pages - 1,
+        ]);
+        self.dc.set_high().ok();
+
+        let dma = dma();
+        dma.cmar1.write(|w| unsafe { w.bits(addr) });
+        dma.cndtr1.write(|w| unsafe { w.bits(len as u32) });
+        dma.ccr1.modify(|_, w| w.en().set_bit());
+    }
+
+    /// Sends a few command bytes without DMA.
+    fn command(&mut self, bytes: &[u8]) {
+        let spi = spi_regs();
+        for &byte in bytes {
+            while spi.sr.read().txe().bit_is_clear() {}
+            // 8 bit access, a half word write would send two frames
+            unsafe { core::ptr::write_volatile(&spi.dr as *const _ as *mut u8, byte) };
+        }
+        self.flush();
+    }
+
+    /// Waits for the last frame to leave and drops what was received, the
+    /// display never answers.
+    fn flush(&mut self) {
+        let spi = spi_regs();
+        while spi.sr.read().bsy().bit_is_set() {}
+        while spi.sr.read().frlvl().bits() != 0 {
+            unsafe { core::ptr::read_volatile(&spi.dr as *const _ as *const u8) };
+        }
+        // Reading SR after DR clears an overrun
+        spi.sr.read();
+    }
+}
+
+fn dma() -> &'static stm32::dma1::RegisterBlock {
+    unsafe { &*stm32::DMA1::ptr() }
+}
+
+fn spi_regs() -> &'static stm32::spi::RegisterBlock {
+    unsafe { &*stm32::SPI::ptr() }
+}
@@ -0,0 +1,126 @@
+//! Rotary encoder with push switch, counted by TIM1 in encoder interface
+//! mode and decoded by `robo_core::encoder`.
+//!
+//! Channel A goes to PA8 (TIM1_CH1), channel B to PB3 (TIM1_CH2) and the
+//! switch to PB4, all pulled to ground when active. The timer counts every
+//! edge of both channels in hardware, so no turn is lost however busy the
+//! firmware is, as long as `tick` runs before the counter moves by half its
+//! range.
+
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::stm32;
+use hal::timer::Timer;
+use robo_core::button::{self, Button};
+use robo_core::encoder::{Config, Encoder, Event};
+use robo_core::ring::Ring;
+
+use crate::hal;
+
+/// Counting on both edges of both channels.
+const SMS_ENCODER_MODE_3: u8 = 0b011;
+/// Capture inputs mapped to TI1 and TI2.
+const CCS_INPUT: u8 = 0b01;
+/// Input filter of 8 samples at fDTS/8, against contact bounce.
+const INPUT_FILTER: u8 = 0b1010;
+/// Switch events held back while the knob turns. When full, the oldest one
+/// makes room and is counted in `dropped_switches`.
+const SWITCH_QUEUE: usize = 4;
+
+pub struct RotaryEncoder {
+    _timer: Timer<stm32::TIM1>,
+    _pins: (PA8<DefaultMode>, PB3<DefaultMode>),
+    switch_pin: PB4<Input<PullUp>>,
+    encoder: Encoder,
+    switch: Button,
+    now: u32,
+    pending: Ring<button::Event, SWITCH_QUEUE>,
+    dropped: u32,
+}
+
+impl RotaryEncoder {
+    pub fn new(
+        mut timer: Timer<stm32::TIM1>,
+        pins: (PA8<DefaultMode>, PB3<DefaultMode>, PB4<DefaultMode>),
+        config: Config,
+        switch: button::Config,
+    ) -> Self {
+        let (a, b, switch_pin) = pins;
+        a.set_alt_mode(AltFunction::AF2);
+        b.set_alt_mode(AltFunction::AF1);
+
+        timer.pause();
+        let tim = unsafe { &*stm32::TIM1::ptr() };
+        tim.ccmr1_input().write(|w| unsafe {
+            w.cc1s()
+                .bits(CCS_INPUT)
+                .ic1f()
+                .bits(INPUT_FILTER)
+                .cc2s()
+                .bits(CCS_INPUT)
+                .ic2f()
+                .bits(INPUT_FILTER)
+        });
+        tim.ccer
+            .modify(|_, w| w.cc1p().clear_bit().cc2p().clear_bit());
+        tim.smcr
+            .modify(|_, w| unsafe { w.sms().bits(SMS_ENCODER_MODE_3) });
+        tim.psc.write(|w| unsafe { w.psc().bits(0) });
+        tim.arr.write(|w| unsafe { w.arr().bits(u16::MAX) });
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        Self {
+            _timer: timer,
+            _pins: (a, b),
+            switch_pin: switch_pin.into_pull_up_input(),
+            encoder: Encoder::new(config, Self::count()),
+            switch: Button::new(switch),
+            now: 0,
+            pending: Ring::new(),
+            dropped: 0,
+        }
+    }
+
+    fn count() -> u16 {
+        let tim = unsafe { &*stm32::TIM1::ptr() };
+        tim.cnt.read().cnt().bits()
+    }
+
+    /// Call every `tick_ms` of the config. Returns a turn or a switch event,
+    /// switch events coming with turns are queued and returned in order on
+    /// the next calls without a turn.
+    pub fn tick(&mut self) -> Option<Event> {
+        self.now = self.now.wrapping_add(self.encoder.config().tick_ms);
+        let pressed = self.switch_pin.is_low().unwrap_or_default();
+        if let Some(event) = self.switch.sample(self.now, pressed) {
+            if self.pending.push(event).is_some() {
+                self.dropped = self.dropped.wrapping_add(1);
+            }
+        }
+        match self.encoder.update(Self::count()) {
+            Some(turn) => Some(Event::Turn(turn)),
+            None => self.pending.pop_oldest().map(Event::Switch),
+        }
+    }
+
+    pub fn position(&self) -> i32 {
+        self.encoder.position()
+    }
+
+    pub fn set_position(&mut self, position: i32) {
+        self.encoder.set_position(position);
+    }
+
+    pub fn velocity(&self) -> i32 {
+        self.encoder.velocity()
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.switch.is_pressed()
+    }
+
+    /// Switch events lost while the knob kept turning.
+    pub fn dropped_switches(&self) -> u32 {
+        self.dropped
+    }
+}
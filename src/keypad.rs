@@ -0,0 +1,61 @@
+//! Matrix keypad on GPIOs, decoded by `robo_core::keypad`.
+//!
+//! Rows are open drain outputs, pulled low one at a time, columns are inputs
+//! with pull-ups. Open drain keeps two keys in one column from shorting a
+//! low row to a high one. Call `scan` from a periodic timer task, every few
+//! milliseconds.
+
+use hal::hal::digital::v2::{InputPin, OutputPin};
+use robo_core::keypad::{Config, KeyEvent, Matrix};
+
+use crate::hal;
+
+/// Lets a driven row settle before the columns are read.
+const SETTLE_CYCLES: u32 = 48;
+
+pub struct Keypad<R, C, const ROWS: usize, const COLS: usize> {
+    rows: [R; ROWS],
+    cols: [C; COLS],
+    matrix: Matrix<ROWS, COLS>,
+}
+
+impl<R, C, const ROWS: usize, const COLS: usize> Keypad<R, C, ROWS, COLS>
+where
+    R: OutputPin,
+    C: InputPin,
+{
+    pub fn new(mut rows: [R; ROWS], cols: [C; COLS], config: Config) -> Self {
+        for row in rows.iter_mut() {
+            row.set_high().ok();
+        }
+        Self {
+            rows,
+            cols,
+            matrix: Matrix::new(config),
+        }
+    }
+
+    /// Reads all keys and reports the ones that changed.
+    pub fn scan(&mut self, on_event: impl FnMut(KeyEvent)) {
+        let mut raw = [0; ROWS];
+        for (row, pin) in self.rows.iter_mut().enumerate() {
+            pin.set_low().ok();
+            cortex_m::asm::delay(SETTLE_CYCLES);
+            for (col, input) in self.cols.iter().enumerate() {
+                if input.is_low().unwrap_or(false) {
+                    raw[row] |= 1 << col;
+                }
+            }
+            pin.set_high().ok();
+        }
+        self.matrix.scan(&raw, on_event);
+    }
+
+    pub fn matrix(&self) -> &Matrix<ROWS, COLS> {
+        &self.matrix
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.matrix.set_config(config);
+    }
+}
@@ -0,0 +1,16 @@
+#![no_std]
+
+use stm32c0xx_hal as hal;
+
+pub mod adc;
+pub mod button;
+pub mod display;
+pub mod encoder;
+pub mod keypad;
+pub mod line;
+pub mod motor;
+pub mod odometry;
+pub mod panel;
+pub mod servo;
+pub mod sonar;
+pub mod ui;
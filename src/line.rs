@@ -0,0 +1,87 @@
+//! Reflectance sensor array on the ADC, for `robo_core::line`.
+//!
+//! Every TIM3 trigger of the `TriggeredSampler` scans all sensors. The ADC
+//! converts in ascending channel order, so the results are put back in the
+//! order of the sensors across the array before a scan is handed out.
+
+use robo_core::line::{MAX_SENSORS, MIN_SENSORS};
+
+use crate::adc::TriggeredSampler;
+
+pub struct LineSensors {
+    sampler: TriggeredSampler,
+    channels: [u8; MAX_SENSORS],
+    len: usize,
+    raw: [u16; MAX_SENSORS],
+    /// Sensors converted so far in the current scan.
+    received: u8,
+}
+
+impl LineSensors {
+    /// `channels` lists the ADC channel of every sensor, from the left of
+    /// the array. Sampling starts with `TriggeredSampler::start` as usual.
+    ///
+    /// Panics unless there are `MIN_SENSORS` to `MAX_SENSORS` channels, all
+    /// different: a repeated channel is converted once per scan, so the
+    /// scan would never complete.
+    pub fn new(mut sampler: TriggeredSampler, channels: &[u8]) -> Self {
+        assert!(
+            (MIN_SENSORS..=MAX_SENSORS).contains(&channels.len()),
+            "{} sensors, the array takes {} to {}",
+            channels.len(),
+            MIN_SENSORS,
+            MAX_SENSORS
+        );
+        for (sensor, channel) in channels.iter().enumerate() {
+            assert!(
+                !channels[..sensor].contains(channel),
+                "ADC channel {} used twice",
+                channel
+            );
+        }
+        sampler.set_channels(channels);
+        let mut sensors = [0; MAX_SENSORS];
+        sensors[..channels.len()].copy_from_slice(channels);
+        Self {
+            sampler,
+            channels: sensors,
+            len: channels.len(),
+            raw: [0; MAX_SENSORS],
+            received: 0,
+        }
+    }
+
+    pub fn sensors(&self) -> usize {
+        self.len
+    }
+
+    pub fn sampler(&self) -> &TriggeredSampler {
+        &self.sampler
+    }
+
+    pub fn sampler_mut(&mut self) -> &mut TriggeredSampler {
+        &mut self.sampler
+    }
+
+    /// Takes the finished conversion, to be called from the `ADC`
+    /// interrupt. Returns the raw readings once all sensors are converted,
+    /// a scan with lost conversions is dropped.
+    pub fn read(&mut self) -> Option<&[u16]> {
+        let (channel, raw) = self.sampler.read()?;
+        let channels = &self.channels[..self.len];
+        let sensor = channels.iter().position(|&ch| ch == channel)?;
+        self.raw[sensor] = raw;
+        self.received |= 1 << sensor;
+        // The highest channel ends the scan
+        if channels.iter().any(|&ch| ch > channel) {
+            return None;
+        }
+        let complete = self.received.count_ones() as usize == self.len;
+        self.received = 0;
+        complete.then_some(&self.raw[..self.len])
+    }
+
+    pub fn release(self) -> TriggeredSampler {
+        self.sampler
+    }
+}
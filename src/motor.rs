@@ -0,0 +1,102 @@
+//! H-bridge outputs for `robo_core::motor`.
+//!
+//! Each bridge input is a `Signal`: a PWM channel, a GPIO for inputs that
+//! only switch direction, or `()` for inputs the chip doesn't have.
+
+use hal::hal::digital::v2::OutputPin;
+use hal::hal::PwmPin;
+use robo_core::motor::{Motor, Outputs, Stop, DUTY_MAX};
+
+use crate::hal;
+
+/// Bridge input driven at a duty cycle of `DUTY_MAX`.
+pub trait Signal {
+    fn set(&mut self, duty: u16);
+}
+
+/// PWM channel, enabled on creation.
+pub struct Pwm<P>(P);
+
+impl<P: PwmPin<Duty = u16>> Pwm<P> {
+    pub fn new(mut pin: P) -> Self {
+        pin.set_duty(0);
+        pin.enable();
+        Self(pin)
+    }
+}
+
+impl<P: PwmPin<Duty = u16>> Signal for Pwm<P> {
+    fn set(&mut self, duty: u16) {
+        let max = self.0.get_max_duty() as u32;
+        self.0
+            .set_duty((duty.min(DUTY_MAX) as u32 * max / DUTY_MAX as u32) as u16);
+    }
+}
+
+/// GPIO, high from half duty up.
+pub struct Digital<P>(pub P);
+
+impl<P: OutputPin> Signal for Digital<P> {
+    fn set(&mut self, duty: u16) {
+        if duty >= DUTY_MAX / 2 {
+            self.0.set_high().ok();
+        } else {
+            self.0.set_low().ok();
+        }
+    }
+}
+
+/// Input the chip doesn't have.
+impl Signal for () {
+    fn set(&mut self, _duty: u16) {}
+}
+
+/// One bridge of a dual H-bridge and the motor on it.
+pub struct HBridge<EN, IN1, IN2> {
+    enable: EN,
+    in1: IN1,
+    in2: IN2,
+    motor: Motor,
+}
+
+impl<EN: Signal, IN1: Signal, IN2: Signal> HBridge<EN, IN1, IN2> {
+    /// Starts stopped, as set by the stop mode of `motor`.
+    pub fn new(enable: EN, in1: IN1, in2: IN2, motor: Motor) -> Self {
+        let mut bridge = Self {
+            enable,
+            in1,
+            in2,
+            motor,
+        };
+        bridge.motor.halt(motor.stop_mode());
+        bridge.apply(bridge.motor.tick(0));
+        bridge
+    }
+
+    pub fn motor(&self) -> &Motor {
+        &self.motor
+    }
+
+    pub fn motor_mut(&mut self) -> &mut Motor {
+        &mut self.motor
+    }
+
+    /// Stops right away, without waiting for the next tick.
+    pub fn halt(&mut self, stop: Stop) {
+        self.motor.halt(stop);
+        let outputs = self.motor.tick(0);
+        self.apply(outputs);
+    }
+
+    /// Moves along the speed ramp, call periodically.
+    pub fn tick(&mut self, elapsed_ms: u32) {
+        let outputs = self.motor.tick(elapsed_ms);
+        self.apply(outputs);
+    }
+
+    fn apply(&mut self, outputs: Outputs) {
+        self.in1.set(outputs.in1);
+        self.in2.set(outputs.in2);
+        self.enable.set(outputs.enable);
+    }
+}
@@ -0,0 +1,123 @@
+//! Two wheel encoders counted on EXTI edges, for `robo_core::odometry`.
+//!
+//! Channel A of the left wheel goes to PB0, channel B to PB1, the right
+//! wheel uses PB4 and PB7. Both edges of channel A interrupt, the level of
+//! channel B then gives the direction, so a wheel counts twice the lines of
+//! its disk per revolution. Forward is channel A leading B. TIM16 runs free
+//! at 1 MHz and timestamps the edges for the period speed estimate, the
+//! timers driving the motors stay free.
+
+use hal::exti::Event as ExtiEvent;
+use hal::gpio::*;
+use hal::prelude::*;
+use hal::rcc::Rcc;
+use hal::stm32;
+use hal::timer::Timer;
+use robo_core::odometry::{Config, Odometry};
+
+use crate::hal;
+
+/// Microseconds from the 16 bit TIM16 counter, extended to 32 bits. Has to
+/// be read at least every 65 ms.
+struct Clock {
+    _timer: Timer<stm32::TIM16>,
+    last: u16,
+    now: u32,
+}
+
+impl Clock {
+    fn new(mut timer: Timer<stm32::TIM16>, rcc: &Rcc) -> Self {
+        timer.pause();
+        let tim = unsafe { &*stm32::TIM16::ptr() };
+        let prescaler = rcc.clocks.apb_tim_clk.raw() / 1_000_000 - 1;
+        tim.psc.write(|w| unsafe { w.psc().bits(prescaler as u16) });
+        tim.arr.write(|w| unsafe { w.arr().bits(u16::MAX) });
+        // Loads the prescaler
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+        Self {
+            _timer: timer,
+            last: 0,
+            now: 0,
+        }
+    }
+
+    fn now(&mut self) -> u32 {
+        let tim = unsafe { &*stm32::TIM16::ptr() };
+        let raw = tim.cnt.read().cnt().bits();
+        self.now = self.now.wrapping_add(raw.wrapping_sub(self.last) as u32);
+        self.last = raw;
+        self.now
+    }
+}
+
+pub struct WheelEncoders {
+    clock: Clock,
+    left: (PB0<Input<Floating>>, PB1<Input<PullUp>>),
+    right: (PB4<Input<Floating>>, PB7<Input<PullUp>>),
+    odometry: Odometry,
+}
+
+impl WheelEncoders {
+    /// Listens to both edges of channel A. `right` usually has `reverse`
+    /// set, the motor is mounted mirrored.
+    pub fn new(
+        timer: Timer<stm32::TIM16>,
+        pins: (
+            PB0<DefaultMode>,
+            PB1<DefaultMode>,
+            PB4<DefaultMode>,
+            PB7<DefaultMode>,
+        ),
+        exti: &mut stm32::EXTI,
+        rcc: &Rcc,
+        left: Config,
+        right: Config,
+        track_mm: u16,
+    ) -> Self {
+        let (left_a, left_b, right_a, right_b) = pins;
+        Self {
+            clock: Clock::new(timer, rcc),
+            left: (
+                left_a.listen(SignalEdge::All, exti),
+                left_b.into_pull_up_input(),
+            ),
+            right: (
+                right_a.listen(SignalEdge::All, exti),
+                right_b.into_pull_up_input(),
+            ),
+            odometry: Odometry::new(left, right, track_mm),
+        }
+    }
+
+    /// Call from the EXTI0_1 and EXTI4_15 interrupts.
+    pub fn on_edge(&mut self, exti: &mut stm32::EXTI) {
+        let now = self.clock.now();
+        if exti.is_pending(ExtiEvent::GPIO0, SignalEdge::All) {
+            let (a, b) = &self.left;
+            let forward = a.is_high().unwrap_or_default() != b.is_high().unwrap_or_default();
+            self.odometry.left.edge(now, forward);
+            exti.unpend(ExtiEvent::GPIO0);
+        }
+        if exti.is_pending(ExtiEvent::GPIO4, SignalEdge::All) {
+            let (a, b) = &self.right;
+            let forward = a.is_high().unwrap_or_default() != b.is_high().unwrap_or_default();
+            self.odometry.right.edge(now, forward);
+            exti.unpend(ExtiEvent::GPIO4);
+        }
+    }
+
+    /// Call periodically, at least every 65 ms.
+    pub fn update(&mut self) {
+        let now = self.clock.now();
+        self.odometry.update(now);
+    }
+
+    pub fn odometry(&self) -> &Odometry {
+        &self.odometry
+    }
+
+    pub fn odometry_mut(&mut self) -> &mut Odometry {
+        &mut self.odometry
+    }
+}
@@ -0,0 +1,293 @@
+//! Display panels on the Nucleo, picked by a cargo feature:
+//!
+//! - `ssd1306-spi`: SSD1306 on SPI, SCK PA5, MOSI PA7, DC PA9, CS PA15 and
+//!   reset on PA10 (default)
+//! - `ssd1306-i2c`: SSD1306 on I2C, SCL PB8 (D15) and SDA PB9 (D14)
+//! - `sh1106`: SH1106 on I2C, same pins
+//!
+//! The UIs draw through `PanelCanvas`, so they don't depend on the variant.
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+use hal::gpio::*;
+use hal::hal::blocking::delay::DelayMs;
+use hal::rcc::Rcc;
+use hal::stm32;
+use klaptik::{Canvas, Rectangle};
+use robo_core::page::PAGE_HEIGHT;
+use ssd1306::mode::BasicMode;
+use ssd1306::prelude::*;
+use ssd1306::Ssd1306;
+
+use crate::hal;
+
+#[cfg(not(any(feature = "ssd1306-spi", feature = "ssd1306-i2c", feature = "sh1106")))]
+compile_error!("enable one display feature: ssd1306-spi, ssd1306-i2c or sh1106");
+
+#[cfg(any(
+    all(feature = "ssd1306-spi", feature = "ssd1306-i2c"),
+    all(feature = "ssd1306-spi", feature = "sh1106"),
+    all(feature = "ssd1306-i2c", feature = "sh1106"),
+))]
+compile_error!("display features are exclusive, use `--no-default-features` to pick another one");
+
+/// Controller operations the UIs need, on 128x64 panels organized in pages
+/// of 8 pixel rows.
+pub trait Panel {
+    /// Sets the controller up and clears the display.
+    fn init(&mut self) -> Result<(), DisplayError>;
+
+    /// Draws `bitmap` in page format, `width` columns wide, from column `x`
+    /// of page `page`.
+    fn draw(&mut self, x: u8, page: u8, width: u8, bitmap: &[u8]) -> Result<(), DisplayError>;
+
+    fn set_contrast(&mut self, contrast: u8) -> Result<(), DisplayError>;
+
+    fn set_invert(&mut self, invert: bool) -> Result<(), DisplayError>;
+
+    /// Only 0 and 180 degrees keep the landscape layout of the UIs.
+    fn set_rotation(&mut self, rotation: DisplayRotation) -> Result<(), DisplayError>;
+
+    fn set_display_on(&mut self, on: bool) -> Result<(), DisplayError>;
+}
+
+/// SSD1306 on any display interface.
+pub struct Ssd1306Panel<DI> {
+    driver: Ssd1306<DI, DisplaySize128x64, BasicMode>,
+}
+
+impl<DI: WriteOnlyDataCommand> Ssd1306Panel<DI> {
+    pub fn new(interface: DI) -> Self {
+        Self {
+            driver: Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0),
+        }
+    }
+
+    /// Pulses the reset line of SPI modules, call before `init`.
+    pub fn reset<RST: hal::hal::digital::v2::OutputPin>(
+        &mut self,
+        rst: &mut RST,
+        delay: &mut impl DelayMs<u8>,
+    ) {
+        self.driver.reset(rst, delay).ok();
+    }
+}
+
+impl<DI: WriteOnlyDataCommand> Panel for Ssd1306Panel<DI> {
+    fn init(&mut self) -> Result<(), DisplayError> {
+        self.driver.init()?;
+        self.driver.clear()
+    }
+
+    fn draw(&mut self, x: u8, page: u8, width: u8, bitmap: &[u8]) -> Result<(), DisplayError> {
+        let rows = (bitmap.len() / width.max(1) as usize * PAGE_HEIGHT) as u8;
+        let top = page * PAGE_HEIGHT as u8;
+        self.driver
+            .set_draw_area((x, top), (x + width, top + rows))?;
+        self.driver.draw(bitmap)
+    }
+
+    fn set_contrast(&mut self, contrast: u8) -> Result<(), DisplayError> {
+        self.driver.set_brightness(Brightness::custom(2, contrast))
+    }
+
+    fn set_invert(&mut self, invert: bool) -> Result<(), DisplayError> {
+        self.driver.set_invert(invert)
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) -> Result<(), DisplayError> {
+        self.driver.set_rotation(rotation)
+    }
+
+    fn set_display_on(&mut self, on: bool) -> Result<(), DisplayError> {
+        self.driver.set_display_on(on)
+    }
+}
+
+/// SH1106 on any display interface.
+///
+/// The controller has 132 columns of RAM with the panel centered in them,
+/// and only page addressing, so draws are sent page by page.
+pub struct Sh1106Panel<DI> {
+    interface: DI,
+}
+
+impl<DI: WriteOnlyDataCommand> Sh1106Panel<DI> {
+    /// RAM columns left of the panel.
+    const COLUMN_OFFSET: u8 = 2;
+    const RAM_WIDTH: usize = 132;
+
+    pub fn new(interface: DI) -> Self {
+        Self { interface }
+    }
+
+    fn command(&mut self, bytes: &[u8]) -> Result<(), DisplayError> {
+        self.interface.send_commands(DataFormat::U8(bytes))
+    }
+
+    /// Points the RAM pointer at column `col` of RAM page `page`.
+    fn locate(&mut self, page: u8, col: u8) -> Result<(), DisplayError> {
+        self.command(&[0xb0 | page, col & 0x0f, 0x10 | col >> 4])
+    }
+}
+
+impl<DI: WriteOnlyDataCommand> Panel for Sh1106Panel<DI> {
+    fn init(&mut self) -> Result<(), DisplayError> {
+        self.command(&[
+            0xae, // display off
+            0xd5, 0x80, // clock divide
+            0xa8, 0x3f, // 64 rows
+            0xd3, 0x00, // no display offset
+            0x40, // start line 0
+            0xad, 0x8b, // DC-DC on
+            0xa1, // segment remap
+            0xc8, // COM scan from the bottom
+            0xda, 0x12, // alternative COM pins
+            0x81, 0x80, // contrast
+            0xd9, 0x22, // precharge
+            0xdb, 0x35, // VCOM deselect level
+            0xa4, // show the RAM
+            0xa6, // not inverted
+        ])?;
+        let blank = [0; Self::RAM_WIDTH];
+        for page in 0..8 {
+            self.locate(page, 0)?;
+            self.interface.send_data(DataFormat::U8(&blank))?;
+        }
+        self.set_display_on(true)
+    }
+
+    fn draw(&mut self, x: u8, page: u8, width: u8, bitmap: &[u8]) -> Result<(), DisplayError> {
+        for (idx, row) in bitmap.chunks(width.max(1) as usize).enumerate() {
+            self.locate(page + idx as u8, x + Self::COLUMN_OFFSET)?;
+            self.interface.send_data(DataFormat::U8(row))?;
+        }
+        Ok(())
+    }
+
+    fn set_contrast(&mut self, contrast: u8) -> Result<(), DisplayError> {
+        self.command(&[0x81, contrast])
+    }
+
+    fn set_invert(&mut self, invert: bool) -> Result<(), DisplayError> {
+        self.command(&[0xa6 | invert as u8])
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) -> Result<(), DisplayError> {
+        match rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate90 => self.command(&[0xa1, 0xc8]),
+            DisplayRotation::Rotate180 | DisplayRotation::Rotate270 => self.command(&[0xa0, 0xc0]),
+        }
+    }
+
+    fn set_display_on(&mut self, on: bool) -> Result<(), DisplayError> {
+        self.command(&[0xae | on as u8])
+    }
+}
+
+/// klaptik `Canvas` drawing to a `Panel`.
+pub struct PanelCanvas<P> {
+    panel: P,
+}
+
+impl<P: Panel> PanelCanvas<P> {
+    pub fn new(panel: P) -> Self {
+        Self { panel }
+    }
+
+    pub fn panel(&mut self) -> &mut P {
+        &mut self.panel
+    }
+}
+
+impl<P: Panel> Canvas for PanelCanvas<P> {
+    fn draw(&mut self, bounds: Rectangle, bitmap: &[u8]) {
+        let (start, end) = (bounds.start(), bounds.end());
+        let width = (end.x - start.x) as u8;
+        let page = (start.y as usize / PAGE_HEIGHT) as u8;
+        self.panel.draw(start.x as u8, page, width, bitmap).unwrap();
+    }
+}
+
+/// Lets a `SpriteDisplay` borrow the canvas, so the panel stays reachable
+/// for settings.
+impl<P: Panel> Canvas for &mut PanelCanvas<P> {
+    fn draw(&mut self, bounds: Rectangle, bitmap: &[u8]) {
+        (**self).draw(bounds, bitmap);
+    }
+}
+
+/// Everything a display variant may be wired to, each variant takes what it
+/// needs and drops the rest.
+pub struct Wiring {
+    pub spi: stm32::SPI,
+    pub i2c: stm32::I2C,
+    pub sck: PA5<DefaultMode>,
+    pub mosi: PA7<DefaultMode>,
+    pub dc: PA9<DefaultMode>,
+    pub cs: PA15<DefaultMode>,
+    pub rst: PA10<DefaultMode>,
+    pub scl: PB8<DefaultMode>,
+    pub sda: PB9<DefaultMode>,
+}
+
+#[cfg(feature = "ssd1306-spi")]
+pub type Display = Ssd1306Panel<
+    SPIInterface<
+        hal::spi::Spi<stm32::SPI, (PA5<DefaultMode>, hal::spi::NoMiso, PA7<DefaultMode>)>,
+        PA9<Output<PushPull>>,
+        PA15<Output<PushPull>>,
+    >,
+>;
+
+#[cfg(any(feature = "ssd1306-i2c", feature = "sh1106"))]
+type I2cBus =
+    I2CInterface<hal::i2c::I2c<stm32::I2C, PB9<Output<OpenDrain>>, PB8<Output<OpenDrain>>>>;
+
+#[cfg(feature = "ssd1306-i2c")]
+pub type Display = Ssd1306Panel<I2cBus>;
+
+#[cfg(feature = "sh1106")]
+pub type Display = Sh1106Panel<I2cBus>;
+
+/// Brings up the display of the selected variant, initialized and cleared.
+#[cfg(feature = "ssd1306-spi")]
+pub fn connect(wiring: Wiring, delay: &mut impl DelayMs<u8>, rcc: &mut Rcc) -> Display {
+    use hal::hal::digital::v2::OutputPin;
+    use hal::prelude::*;
+    use hal::spi::{Mode, NoMiso, Phase, Polarity};
+
+    let mut cs = wiring.cs.into_push_pull_output();
+    cs.set_high().ok();
+    let mut dc = wiring.dc.into_push_pull_output();
+    dc.set_high().ok();
+    let mut rst = wiring.rst.into_push_pull_output();
+    rst.set_high().ok();
+
+    let spi = wiring.spi.spi(
+        (wiring.sck, NoMiso, wiring.mosi),
+        Mode {
+            polarity: Polarity::IdleLow,
+            phase: Phase::CaptureOnFirstTransition,
+        },
+        2.MHz(),
+        rcc,
+    );
+    let mut display = Ssd1306Panel::new(SPIInterface::new(spi, dc, cs));
+    display.reset(&mut rst, delay);
+    display.init().unwrap();
+    display
+}
+
+/// Brings up the display of the selected variant, initialized and cleared.
+#[cfg(any(feature = "ssd1306-i2c", feature = "sh1106"))]
+pub fn connect(wiring: Wiring, _delay: &mut impl DelayMs<u8>, rcc: &mut Rcc) -> Display {
+    use hal::i2c::Config;
+    use hal::prelude::*;
+
+    let sda = wiring.sda.into_open_drain_output_in_state(PinState::High);
+    let scl = wiring.scl.into_open_drain_output_in_state(PinState::High);
+    let i2c = wiring.i2c.i2c(sda, scl, Config::new(400.kHz()), rcc);
+    let mut display = Display::new(ssd1306::I2CDisplayInterface::new(i2c));
+    display.init().unwrap();
+    display
+}
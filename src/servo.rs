@@ -0,0 +1,51 @@
+//! RC servos on the PWM channels of one timer, for `robo_core::servo`.
+//!
+//! The timer runs at 50 Hz and each of its channels drives one servo, so a
+//! four channel timer such as TIM1 or TIM3 serves up to four. A detached
+//! servo gets a duty cycle of 0, the line stays low and carries no pulses.
+
+use hal::hal::PwmPin;
+use robo_core::servo::{Calibration, Servo, PERIOD_US};
+
+use crate::hal;
+
+/// Servo frame rate.
+pub const FRAME_HZ: u32 = 1_000_000 / PERIOD_US as u32;
+
+/// One servo on a PWM channel, enabled on creation.
+pub struct ServoPin<P> {
+    pin: P,
+    servo: Servo,
+}
+
+impl<P: PwmPin<Duty = u16>> ServoPin<P> {
+    /// Starts detached.
+    pub fn new(mut pin: P, calibration: Calibration) -> Self {
+        pin.set_duty(0);
+        pin.enable();
+        Self {
+            pin,
+            servo: Servo::new(calibration),
+        }
+    }
+
+    pub fn servo(&self) -> &Servo {
+        &self.servo
+    }
+
+    pub fn servo_mut(&mut self) -> &mut Servo {
+        &mut self.servo
+    }
+
+    /// Call every `elapsed_ms`, ideally once per frame.
+    pub fn tick(&mut self, elapsed_ms: u32) {
+        let duty = match self.servo.tick(elapsed_ms) {
+            Some(us) => {
+                let max = self.pin.get_max_duty() as u32;
+                (us as u32 * max / PERIOD_US as u32) as u16
+            }
+            None => 0,
+        };
+        self.pin.set_duty(duty);
+    }
+}
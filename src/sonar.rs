@@ -0,0 +1,103 @@
+//! HC-SR04 ultrasonic rangefinder on TIM1, for `robo_core::sonar`.
+//!
+//! The timer counts microseconds over a measurement period. Channel 3
+//! drives the trigger on PB6 with a PWM pulse at the start of every period,
+//! channels 1 and 2 both capture the echo on PA8, the rising and the
+//! falling edge. The update interrupt at the end of the period collects the
+//! captures, the firmware never waits for the echo. The sensor runs on 5 V:
+//! feed the echo through a divider, or rely on PA8 being 5 V tolerant.
+
+use hal::gpio::*;
+use hal::rcc::Rcc;
+use hal::stm32;
+use hal::timer::Timer;
+use robo_core::sonar::Capture;
+
+use crate::hal;
+
+/// Measurement period, longer than the 38 ms echo of an empty range.
+pub const PERIOD_US: u16 = 60_000;
+/// The sensor wants at least 10 µs.
+const TRIGGER_US: u16 = 12;
+
+/// CC1 on TI1 and CC2 on TI1 too, so both channels see the echo pin.
+const CC1S_TI1: u8 = 0b01;
+const CC2S_TI1: u8 = 0b10;
+/// Input filter of 4 samples at fCK_INT.
+const INPUT_FILTER: u8 = 0b0010;
+/// Output high while the counter is below CCR.
+const OCM_PWM_MODE_1: u8 = 0b110;
+
+pub struct Hcsr04 {
+    _timer: Timer<stm32::TIM1>,
+    _pins: (PA8<DefaultMode>, PB6<DefaultMode>),
+}
+
+impl Hcsr04 {
+    pub fn new(
+        mut timer: Timer<stm32::TIM1>,
+        pins: (PA8<DefaultMode>, PB6<DefaultMode>),
+        rcc: &Rcc,
+    ) -> Self {
+        let (echo, trigger) = pins;
+        echo.set_alt_mode(AltFunction::AF2);
+        trigger.set_alt_mode(AltFunction::AF1);
+
+        timer.pause();
+        let tim = unsafe { &*stm32::TIM1::ptr() };
+        let prescaler = rcc.clocks.apb_tim_clk.raw() / 1_000_000 - 1;
+        tim.psc.write(|w| unsafe { w.psc().bits(prescaler as u16) });
+        tim.arr.write(|w| unsafe { w.arr().bits(PERIOD_US - 1) });
+        tim.ccmr1_input().write(|w| unsafe {
+            w.cc1s()
+                .bits(CC1S_TI1)
+                .ic1f()
+                .bits(INPUT_FILTER)
+                .cc2s()
+                .bits(CC2S_TI1)
+        });
+        tim.ccmr2_output()
+            .write(|w| unsafe { w.oc3m().bits(OCM_PWM_MODE_1).oc3pe().set_bit() });
+        tim.ccr3.write(|w| unsafe { w.ccr3().bits(TRIGGER_US) });
+        tim.ccer.write(|w| {
+            w.cc1p()
+                .clear_bit()
+                .cc1e()
+                .set_bit()
+                .cc2p()
+                .set_bit()
+                .cc2e()
+                .set_bit()
+                .cc3e()
+                .set_bit()
+        });
+        tim.bdtr.modify(|_, w| w.moe().set_bit());
+        // Loads the prescaler and clears the flags it raises
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.sr.write(|w| unsafe { w.bits(0) });
+        tim.dier.modify(|_, w| w.uie().set_bit());
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        Self {
+            _timer: timer,
+            _pins: (echo, trigger),
+        }
+    }
+
+    /// Call from the `TIM1_BRK_UP_TRG_COM` interrupt, once per period.
+    pub fn on_update(&mut self) -> Capture {
+        let tim = unsafe { &*stm32::TIM1::ptr() };
+        let sr = tim.sr.read();
+        // Reading the captures clears their flags
+        let rise = tim.ccr1.read().ccr1().bits();
+        let fall = tim.ccr2.read().ccr2().bits();
+        tim.sr.write(|w| unsafe { w.bits(0) });
+        match (sr.cc1if().bit_is_set(), sr.cc2if().bit_is_set()) {
+            (true, true) if fall > rise => Capture::Echo((fall - rise) as u32),
+            (true, _) => Capture::Unfinished,
+            // An echo held from the previous period, the range is empty
+            (false, true) => Capture::Unfinished,
+            (false, false) => Capture::Missing,
+        }
+    }
+}
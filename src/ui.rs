@@ -0,0 +1,106 @@
+//! Widgets shared by the klaptik UIs: a status bar on the top page and the
+//! boot splash.
+//!
+//! Apps add `FONT_SPRITE`, `BLANK_SPRITE` and the numeric sheets they use to
+//! their sprites and keep their own sprite ids below `FONT`. Screens
+//! combined with the status bar leave the top page to it: render the bar
+//! after the screen, and invalidate it whenever the screen is drawn in full.
+
+use core::fmt::Write;
+
+use klaptik::*;
+use robo_core::screen::Splash;
+use robo_core::status::Status;
+
+/// Sprite ids of the shared sprites.
+pub const FONT: SpriteId = 0xf0;
+pub const BLANK: SpriteId = 0xf1;
+pub const NUMERIC_8X8: SpriteId = 0xf2;
+pub const NUMERIC_8X16: SpriteId = 0xf3;
+pub const NUMERIC_12X16: SpriteId = 0xf4;
+pub const NUMERIC_16X32: SpriteId = 0xf5;
+
+pub const FONT_ALPHABET: &[u8] = b" !%-./0123456789:<=>ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// 8x8 font, upper case only.
+pub const FONT_SPRITE: (FlashSprite, Glyphs) = (
+    FlashSprite::new(
+        FONT,
+        FONT_ALPHABET.len() as _,
+        Size::new(8, 8),
+        include_bytes!("../examples/assets/font8x8.bin"),
+    ),
+    Glyphs::Alphabet(FONT_ALPHABET),
+);
+
+/// Empty full screen background.
+pub const BLANK_SPRITE: (FlashSprite, Glyphs) = (
+    FlashSprite::new(BLANK, 1, Size::new(128, 64), &[0; 128 * 64 / 8]),
+    Glyphs::Sequential(1),
+);
+
+/// Characters of the numeric sheets, for labels filled with
+/// `robo_core::fixed::Fixed`. `^` is the degree sign.
+pub const NUMERIC_ALPHABET: &[u8] = b" -.:%0123456789ACVm^";
+
+const fn numeric(id: SpriteId, size: Size, bitmap: &'static [u8]) -> (FlashSprite, Glyphs) {
+    (
+        FlashSprite::new(id, NUMERIC_ALPHABET.len() as _, size, bitmap),
+        Glyphs::Alphabet(NUMERIC_ALPHABET),
+    )
+}
+
+/// Numbers with sign, decimal point and units in four sizes.
+pub const NUMERIC_8X8_SPRITE: (FlashSprite, Glyphs) = numeric(
+    NUMERIC_8X8,
+    Size::new(8, 8),
+    include_bytes!("../examples/assets/numeric8x8.bin"),
+);
+pub const NUMERIC_8X16_SPRITE: (FlashSprite, Glyphs) = numeric(
+    NUMERIC_8X16,
+    Size::new(8, 16),
+    include_bytes!("../examples/assets/numeric8x16.bin"),
+);
+pub const NUMERIC_12X16_SPRITE: (FlashSprite, Glyphs) = numeric(
+    NUMERIC_12X16,
+    Size::new(12, 16),
+    include_bytes!("../examples/assets/numeric12x16.bin"),
+);
+pub const NUMERIC_16X32_SPRITE: (FlashSprite, Glyphs) = numeric(
+    NUMERIC_16X32,
+    Size::new(16, 32),
+    include_bytes!("../examples/assets/numeric16x32.bin"),
+);
+
+/// Firmware version as shown in the status bar.
+pub const SHORT_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION_MAJOR"),
+    ".",
+    env!("CARGO_PKG_VERSION_MINOR")
+);
+
+widget_group! {
+    StatusBarUI<&Status>,
+    {
+        line: Label<16>, FONT, "                ", Point::zero(), Size::new(8, 8);
+    },
+    |widget: &mut StatusBarUI, status: &Status| {
+        status.write_line(16, &mut widget.line).ok();
+    }
+}
+
+widget_group! {
+    SplashUI<&Splash>,
+    {
+        bg: GlyphIcon, BLANK, 0, Point::zero();
+        name: Label<16>, FONT, "   ROBO RUST    ", Point::new(0, 8*2), Size::new(8, 8);
+        board: Label<16>, FONT, " NUCLEO-C031C6  ", Point::new(0, 8*3), Size::new(8, 8);
+        version: Label<16>, FONT, "                ", Point::new(0, 8*5), Size::new(8, 8);
+        progress: Label<16>, FONT, "                ", Point::new(0, 8*7), Size::new(8, 8);
+    },
+    |widget: &mut SplashUI, splash: &Splash| {
+        write!(widget.version, " FW {: <12}", env!("CARGO_PKG_VERSION")).ok();
+        let done = splash.progress(16);
+        write!(widget.progress, "{:=<done$}{: <rest$}", "", "", rest = 16 - done).ok();
+    }
+}
@@ -0,0 +1,77 @@
+#!/usr/bin/env python3
+"""Renders klaptik glyph sheets from embedded-graphics 1bpp raw fonts.
+
+The raw fonts live in the embedded-graphics sources under `fonts/raw/ascii`
+and hold 16 ASCII glyphs per row, starting at the space character. The
+`fonts/raw/iso_8859_1` variants continue with the Latin-1 characters from
+U+00A0, e.g. the degree sign.
+
+    tools/glyphs.py font_5x8.raw 5x8 8x8 " 0123456789" out.bin
+
+Glyphs are centered in the cell and optionally scaled up with `--scale`.
+The output is in SSD1306 page format, glyph after glyph, as expected by
+`FlashSprite`.
+"""
+
+import argparse
+
+GLYPHS_PER_ROW = 16
+
+
+def parse_size(text):
+    width, height = text.lower().split("x")
+    return int(width), int(height)
+
+
+def load_font(path, size):
+    width, height = size
+    data = open(path, "rb").read()
+    stride = (GLYPHS_PER_ROW * width + 7) // 8
+
+    def pixel(code, x, y):
+        index = code - 0x20 if code < 0x80 else code - 0xA0 + 0x60
+        px = (index % GLYPHS_PER_ROW) * width + x
+        py = (index // GLYPHS_PER_ROW) * height + y
+        byte = data[py * stride + px // 8]
+        return (byte >> (7 - px % 8)) & 1
+
+    return pixel
+
+
+def render(pixel, font_size, cell, char, scale):
+    font_w, font_h = font_size
+    cell_w, cell_h = cell
+    off_x = (cell_w - font_w * scale) // 2
+    off_y = (cell_h - font_h * scale) // 2
+    pages = bytearray(cell_w * cell_h // 8)
+    for y in range(cell_h):
+        for x in range(cell_w):
+            fx, fy = (x - off_x) // scale, (y - off_y) // scale
+            if x < off_x or y < off_y or fx >= font_w or fy >= font_h:
+                continue
+            if pixel(ord(char), fx, fy):
+                pages[(y // 8) * cell_w + x] |= 1 << (y % 8)
+    return pages
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("font", help="embedded-graphics raw font")
+    parser.add_argument("font_size", type=parse_size, help="font glyph size, e.g. 5x8")
+    parser.add_argument("cell", type=parse_size, help="sprite glyph size, e.g. 8x8")
+    parser.add_argument("alphabet", help="characters to render, in sprite order")
+    parser.add_argument("output", help="sprite file to write")
+    parser.add_argument("--scale", type=int, default=1, help="integer upscale factor")
+    args = parser.parse_args()
+
+    if args.cell[1] % 8:
+        parser.error("cell height must be a multiple of 8")
+
+    pixel = load_font(args.font, args.font_size)
+    with open(args.output, "wb") as out:
+        for char in args.alphabet:
+            out.write(render(pixel, args.font_size, args.cell, char, args.scale))
+
+
+if __name__ == "__main__":
+    main()